@@ -0,0 +1,94 @@
+use std::{error, fmt};
+
+// Mesen prefixes the log with a signature and the CRC32 of the ROM, FCEUX doesn't
+const MESEN_HEADERS: [&[u8]; 2] = [b"CDLv2", b"CDL\x01"];
+const MESEN_CRC_SIZE: usize = 4;
+const TILE_SIZE_IN_BYTES: usize = 16;
+
+pub const PRG_CODE: u8 = 0x01;
+pub const PRG_DATA: u8 = 0x02;
+pub const CHR_DRAWN: u8 = 0x01;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidCodeDataLogError;
+
+impl fmt::Display for InvalidCodeDataLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The CDL file doesn't match the size of the ROM")
+    }
+}
+
+impl error::Error for InvalidCodeDataLogError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Emulator record of how every byte of PRG and CHR ROM was accessed.
+#[derive(PartialEq, Debug, Clone)]
+pub struct CodeDataLog {
+    pub prg: Vec<u8>,
+    pub chr: Vec<u8>,
+}
+
+impl CodeDataLog {
+    /// Reads FCEUX and Mesen logs. Logs of games with CHR RAM may leave out the CHR part.
+    pub fn parse(
+        bytes: &[u8],
+        prg_size: usize,
+        chr_size: usize,
+    ) -> Result<CodeDataLog, InvalidCodeDataLogError> {
+        let flags = MESEN_HEADERS
+            .iter()
+            .find(|header| bytes.starts_with(header))
+            .map_or(Some(bytes), |header| {
+                bytes.get(header.len() + MESEN_CRC_SIZE..)
+            })
+            .ok_or(InvalidCodeDataLogError)?;
+        if flags.len() != prg_size + chr_size && flags.len() != prg_size {
+            return Err(InvalidCodeDataLogError);
+        }
+        let (prg, chr) = flags.split_at(prg_size);
+        Ok(CodeDataLog {
+            prg: prg.to_vec(),
+            chr: chr.to_vec(),
+        })
+    }
+
+    /// For every tile of CHR ROM, whether the PPU drew any part of it.
+    pub fn drawn_tiles(&self) -> Vec<bool> {
+        self.chr
+            .chunks(TILE_SIZE_IN_BYTES)
+            .map(|tile| tile.iter().any(|flags| flags & CHR_DRAWN != 0))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fceux_log() {
+        let mut bytes = vec![PRG_CODE, PRG_DATA, 0x00, 0x00];
+        bytes.extend_from_slice(&[0x00; 16]);
+        bytes.extend_from_slice(&[CHR_DRAWN; 16]);
+        let log = CodeDataLog::parse(&bytes, 4, 32).unwrap();
+        assert_eq!(log.prg, vec![PRG_CODE, PRG_DATA, 0x00, 0x00]);
+        assert_eq!(log.drawn_tiles(), vec![false, true]);
+    }
+
+    #[test]
+    fn parse_mesen_log() {
+        let mut bytes = b"CDLv2".to_vec();
+        bytes.extend_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        bytes.extend_from_slice(&[PRG_CODE; 4]);
+        let log = CodeDataLog::parse(&bytes, 4, 0x2000).unwrap();
+        assert_eq!(log.prg, vec![PRG_CODE; 4]);
+        assert!(log.chr.is_empty());
+        assert_eq!(
+            CodeDataLog::parse(&bytes[..8], 4, 0),
+            Err(InvalidCodeDataLogError)
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+
+use super::data::{Tile, BITS_PER_PIXEL, TILE_HEIGHT_IN_PIXELS, TILE_WIDTH_IN_PIXELS};
+
+/// How the pixels of 8x8 tiles are stored as bitplanes.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TileCodec {
+    /// Two planes of 8 bytes one after the other.
+    Nes2bpp,
+    /// A single plane.
+    Planar1bpp,
+    /// Two planes interleaved row by row.
+    GameBoy2bpp,
+    /// Planes 0 and 1 interleaved as on the Game Boy, followed by planes 2 and 3.
+    Snes4bpp,
+}
+
+impl fmt::Display for TileCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TileCodec::Nes2bpp => write!(f, "NES 2bpp"),
+            TileCodec::Planar1bpp => write!(f, "1bpp"),
+            TileCodec::GameBoy2bpp => write!(f, "Game Boy 2bpp"),
+            TileCodec::Snes4bpp => write!(f, "SNES 4bpp"),
+        }
+    }
+}
+
+impl TileCodec {
+    pub const ALL: [TileCodec; 4] = [
+        TileCodec::Nes2bpp,
+        TileCodec::Planar1bpp,
+        TileCodec::GameBoy2bpp,
+        TileCodec::Snes4bpp,
+    ];
+
+    pub fn bits_per_pixel(self) -> usize {
+        match self {
+            TileCodec::Planar1bpp => 1,
+            TileCodec::Nes2bpp | TileCodec::GameBoy2bpp => 2,
+            TileCodec::Snes4bpp => 4,
+        }
+    }
+
+    /// Bytes a tile takes.
+    pub fn tile_size(self) -> usize {
+        TILE_HEIGHT_IN_PIXELS * self.bits_per_pixel()
+    }
+
+    /// Offset in the tile of the byte holding plane `plane` of row `row`.
+    fn plane_offset(self, row: usize, plane: usize) -> usize {
+        match self {
+            TileCodec::Nes2bpp => plane * TILE_HEIGHT_IN_PIXELS + row,
+            TileCodec::Planar1bpp => row,
+            TileCodec::GameBoy2bpp => row * 2 + plane,
+            TileCodec::Snes4bpp => plane / 2 * TILE_HEIGHT_IN_PIXELS * 2 + row * 2 + plane % 2,
+        }
+    }
+
+    /// Decodes a tile from the start of `bytes`, missing bytes reading as 0.
+    pub fn decode(self, bytes: &[u8]) -> Tile {
+        let mut tile: Tile = [0; TILE_HEIGHT_IN_PIXELS];
+        for (row, pattern) in tile.iter_mut().enumerate() {
+            for plane in 0..self.bits_per_pixel() {
+                let byte = bytes
+                    .get(self.plane_offset(row, plane))
+                    .copied()
+                    .unwrap_or(0);
+                for x in 0..TILE_WIDTH_IN_PIXELS {
+                    let bit = (byte >> (TILE_WIDTH_IN_PIXELS - 1 - x)) & 1;
+                    *pattern |=
+                        (bit as u32) << ((TILE_WIDTH_IN_PIXELS - 1 - x) * BITS_PER_PIXEL + plane);
+                }
+            }
+        }
+        tile
+    }
+
+    /// Encodes `tile`, dropping the bits of pixels that don't fit.
+    pub fn encode(self, tile: &Tile) -> Vec<u8> {
+        let mut bytes = vec![0u8; self.tile_size()];
+        for (row, pattern) in tile.iter().enumerate() {
+            for plane in 0..self.bits_per_pixel() {
+                let byte = (0..TILE_WIDTH_IN_PIXELS).fold(0u8, |byte, x| {
+                    let shift = (TILE_WIDTH_IN_PIXELS - 1 - x) * BITS_PER_PIXEL + plane;
+                    byte | (((pattern >> shift) & 1) as u8) << (TILE_WIDTH_IN_PIXELS - 1 - x)
+                });
+                bytes[self.plane_offset(row, plane)] = byte;
+            }
+        }
+        bytes
+    }
+
+    /// Every whole tile of `bytes`.
+    pub fn decode_tiles(self, bytes: &[u8]) -> Vec<Tile> {
+        bytes
+            .chunks_exact(self.tile_size())
+            .map(|tile| self.decode(tile))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chr::data::tile_pixel;
+
+    #[test]
+    fn decode_formats() {
+        let bytes = (0..32).map(|i| (i * 37 + 5) as u8).collect::<Vec<u8>>();
+        for codec in TileCodec::ALL {
+            let tile = codec.decode(&bytes);
+            assert_eq!(codec.encode(&tile), bytes[..codec.tile_size()]);
+        }
+
+        // Pixel 0 of row 1 has its plane 1 bit set only.
+        let mut nes = [0u8; 16];
+        nes[9] = 0x80;
+        assert_eq!(tile_pixel(&TileCodec::Nes2bpp.decode(&nes), 0, 1), 2);
+        let mut game_boy = [0u8; 16];
+        game_boy[3] = 0x80;
+        assert_eq!(
+            tile_pixel(&TileCodec::GameBoy2bpp.decode(&game_boy), 0, 1),
+            2
+        );
+        let mut snes = [0u8; 32];
+        snes[3] = 0x80;
+        snes[19] = 0x80;
+        assert_eq!(tile_pixel(&TileCodec::Snes4bpp.decode(&snes), 0, 1), 0b1010);
+        assert_eq!(tile_pixel(&TileCodec::Planar1bpp.decode(&[0, 1]), 7, 1), 1);
+        assert!(TileCodec::Snes4bpp.decode_tiles(&bytes[..31]).is_empty());
+    }
+}
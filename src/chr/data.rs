@@ -1,23 +1,25 @@
-use std::{error, fmt};
+use std::{error, fmt, ops::Range};
 
 use wasm_bindgen::Clamped;
 use web_sys::ImageData;
 
+use super::{
+    super::export::png::{decode, InvalidPngError},
+    codec::TileCodec,
+};
+
 const CHR_BANK_SIZE: usize = 0x2000;
 const PATTERN_TABLES_PER_BANK: usize = 2;
 const PATTERN_TABLE_SIZE_IN_BYTES: usize = 0x1000;
-const TILES_PER_PATTERN_TABLE: usize = 256;
+pub const TILES_PER_PATTERN_TABLE: usize = 256;
 const TILE_SIZE_IN_BYTES: usize = 16;
 const TILE_PATTERN_ROWS: usize = 8;
 
 pub const TILES_PER_ROW: usize = 16;
 pub const TILE_WIDTH_IN_PIXELS: usize = 8;
-pub const TILE_ROWS: usize = 16;
 pub const TILE_HEIGHT_IN_PIXELS: usize = 8;
-pub const BITS_PER_PIXEL: usize = 2;
-
-pub const TILE_PATTERN_WIDTH_IN_PIXELS: usize = TILES_PER_ROW * TILE_WIDTH_IN_PIXELS;
-pub const TILE_PATTERN_HEIGHT_IN_PIXELS: usize = TILE_ROWS * TILE_HEIGHT_IN_PIXELS;
+/// Bits a pixel takes in a `Tile` row, enough for any codec.
+pub const BITS_PER_PIXEL: usize = 4;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct InvalidChrDataError;
@@ -34,57 +36,287 @@ impl error::Error for InvalidChrDataError {
     }
 }
 
-pub type Tile = [u16; TILE_PATTERN_ROWS];
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportImageError {
+    Png(InvalidPngError),
+    Size {
+        width: usize,
+        height: usize,
+        expected_width: usize,
+        expected_height: usize,
+    },
+}
+
+impl fmt::Display for ImportImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportImageError::Png(e) => e.fmt(f),
+            ImportImageError::Size {
+                width,
+                height,
+                expected_width,
+                expected_height,
+            } => write!(
+                f,
+                "The image is {}x{}, the tiles need at least {}x{}",
+                width, height, expected_width, expected_height
+            ),
+        }
+    }
+}
+
+impl error::Error for ImportImageError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+pub type Tile = [u32; TILE_PATTERN_ROWS];
+
+/// Palette index of the pixel at `x`, `y` of `tile`.
+pub fn tile_pixel(tile: &Tile, x: usize, y: usize) -> u8 {
+    ((tile[y] >> (((TILE_WIDTH_IN_PIXELS - 1) - x) * BITS_PER_PIXEL)) & 0xF) as u8
+}
+
+/// `tile` with the pixel at `x`, `y` set to palette index `value`.
+pub fn with_tile_pixel(tile: Tile, x: usize, y: usize, value: u8) -> Tile {
+    let shift = ((TILE_WIDTH_IN_PIXELS - 1) - x) * BITS_PER_PIXEL;
+    let mut tile = tile;
+    tile[y] = (tile[y] & !(0xF << shift)) | ((value as u32 & 0xF) << shift);
+    tile
+}
+
+/// Builds a tile taking each pixel from the pixel of `tile` that `source` maps it to.
+fn remap_tile(tile: &Tile, source: impl Fn(usize, usize) -> (usize, usize)) -> Tile {
+    let mut remapped: Tile = [0; TILE_PATTERN_ROWS];
+    for y in 0..TILE_HEIGHT_IN_PIXELS {
+        for x in 0..TILE_WIDTH_IN_PIXELS {
+            let (source_x, source_y) = source(x, y);
+            remapped = with_tile_pixel(remapped, x, y, tile_pixel(tile, source_x, source_y));
+        }
+    }
+    remapped
+}
+
+pub fn flip_tile_horizontally(tile: &Tile) -> Tile {
+    remap_tile(tile, |x, y| (TILE_WIDTH_IN_PIXELS - 1 - x, y))
+}
+
+pub fn flip_tile_vertically(tile: &Tile) -> Tile {
+    remap_tile(tile, |x, y| (x, TILE_HEIGHT_IN_PIXELS - 1 - y))
+}
+
+/// Rotates `tile` a quarter turn clockwise.
+pub fn rotate_tile(tile: &Tile) -> Tile {
+    remap_tile(tile, |x, y| (y, TILE_HEIGHT_IN_PIXELS - 1 - x))
+}
 
 #[derive(PartialEq, Clone, Debug, Copy)]
 pub struct PatternTable {
     tiles: [Tile; TILES_PER_PATTERN_TABLE],
 }
 
-impl PatternTable {
-    pub fn to_rgba_pixels(self, palette: Vec<u32>) -> Vec<u8> {
-        const RGBA_COLOR_DEPTH_IN_BYTES: usize = 4;
-        const BUFFER_SIZE: usize = TILE_PATTERN_WIDTH_IN_PIXELS
-            * TILE_PATTERN_HEIGHT_IN_PIXELS
-            * RGBA_COLOR_DEPTH_IN_BYTES;
+/// How the tiles of a CHR bank are laid out in the viewer.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Arrangement {
+    /// Rows of 16 tiles, one pattern table above the other.
+    Tiles8x8,
+    /// Tiles paired top and bottom, as sprites use them in 8x16 mode.
+    Sprites8x16,
+    /// Rows of the given number of tiles.
+    Columns(usize),
+}
 
-        let mut buffer = vec![0u8; BUFFER_SIZE];
+impl Arrangement {
+    /// Tile column and row of the `index`th tile.
+    pub fn position(self, index: usize) -> (usize, usize) {
+        match self {
+            Arrangement::Tiles8x8 => (index % TILES_PER_ROW, index / TILES_PER_ROW),
+            Arrangement::Sprites8x16 => {
+                let pair = index / 2;
+                (pair % TILES_PER_ROW, pair / TILES_PER_ROW * 2 + index % 2)
+            }
+            Arrangement::Columns(columns) => (index % columns.max(1), index / columns.max(1)),
+        }
+    }
 
-        for (index, tile) in self.tiles.iter().enumerate() {
-            let column_index = index % TILES_PER_ROW;
-            let row_index = index / TILES_PER_ROW;
-            let start_position_x = column_index * TILE_WIDTH_IN_PIXELS;
-            let start_position_y = row_index * TILE_HEIGHT_IN_PIXELS;
+    /// Which of `count` tiles sits at tile column `column` and row `row`.
+    pub fn index_at(self, column: usize, row: usize, count: usize) -> Option<usize> {
+        (0..count).find(|index| self.position(*index) == (column, row))
+    }
 
-            for (current_row_index, tile_row) in tile.iter().enumerate().take(TILE_HEIGHT_IN_PIXELS)
-            {
-                for current_column_index in 0..TILE_WIDTH_IN_PIXELS {
-                    let pixel = (tile_row
-                        >> (((TILE_WIDTH_IN_PIXELS - 1) - current_column_index) * BITS_PER_PIXEL))
-                        & 3;
-                    let position_x = start_position_x + current_column_index;
-                    let position_y = start_position_y + current_row_index;
-                    let buffer_index = (position_y * TILE_PATTERN_WIDTH_IN_PIXELS + position_x)
-                        * RGBA_COLOR_DEPTH_IN_BYTES;
-                    let color = palette[pixel as usize];
-                    buffer[buffer_index] = ((color >> 24) & 0xFF) as u8;
-                    buffer[buffer_index + 1] = ((color >> 16) & 0xFF) as u8;
-                    buffer[buffer_index + 2] = ((color >> 8) & 0xFF) as u8;
-                    buffer[buffer_index + 3] = (color & 0xFF) as u8;
+    /// Width and height in tiles of `count` tiles.
+    pub fn size(self, count: usize) -> (usize, usize) {
+        (0..count)
+            .map(|index| self.position(index))
+            .fold((0, 0), |(width, height), (column, row)| {
+                (width.max(column + 1), height.max(row + 1))
+            })
+    }
+}
+
+/// Palette index of each pixel of `tiles` laid out by `arrangement`, with their width and
+/// height.
+pub fn index_tiles(tiles: &[Tile], arrangement: Arrangement) -> (usize, usize, Vec<u8>) {
+    let (columns, rows) = arrangement.size(tiles.len());
+    let width = columns * TILE_WIDTH_IN_PIXELS;
+    let height = rows * TILE_HEIGHT_IN_PIXELS;
+    let mut indices = vec![0u8; width * height];
+
+    for (index, tile) in tiles.iter().enumerate() {
+        let (column, row) = arrangement.position(index);
+        for (current_row_index, tile_row) in tile.iter().enumerate().take(TILE_HEIGHT_IN_PIXELS) {
+            for current_column_index in 0..TILE_WIDTH_IN_PIXELS {
+                let pixel = (tile_row
+                    >> (((TILE_WIDTH_IN_PIXELS - 1) - current_column_index) * BITS_PER_PIXEL))
+                    & 0xF;
+                let position_x = column * TILE_WIDTH_IN_PIXELS + current_column_index;
+                let position_y = row * TILE_HEIGHT_IN_PIXELS + current_row_index;
+                indices[position_y * width + position_x] = pixel as u8;
+            }
+        }
+    }
+    (width, height, indices)
+}
+
+/// RGBA pixels of `tiles` laid out by `arrangement`, with their width and height. Tiles whose
+/// `drawn_tiles` flag is false are darkened.
+pub fn render_tiles(
+    tiles: &[Tile],
+    arrangement: Arrangement,
+    palette: &[u32],
+    drawn_tiles: Option<&[bool]>,
+) -> (usize, usize, Vec<u8>) {
+    const RGBA_COLOR_DEPTH_IN_BYTES: usize = 4;
+    const DIMMING_SHIFT: u8 = 2;
+    let (width, height, indices) = index_tiles(tiles, arrangement);
+    let mut buffer = indices
+        .iter()
+        .flat_map(|index| palette[*index as usize].to_be_bytes())
+        .collect::<Vec<u8>>();
+
+    let undrawn = drawn_tiles
+        .into_iter()
+        .flat_map(|drawn| drawn.iter().take(tiles.len()).enumerate())
+        .filter(|(_, drawn)| !**drawn);
+    for (index, _) in undrawn {
+        let (column, row) = arrangement.position(index);
+        for position_y in row * TILE_HEIGHT_IN_PIXELS..(row + 1) * TILE_HEIGHT_IN_PIXELS {
+            let row_start =
+                (position_y * width + column * TILE_WIDTH_IN_PIXELS) * RGBA_COLOR_DEPTH_IN_BYTES;
+            let row = &mut buffer
+                [row_start..row_start + TILE_WIDTH_IN_PIXELS * RGBA_COLOR_DEPTH_IN_BYTES];
+            for pixel in row.chunks_mut(RGBA_COLOR_DEPTH_IN_BYTES) {
+                for channel in pixel.iter_mut().take(3) {
+                    *channel >>= DIMMING_SHIFT;
                 }
             }
         }
-        buffer
     }
+    (width, height, buffer)
+}
+
+/// Index of the `palette` color nearest each pixel, fully transparent pixels taking index 0,
+/// with the number of pixels that didn't match a color exactly.
+pub fn map_colors(pixels: &[u32], palette: &[u32]) -> (Vec<u8>, usize) {
+    let channels = |color: u32| [color >> 24, (color >> 16) & 0xFF, (color >> 8) & 0xFF];
+    let mut approximated = 0;
+    let indices = pixels
+        .iter()
+        .map(|&pixel| {
+            if pixel & 0xFF == 0 {
+                return 0;
+            }
+            let (index, distance) = palette
+                .iter()
+                .enumerate()
+                .map(|(index, &color)| {
+                    let distance = channels(pixel)
+                        .iter()
+                        .zip(channels(color))
+                        .map(|(a, b)| a.abs_diff(b).pow(2))
+                        .sum::<u32>();
+                    (index, distance)
+                })
+                .min_by_key(|(_, distance)| *distance)
+                .unwrap_or((0, 0));
+            if distance != 0 {
+                approximated += 1;
+            }
+            index as u8
+        })
+        .collect();
+    (indices, approximated)
+}
 
-    pub fn to_image_data(self, palette: Vec<u32>) -> ImageData {
-        let rgba_buffer = self.to_rgba_pixels(palette);
-        let clamped_buffer = Clamped(rgba_buffer.as_slice());
-        let width = TILE_PATTERN_WIDTH_IN_PIXELS as u32;
-        let height = TILE_PATTERN_HEIGHT_IN_PIXELS as u32;
-        ImageData::new_with_u8_clamped_array_and_sh(clamped_buffer, width, height)
-            .expect("Couldn't create ImageData")
+/// `count` tiles read from an image of palette indices laid out by `arrangement`, the inverse
+/// of `index_tiles`.
+pub fn tiles_from_indices(
+    width: usize,
+    height: usize,
+    indices: &[u8],
+    arrangement: Arrangement,
+    count: usize,
+) -> Result<Vec<Tile>, ImportImageError> {
+    let (columns, rows) = arrangement.size(count);
+    let (expected_width, expected_height) =
+        (columns * TILE_WIDTH_IN_PIXELS, rows * TILE_HEIGHT_IN_PIXELS);
+    if width < expected_width || height < expected_height || indices.len() < width * height {
+        return Err(ImportImageError::Size {
+            width,
+            height,
+            expected_width,
+            expected_height,
+        });
     }
+    let tiles = (0..count)
+        .map(|index| {
+            let (column, row) = arrangement.position(index);
+            let mut tile: Tile = [0; TILE_PATTERN_ROWS];
+            for (current_row_index, tile_row) in tile.iter_mut().enumerate() {
+                let position_y = row * TILE_HEIGHT_IN_PIXELS + current_row_index;
+                for current_column_index in 0..TILE_WIDTH_IN_PIXELS {
+                    let position_x = column * TILE_WIDTH_IN_PIXELS + current_column_index;
+                    let pixel = (indices[position_y * width + position_x] & 0xF) as u32;
+                    *tile_row |= pixel
+                        << (((TILE_WIDTH_IN_PIXELS - 1) - current_column_index) * BITS_PER_PIXEL);
+                }
+            }
+            tile
+        })
+        .collect();
+    Ok(tiles)
+}
+
+/// `count` tiles of a PNG drawn with the `palette` colors, with the number of pixels that
+/// took the nearest color.
+pub fn import_png(
+    png: &[u8],
+    arrangement: Arrangement,
+    palette: &[u32],
+    count: usize,
+) -> Result<(Vec<Tile>, usize), ImportImageError> {
+    let (width, height, pixels) = decode(png).map_err(ImportImageError::Png)?;
+    let (indices, approximated) = map_colors(&pixels, palette);
+    let tiles = tiles_from_indices(width, height, &indices, arrangement, count)?;
+    Ok((tiles, approximated))
+}
+
+/// Tiles of page `page` of `bytes` as `codec` stores them, pages being the size of a CHR bank
+/// from `offset` on.
+pub fn page_tiles(bytes: &[u8], offset: usize, page: usize, codec: TileCodec) -> Vec<Tile> {
+    let start = (offset + page * CHR_BANK_SIZE).min(bytes.len());
+    let end = (start + CHR_BANK_SIZE).min(bytes.len());
+    codec.decode_tiles(&bytes[start..end])
+}
+
+pub fn page_count(len: usize, offset: usize) -> usize {
+    len.saturating_sub(offset).div_ceil(CHR_BANK_SIZE)
+}
+
+pub fn to_image_data(width: usize, height: usize, buffer: &[u8]) -> ImageData {
+    ImageData::new_with_u8_clamped_array_and_sh(Clamped(buffer), width as u32, height as u32)
+        .expect("Couldn't create ImageData")
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -94,7 +326,7 @@ pub struct ChrData {
 
 impl ChrData {
     pub fn parse(chr_data: Vec<u8>) -> Result<ChrData, InvalidChrDataError> {
-        if chr_data.len() % CHR_BANK_SIZE != 0 {
+        if !chr_data.len().is_multiple_of(CHR_BANK_SIZE) {
             return Err(InvalidChrDataError);
         }
         let chr_rom_banks = chr_data.len() / CHR_BANK_SIZE;
@@ -114,12 +346,61 @@ impl ChrData {
         })
     }
 
-    fn interleave_pattern_bytes(lsb: u8, msb: u8) -> u16 {
-        let mut pattern = (lsb as u16) | (msb as u16) << 8;
-        pattern = (pattern & 0xF00F) | ((pattern & 0x0F00) >> 4) | ((pattern & 0x00F0) << 4);
-        pattern = (pattern & 0xC3C3) | ((pattern & 0x3030) >> 2) | ((pattern & 0x0C0C) << 2);
-        pattern = (pattern & 0x9999) | ((pattern & 0x4444) >> 1) | ((pattern & 0x2222) << 1);
-        pattern
+    pub fn bank_count(&self) -> usize {
+        self.pattern_tables.len() / PATTERN_TABLES_PER_BANK
+    }
+
+    /// Tiles of the pattern tables in `tables`.
+    pub fn tiles(&self, tables: Range<usize>) -> Vec<Tile> {
+        self.pattern_tables
+            .get(
+                tables.start.min(self.pattern_tables.len())
+                    ..tables.end.min(self.pattern_tables.len()),
+            )
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|pattern_table| pattern_table.tiles)
+            .collect()
+    }
+
+    /// Tiles of both pattern tables of CHR bank `bank`.
+    pub fn bank_tiles(&self, bank: usize) -> Vec<Tile> {
+        self.tiles(bank * PATTERN_TABLES_PER_BANK..(bank + 1) * PATTERN_TABLES_PER_BANK)
+    }
+
+    pub fn set_tile(&mut self, table: usize, index: usize, tile: Tile) {
+        if let Some(current) = self
+            .pattern_tables
+            .get_mut(table)
+            .and_then(|pattern_table| pattern_table.tiles.get_mut(index))
+        {
+            *current = tile;
+        }
+    }
+
+    /// Overwrites the pattern tables in `tables` with `tiles`.
+    pub fn replace_tiles(&mut self, tables: Range<usize>, tiles: &[Tile]) {
+        let pattern_tables = self.pattern_tables.iter_mut().skip(tables.start);
+        for (pattern_table, tiles) in pattern_tables
+            .take(tables.len())
+            .zip(tiles.chunks(TILES_PER_PATTERN_TABLE))
+        {
+            pattern_table.tiles[..tiles.len()].copy_from_slice(tiles);
+        }
+    }
+
+    /// Encodes the pattern tables back into CHR ROM bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; self.pattern_tables.len() * PATTERN_TABLE_SIZE_IN_BYTES];
+        for (pattern_table_id, pattern_table) in self.pattern_tables.iter().enumerate() {
+            for (tile_number, tile) in pattern_table.tiles.iter().enumerate() {
+                let offset = PATTERN_TABLE_SIZE_IN_BYTES * pattern_table_id
+                    + tile_number * TILE_SIZE_IN_BYTES;
+                bytes[offset..offset + TILE_SIZE_IN_BYTES]
+                    .copy_from_slice(&TileCodec::Nes2bpp.encode(tile));
+            }
+        }
+        bytes
     }
 
     fn get_tiles_from_pattern_table(
@@ -131,13 +412,7 @@ impl ChrData {
         for (tile_number, tile) in tiles.iter_mut().enumerate().take(TILES_PER_PATTERN_TABLE) {
             let offset =
                 PATTERN_TABLE_SIZE_IN_BYTES * pattern_table_id + tile_number * TILE_SIZE_IN_BYTES;
-            let mut tile_pattern: [u16; TILE_PATTERN_ROWS] = [0; TILE_PATTERN_ROWS];
-            for row in 0..TILE_PATTERN_ROWS {
-                let tile_lsb = chr_data[offset + row];
-                let tile_msb = chr_data[offset + row + TILE_PATTERN_ROWS];
-                tile_pattern[row] = ChrData::interleave_pattern_bytes(tile_lsb, tile_msb);
-            }
-            *tile = tile_pattern;
+            *tile = TileCodec::Nes2bpp.decode(&chr_data[offset..offset + TILE_SIZE_IN_BYTES]);
         }
         tiles
     }
@@ -169,7 +444,8 @@ mod tests {
         }
 
         let expected_tile: Tile = [
-            0x1003, 0x500C, 0x1030, 0x10C0, 0x0328, 0x0C02, 0x3008, 0xC02A,
+            0x01000003, 0x11000030, 0x01000300, 0x01003000, 0x00030220, 0x00300002, 0x03000020,
+            0x30000222,
         ];
         let result = ChrData::parse(valid_chr_data);
         assert!(result.is_ok());
@@ -177,4 +453,136 @@ mod tests {
         let parsed_tile = parsed_chr_data.pattern_tables[0].tiles[0];
         assert_eq!(parsed_tile, expected_tile);
     }
+
+    #[test]
+    fn arrange_sprites() {
+        assert_eq!(Arrangement::Tiles8x8.size(512), (16, 32));
+        assert_eq!(Arrangement::Sprites8x16.size(512), (16, 32));
+        assert_eq!(Arrangement::Sprites8x16.position(1), (0, 1));
+        assert_eq!(Arrangement::Sprites8x16.position(34), (1, 2));
+        assert_eq!(Arrangement::Columns(4).size(10), (4, 3));
+
+        let mut chr_data = vec![0u8; 0x2000];
+        // Tile 1 is filled with color 1.
+        chr_data[0x10..0x18].fill(0xFF);
+        let chr_data = ChrData::parse(chr_data).unwrap();
+        let tiles = chr_data.bank_tiles(0);
+        assert_eq!(tiles.len(), 512);
+        let palette = [0x000000FF, 0xFFFFFFFF, 0, 0];
+        let (width, height, pixels) =
+            render_tiles(&tiles, Arrangement::Sprites8x16, &palette, None);
+        assert_eq!((width, height), (128, 256));
+        let pixel = |x: usize, y: usize| pixels[(y * width + x) * 4];
+        assert_eq!(pixel(0, 8), 0xFF);
+        assert_eq!(pixel(8, 0), 0x00);
+
+        let drawn = vec![false; 512];
+        let (_, _, pixels) = render_tiles(&tiles, Arrangement::Tiles8x8, &palette, Some(&drawn));
+        assert_eq!(pixels[8 * 4..8 * 4 + 4], [0x3F, 0x3F, 0x3F, 0xFF]);
+    }
+
+    #[test]
+    fn import_png_tiles() {
+        use crate::export::png::encode_rgba;
+
+        let chr_data: Vec<u8> = (0..0x2000).map(|i| (i * 7 + i / 13) as u8).collect();
+        let original = ChrData::parse(chr_data.clone()).unwrap();
+        let palette = [0x000000FF, 0x555555FF, 0xAAAAAAFF, 0xFFFFFFFF];
+        let tiles = original.tiles(1..2);
+        let (width, height, mut pixels) =
+            render_tiles(&tiles, Arrangement::Sprites8x16, &palette, None);
+        // An off-palette pixel and a transparent one.
+        pixels[..4].copy_from_slice(&[0x50, 0x50, 0x58, 0xFF]);
+        pixels[4..8].copy_from_slice(&[0xFF, 0xFF, 0xFF, 0x00]);
+        let png = encode_rgba(width, height, &pixels);
+
+        let (imported, approximated) = import_png(
+            &png,
+            Arrangement::Sprites8x16,
+            &palette,
+            TILES_PER_PATTERN_TABLE,
+        )
+        .unwrap();
+        assert_eq!(approximated, 1);
+        assert_eq!(imported[1..], tiles[1..]);
+        assert_eq!(imported[0][0] >> 24, 0x10);
+        assert_eq!(imported[0][1..], tiles[0][1..]);
+
+        let mut replaced = original.clone();
+        replaced.replace_tiles(0..1, &tiles);
+        assert_eq!(replaced.pattern_tables[0], original.pattern_tables[1]);
+        assert_eq!(replaced.to_bytes()[..0x1000], chr_data[0x1000..]);
+        assert_eq!(
+            import_png(&png, Arrangement::Tiles8x8, &palette, 512),
+            Err(ImportImageError::Size {
+                width: 128,
+                height: 128,
+                expected_width: 128,
+                expected_height: 256,
+            })
+        );
+    }
+
+    #[test]
+    fn edit_tiles() {
+        // A diagonal of color 1 with color 2 in the top right corner.
+        let mut tile: Tile = [0; TILE_PATTERN_ROWS];
+        for position in 0..8 {
+            tile = with_tile_pixel(tile, position, position, 1);
+        }
+        tile = with_tile_pixel(tile, 7, 0, 2);
+        assert_eq!(tile[0], 0x10000002);
+        assert_eq!(tile_pixel(&tile, 7, 0), 2);
+
+        let flipped = flip_tile_horizontally(&tile);
+        assert_eq!(
+            (tile_pixel(&flipped, 0, 0), tile_pixel(&flipped, 7, 0)),
+            (2, 1)
+        );
+        let flipped = flip_tile_vertically(&tile);
+        assert_eq!(
+            (tile_pixel(&flipped, 7, 7), tile_pixel(&flipped, 0, 7)),
+            (2, 1)
+        );
+        let rotated = rotate_tile(&tile);
+        assert_eq!(tile_pixel(&rotated, 7, 7), 2);
+        assert_eq!(tile_pixel(&rotated, 7, 0), 1);
+        assert_eq!(
+            rotate_tile(&rotate_tile(&rotated)),
+            flip_tile_horizontally(&tile)
+        );
+
+        let mut chr_data = ChrData::parse(vec![0; 0x2000]).unwrap();
+        chr_data.set_tile(1, 2, tile);
+        assert_eq!(chr_data.tiles(1..2)[2], tile);
+        let bytes = chr_data.to_bytes();
+        // Low plane of the first row, then its high plane.
+        assert_eq!((bytes[0x1020], bytes[0x1028]), (0x80, 0x01));
+        assert_eq!(Arrangement::Sprites8x16.index_at(1, 1, 512), Some(3));
+    }
+
+    #[test]
+    fn decode_pages() {
+        let bytes: Vec<u8> = (0..0x3010).map(|i| (i * 3) as u8).collect();
+        assert_eq!(page_count(bytes.len(), 0x10), 2);
+        assert_eq!(page_tiles(&bytes, 0x10, 0, TileCodec::Snes4bpp).len(), 256);
+        assert_eq!(
+            page_tiles(&bytes, 0x10, 1, TileCodec::Planar1bpp).len(),
+            512
+        );
+        let tiles = page_tiles(&bytes, 0, 0, TileCodec::Nes2bpp);
+        assert_eq!(
+            tiles,
+            ChrData::parse(bytes[..0x2000].to_vec())
+                .unwrap()
+                .bank_tiles(0)
+        );
+    }
+
+    #[test]
+    fn chr_data_to_bytes() {
+        let chr_data: Vec<u8> = (0..0x2000).map(|i| (i * 7 + i / 13) as u8).collect();
+        let parsed_chr_data = ChrData::parse(chr_data.clone()).unwrap();
+        assert_eq!(parsed_chr_data.to_bytes(), chr_data);
+    }
 }
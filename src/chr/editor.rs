@@ -0,0 +1,88 @@
+use yew::prelude::*;
+
+use super::{
+    super::ui::{button::Button, r#box::Box},
+    data::{
+        flip_tile_horizontally, flip_tile_vertically, rotate_tile, tile_pixel, with_tile_pixel,
+        Tile, TILE_HEIGHT_IN_PIXELS, TILE_WIDTH_IN_PIXELS,
+    },
+    swatch_style,
+};
+
+#[derive(Properties, PartialEq)]
+pub struct TileEditorProps {
+    pub tile: Tile,
+    /// Title above the pixels, naming the tile.
+    pub title: String,
+    pub colors: Vec<u32>,
+    /// Palette index painted by clicking a pixel.
+    pub paint: u8,
+    pub on_change: Callback<Tile>,
+    pub on_download_rom: Callback<MouseEvent>,
+}
+
+/// Zoomed pixels of a tile, painted by clicking or dragging.
+#[function_component(TileEditor)]
+pub fn tile_editor(props: &TileEditorProps) -> Html {
+    let clipboard = use_state(|| None::<Tile>);
+    let tile = props.tile;
+    let transform = |operation: fn(&Tile) -> Tile| {
+        let on_change = props.on_change.clone();
+        Callback::from(move |_: MouseEvent| on_change.emit(operation(&tile)))
+    };
+    let on_copy = {
+        let clipboard = clipboard.clone();
+        Callback::from(move |_: MouseEvent| clipboard.set(Some(tile)))
+    };
+    let on_paste = {
+        let on_change = props.on_change.clone();
+        let copied = *clipboard;
+        Callback::from(move |_: MouseEvent| {
+            if let Some(copied) = copied {
+                on_change.emit(copied);
+            }
+        })
+    };
+
+    html! {
+        <Box class={classes!("flex", "flex-col", "gap-2")}>
+            <div class={classes!("text-xs", "font-mono")}>{props.title.clone()}</div>
+            <div class={classes!("grid", "grid-cols-8", "w-48", "border", "border-base-300")}>
+            {
+                (0..TILE_HEIGHT_IN_PIXELS)
+                    .flat_map(|y| (0..TILE_WIDTH_IN_PIXELS).map(move |x| (x, y)))
+                    .map(|(x, y)| {
+                        let paint = |only_dragging: bool| {
+                            let on_change = props.on_change.clone();
+                            let paint = props.paint;
+                            Callback::from(move |e: MouseEvent| {
+                                if !only_dragging || e.buttons() & 1 != 0 {
+                                    on_change.emit(with_tile_pixel(tile, x, y, paint));
+                                }
+                            })
+                        };
+                        let color = props.colors[tile_pixel(&tile, x, y) as usize];
+                        html! {
+                            <div
+                                class={classes!("h-6", "cursor-crosshair")}
+                                style={swatch_style(color)}
+                                onmousedown={paint(false)}
+                                onmouseenter={paint(true)}/>
+                        }
+                    })
+                    .collect::<Html>()
+            }
+            </div>
+            <div class={classes!("join")}>
+                <Button class={classes!("join-item", "btn-xs")} onclick={on_copy}>{"Copy"}</Button>
+                <Button class={classes!("join-item", "btn-xs")} onclick={on_paste}>{"Paste"}</Button>
+                <Button class={classes!("join-item", "btn-xs")} onclick={transform(flip_tile_horizontally)}>{"Flip H"}</Button>
+                <Button class={classes!("join-item", "btn-xs")} onclick={transform(flip_tile_vertically)}>{"Flip V"}</Button>
+                <Button class={classes!("join-item", "btn-xs")} onclick={transform(rotate_tile)}>{"Rotate"}</Button>
+            </div>
+            <Button class={classes!("btn-sm")} onclick={props.on_download_rom.clone()}>
+                {"Download modified ROM"}
+            </Button>
+        </Box>
+    }
+}
@@ -1,86 +1,356 @@
+pub mod codec;
 pub mod data;
+pub mod editor;
+pub mod palette;
 
-use std::cmp;
+use std::{cmp, ops::Range};
 
+use gloo::file::File;
 use yew::prelude::*;
 
-use super::ui::{canvas::Canvas, input::ColorInput, pagination::Pagination, r#box::Box};
-use data::{ChrData, PatternTable};
+use super::{
+    export::png::{encode_indexed, encode_rgba},
+    ui::{
+        button::Button,
+        canvas::Canvas,
+        download::download,
+        input::{FileInput, TextInput},
+        pagination::Pagination,
+        r#box::Box,
+        select::Select,
+    },
+};
+use codec::TileCodec;
+use data::{
+    index_tiles, page_count, page_tiles, render_tiles, to_image_data, Arrangement, ChrData, Tile,
+    TILES_PER_PATTERN_TABLE, TILES_PER_ROW, TILE_HEIGHT_IN_PIXELS, TILE_WIDTH_IN_PIXELS,
+};
+use editor::TileEditor;
+use palette::{MasterPalette, Ppu, COLORS};
 
-fn render_pattern_table(pattern_table: &PatternTable, colors: &Vec<u32>) {
+const EMPHASES: [&str; 8] = [
+    "No emphasis",
+    "Red",
+    "Green",
+    "Red + green",
+    "Blue",
+    "Red + blue",
+    "Green + blue",
+    "All emphasis",
+];
+
+const SOURCES: [&str; 2] = ["CHR ROM", "PRG ROM"];
+const ARRANGEMENTS: [&str; 3] = ["8x8 tiles", "8x16 sprites", "Columns"];
+const SHEETS: [&str; 3] = ["Table $0000", "Table $1000", "All tables"];
+const PNG_FORMATS: [&str; 2] = ["Indexed PNG", "RGBA PNG"];
+/// Canvas pixels per tile pixel.
+const SCALE: usize = 2;
+
+fn render_bank(
+    tiles: &[Tile],
+    arrangement: Arrangement,
+    colors: &[u32],
+    drawn_tiles: Option<&[bool]>,
+) {
     let canvas = Canvas::get_by_id("canvas".to_owned());
-    let image_data = pattern_table.to_image_data((*colors).clone());
-    Canvas::render_image_data(&canvas, image_data);
+    let (width, height, pixels) = render_tiles(tiles, arrangement, colors, drawn_tiles);
+    Canvas::render_image_data(&canvas, to_image_data(width, height, &pixels));
 }
 
-fn rgba_color_to_rgb_hex_string(color: u32) -> String {
-    let rgb = color >> 8;
-    format!(
-        "#{:02X}{:02X}{:02X}",
-        (rgb >> 16) & 0xFF,
-        (rgb >> 8) & 0xFF,
-        rgb & 0xFF
-    )
+/// Pattern tables of `sheet`, one of the tables of `bank` or all of them.
+fn sheet_tables(chr_data: &ChrData, bank: usize, sheet: usize) -> Range<usize> {
+    match sheet {
+        0 | 1 => bank * 2 + sheet..bank * 2 + sheet + 1,
+        _ => 0..chr_data.pattern_tables.len(),
+    }
 }
 
-fn rgb_hex_string_to_rgba_color(string: String) -> u32 {
-    let rgb = u32::from_str_radix(&string[1..], 16).unwrap();
-    (rgb << 8) | 0xFF
+/// Encodes the tiles of `sheet` as a PNG.
+fn export_png(
+    chr_data: &ChrData,
+    bank: usize,
+    sheet: usize,
+    indexed: bool,
+    arrangement: Arrangement,
+    colors: &[u32],
+) -> (String, Vec<u8>) {
+    let tables = sheet_tables(chr_data, bank, sheet);
+    let name = match tables.len() {
+        1 => format!("chr_table_{}.png", tables.start),
+        _ => "chr.png".to_string(),
+    };
+    let tiles = chr_data.tiles(tables);
+    let png = if indexed {
+        let (width, height, indices) = index_tiles(&tiles, arrangement);
+        encode_indexed(width, height, &indices, colors)
+    } else {
+        let (width, height, pixels) = render_tiles(&tiles, arrangement, colors, None);
+        encode_rgba(width, height, &pixels)
+    };
+    (name, png)
+}
+
+/// Pattern tables to replace with the tiles of a PNG, and how they are drawn.
+#[derive(PartialEq, Debug, Clone)]
+pub struct PngImport {
+    pub tables: Range<usize>,
+    pub arrangement: Arrangement,
+    pub colors: Vec<u32>,
 }
 
 #[derive(Properties, PartialEq)]
 pub struct ChrProps {
     pub chr_data: Option<ChrData>,
+
+    /// Tiles a CDL file saw being drawn, the others are dimmed.
+    #[prop_or_default]
+    pub drawn_tiles: Option<Vec<bool>>,
+
+    /// Palette loaded from a .pal file, offered beside the generated ones.
+    #[prop_or_default]
+    pub palette: Option<MasterPalette>,
+
+    /// PRG ROM bytes, for graphics stored in code banks.
+    #[prop_or_default]
+    pub prg_rom: Vec<u8>,
+
+    #[prop_or_default]
+    pub on_import_png: Callback<(File, PngImport)>,
+
+    /// Called with the pattern table, index and new pixels of an edited tile.
+    #[prop_or_default]
+    pub on_edit_tile: Callback<(usize, usize, Tile)>,
+
+    #[prop_or_default]
+    pub on_download_rom: Callback<MouseEvent>,
+}
+
+fn swatch_style(color: u32) -> String {
+    format!("background-color: #{:06X}", color >> 8)
 }
 
 #[function_component(Chr)]
 pub fn chr(props: &ChrProps) -> Html {
-    let pattern_table_count = props
-        .chr_data
-        .as_ref()
-        .map(|data| data.pattern_tables.len())
-        .unwrap_or(0usize);
-
-    let colors = use_state(|| vec![0xFF3030FFu32, 0x30FF30FFu32, 0x3030FFFFu32, 0xEFEFEFFFu32]);
-    let color_callback = |idx: usize| {
-        let colors = colors.clone();
-        Callback::from(move |color: String| {
-            colors.set({
-                let mut colors = (*colors).clone();
-                colors[idx] = rgb_hex_string_to_rgba_color(color);
-                colors
+    let source = use_state(|| 0usize);
+    let codec = use_state(|| TileCodec::Nes2bpp);
+    let offset = use_state(|| 0usize);
+    // Only the CHR ROM read as NES tiles can be edited, imported into and exported.
+    let editable = *source == 0 && *codec == TileCodec::Nes2bpp && *offset == 0;
+    let source_bytes = use_memo(
+        (
+            *source,
+            editable,
+            props.chr_data.clone(),
+            props.prg_rom.clone(),
+        ),
+        |(source, editable, chr_data, prg_rom)| match (source, editable) {
+            (_, true) => Vec::new(),
+            (0, false) => chr_data.as_ref().map_or(Vec::new(), ChrData::to_bytes),
+            _ => prg_rom.clone(),
+        },
+    );
+    let pages = match editable {
+        true => props.chr_data.as_ref().map_or(0, ChrData::bank_count),
+        false => page_count(source_bytes.len(), *offset),
+    };
+
+    let ppu = use_state(|| 0usize);
+    let emphasis = use_state(|| 0u8);
+    let entries = use_state(|| {
+        [
+            0x0Fu8, 0x16, 0x2A, 0x30, 0x00, 0x10, 0x12, 0x14, 0x17, 0x19, 0x1A, 0x1C, 0x20, 0x27,
+            0x28, 0x2C,
+        ]
+    });
+    let active_entry = use_state(|| 0usize);
+
+    {
+        let ppu = ppu.clone();
+        use_effect_with(props.palette.clone(), move |palette| {
+            if palette.is_some() {
+                ppu.set(Ppu::ALL.len());
+            }
+        });
+    }
+    let master_palette = use_memo((*ppu, props.palette.clone()), |(ppu, loaded)| {
+        match (Ppu::ALL.get(*ppu), loaded) {
+            (Some(ppu), _) => MasterPalette::generate(*ppu),
+            (None, Some(loaded)) => loaded.clone(),
+            (None, None) => MasterPalette::generate(Ppu::Ntsc),
+        }
+    });
+    let colors = entries
+        .iter()
+        .take(1 << codec.bits_per_pixel())
+        .map(|&entry| master_palette.color(entry, *emphasis))
+        .collect::<Vec<u32>>();
+
+    let mut ppu_options = Ppu::ALL.iter().map(Ppu::to_string).collect::<Vec<String>>();
+    if props.palette.is_some() {
+        ppu_options.push("Loaded .pal".to_string());
+    }
+    let on_ppu_change = {
+        let ppu = ppu.clone();
+        Callback::from(move |index: usize| ppu.set(index))
+    };
+    let on_emphasis_change = {
+        let emphasis = emphasis.clone();
+        Callback::from(move |index: usize| emphasis.set(index as u8))
+    };
+
+    let arrangement = use_state(|| Arrangement::Tiles8x8);
+    let on_arrangement_change = {
+        let arrangement = arrangement.clone();
+        Callback::from(move |index: usize| {
+            arrangement.set(match index {
+                0 => Arrangement::Tiles8x8,
+                1 => Arrangement::Sprites8x16,
+                _ => Arrangement::Columns(TILES_PER_ROW),
             })
         })
     };
+    let on_columns_change = {
+        let arrangement = arrangement.clone();
+        Callback::from(move |text: String| {
+            if let Ok(columns @ 1..) = text.trim().parse::<usize>() {
+                arrangement.set(Arrangement::Columns(columns));
+            }
+        })
+    };
 
-    let chr_data = props.chr_data.clone();
+    let current_page = use_state(|| 0usize);
     let change_callback = {
-        let chr_data = chr_data.clone();
-        let colors = (*colors).clone();
-        if chr_data.is_some() {
-            let pattern_tables = chr_data.unwrap().pattern_tables.clone();
-            Callback::from(move |page: usize| {
-                log::info!("on_change {}", page);
-                let index = cmp::min(page, pattern_table_count);
-                let pattern_table = pattern_tables[index];
-                render_pattern_table(&pattern_table, &colors);
-            })
-        } else {
-            Callback::from(|_: usize| {})
-        }
+        let current_page = current_page.clone();
+        Callback::from(move |page: usize| {
+            log::info!("on_change {}", page);
+            current_page.set(cmp::min(page, pages.saturating_sub(1)));
+        })
+    };
+    let sheet = use_state(|| 0usize);
+    let png_format = use_state(|| 0usize);
+    let on_sheet_change = {
+        let sheet = sheet.clone();
+        Callback::from(move |index: usize| sheet.set(index))
+    };
+    let on_png_format_change = {
+        let png_format = png_format.clone();
+        Callback::from(move |index: usize| png_format.set(index))
+    };
+    let on_download_png = {
+        let chr_data = props.chr_data.clone();
+        let bank = *current_page;
+        let sheet = *sheet;
+        let indexed = *png_format == 0;
+        let arrangement = *arrangement;
+        let colors = colors.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(chr_data) = chr_data.as_ref() {
+                let (name, png) = export_png(chr_data, bank, sheet, indexed, arrangement, &colors);
+                download(&name, "image/png", &png);
+            }
+        })
+    };
+    let on_png_change = {
+        let on_import_png = props.on_import_png.clone();
+        let tables = props.chr_data.as_ref().map_or(0..0, |chr_data| {
+            sheet_tables(chr_data, *current_page, *sheet)
+        });
+        let arrangement = *arrangement;
+        let colors = colors.clone();
+        Callback::from(move |file: File| {
+            let import = PngImport {
+                tables: tables.clone(),
+                arrangement,
+                colors: colors.clone(),
+            };
+            on_import_png.emit((file, import));
+        })
     };
 
+    let tiles = match editable {
+        true => props
+            .chr_data
+            .as_ref()
+            .map_or(Vec::new(), |data| data.bank_tiles(*current_page)),
+        false => page_tiles(&source_bytes, *offset, *current_page, *codec),
+    };
+    // Pages are counted differently for another source, codec or offset.
+    let on_source_change = {
+        let source = source.clone();
+        let current_page = current_page.clone();
+        Callback::from(move |index: usize| {
+            source.set(index);
+            current_page.set(0);
+        })
+    };
+    let on_codec_change = {
+        let codec = codec.clone();
+        let current_page = current_page.clone();
+        Callback::from(move |index: usize| {
+            codec.set(TileCodec::ALL[index]);
+            current_page.set(0);
+        })
+    };
+    let on_offset_change = {
+        let offset = offset.clone();
+        let current_page = current_page.clone();
+        Callback::from(move |text: String| {
+            if let Ok(value) = usize::from_str_radix(text.trim().trim_start_matches('$'), 16) {
+                offset.set(value);
+                current_page.set(0);
+            }
+        })
+    };
+    let (columns, rows) = arrangement.size(tiles.len());
+
+    // Tile of the bank open in the editor.
+    let edited = use_state(|| None::<usize>);
+    let on_canvas_click = {
+        let edited = edited.clone();
+        let arrangement = *arrangement;
+        let count = tiles.len();
+        Callback::from(move |e: MouseEvent| {
+            let tile_size = (TILE_WIDTH_IN_PIXELS * SCALE) as i32;
+            let (column, row) = (e.offset_x() / tile_size, e.offset_y() / tile_size);
+            if let Some(index) = arrangement.index_at(column as usize, row as usize, count) {
+                edited.set(Some(index));
+            }
+        })
+    };
+    let editor = edited.filter(|_| editable).and_then(|index| {
+        let tile = tiles.get(index)?;
+        let table = *current_page * 2 + index / TILES_PER_PATTERN_TABLE;
+        let tile_index = index % TILES_PER_PATTERN_TABLE;
+        let on_change = {
+            let on_edit_tile = props.on_edit_tile.clone();
+            Callback::from(move |tile: Tile| on_edit_tile.emit((table, tile_index, tile)))
+        };
+        Some(html! {
+            <TileEditor
+                tile={*tile}
+                title={format!("Table {} tile ${:02X}", table, tile_index)}
+                colors={colors.clone()}
+                paint={(*active_entry % colors.len()) as u8}
+                {on_change}
+                on_download_rom={props.on_download_rom.clone()}/>
+        })
+    });
+
     use_effect({
-        let chr_data = props.chr_data.clone();
+        let drawn_tiles = props.drawn_tiles.clone().filter(|_| editable);
         let colors = colors.clone();
+        let bank = *current_page;
+        let arrangement = *arrangement;
+        let tiles = tiles.clone();
         move || {
             log::info!("Chr use_effect");
-            chr_data
-                .map(|d| d.pattern_tables)
-                .map(|t| t[0])
-                .inspect(|pt| {
-                    render_pattern_table(pt, &colors);
-                });
+            let bank_tiles = tiles.len();
+            let drawn_tiles = drawn_tiles
+                .as_ref()
+                .and_then(|drawn| drawn.get(bank * bank_tiles..(bank + 1) * bank_tiles));
+            if !tiles.is_empty() {
+                render_bank(&tiles, arrangement, &colors, drawn_tiles);
+            }
             || ()
         }
     });
@@ -88,31 +358,136 @@ pub fn chr(props: &ChrProps) -> Html {
     html! {
         <Box class={classes!("flex", "box-border", "border", "border-base-300")}>
             <Box class={classes!("grow")}>
-                <Pagination count={pattern_table_count} on_change={change_callback}/>
+                <Box class={classes!("flex", "gap-2")}>
+                    <Select
+                        class={classes!("select-sm")}
+                        options={SOURCES.iter().map(|name| name.to_string()).collect::<Vec<String>>()}
+                        selected={*source}
+                        on_change={on_source_change}/>
+                    <Select
+                        class={classes!("select-sm")}
+                        options={TileCodec::ALL.iter().map(TileCodec::to_string).collect::<Vec<String>>()}
+                        selected={TileCodec::ALL.iter().position(|codec_option| *codec_option == *codec).unwrap_or(0)}
+                        on_change={on_codec_change}/>
+                    <TextInput
+                        class={classes!("input-sm", "w-24")}
+                        value={format!("${:04X}", *offset)}
+                        placeholder="Offset"
+                        on_change={on_offset_change}/>
+                </Box>
+                <Box class={classes!("flex", "gap-2")}>
+                    <Pagination count={pages} page={Some(*current_page)} on_change={change_callback}/>
+                    <Select
+                        class={classes!("select-sm")}
+                        options={ARRANGEMENTS.iter().map(|name| name.to_string()).collect::<Vec<String>>()}
+                        selected={match *arrangement {
+                            Arrangement::Tiles8x8 => 0,
+                            Arrangement::Sprites8x16 => 1,
+                            Arrangement::Columns(_) => 2,
+                        }}
+                        on_change={on_arrangement_change}/>
+                    {
+                        match *arrangement {
+                            Arrangement::Columns(columns) => html! {
+                                <TextInput
+                                    class={classes!("input-sm", "w-16")}
+                                    value={columns.to_string()}
+                                    placeholder="Columns"
+                                    on_change={on_columns_change}/>
+                            },
+                            _ => html! {},
+                        }
+                    }
+                </Box>
+                {
+                    if editable {
+                        html! {
+                            <>
+                                <Box class={classes!("join")}>
+                                    <Select
+                                        class={classes!("join-item", "select-sm")}
+                                        options={SHEETS.iter().map(|name| name.to_string()).collect::<Vec<String>>()}
+                                        selected={*sheet}
+                                        on_change={on_sheet_change}/>
+                                    <Select
+                                        class={classes!("join-item", "select-sm")}
+                                        options={PNG_FORMATS.iter().map(|name| name.to_string()).collect::<Vec<String>>()}
+                                        selected={*png_format}
+                                        on_change={on_png_format_change}/>
+                                    <Button class={classes!("join-item", "btn-sm")} onclick={on_download_png}>
+                                        {"Download PNG"}
+                                    </Button>
+                                </Box>
+                                <FileInput id="chr-png-input" prompt="Import PNG" placeholder=".png" on_change={on_png_change}/>
+                            </>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
                 <Box>
-                    <Canvas id="canvas" width={256} height={256} class={classes!("bg-black")}/>
+                    <Canvas
+                        id="canvas"
+                        width={(columns.max(1) * TILE_WIDTH_IN_PIXELS * SCALE) as u32}
+                        height={(rows.max(1) * TILE_HEIGHT_IN_PIXELS * SCALE) as u32}
+                        class={classes!("bg-black", "cursor-pointer")}
+                        onclick={on_canvas_click}/>
                 </Box>
             </Box>
             <Box class={classes!("grow-0", "flex", "flex-col", "p-3", "gap-3")}>
+                <Select class={classes!("select-sm")} options={ppu_options} selected={*ppu} on_change={on_ppu_change}/>
+                <Select
+                    class={classes!("select-sm")}
+                    options={EMPHASES.iter().map(|name| name.to_string()).collect::<Vec<String>>()}
+                    selected={*emphasis as usize}
+                    on_change={on_emphasis_change}/>
+                <Box class={classes!("flex", "flex-wrap", "gap-2", "w-64")}>
                 {
-                    (*colors).clone()
-                        .into_iter()
+                    entries
+                        .iter()
+                        .zip(colors.iter())
                         .enumerate()
-                        .map(|(idx, color)| {
+                        .map(|(slot, (entry, &color))| {
+                            let onclick = {
+                                let active_entry = active_entry.clone();
+                                Callback::from(move |_: MouseEvent| active_entry.set(slot))
+                            };
+                            let ring = (*active_entry == slot).then_some("ring-2");
+                            html! {
+                                <div class={classes!("flex", "flex-col", "items-center", "text-xs", "font-mono", "cursor-pointer")} {onclick}>
+                                    <div class={classes!("w-8", "h-8", "border", "border-base-300", ring)} style={swatch_style(color)}/>
+                                    {format!("${:02X}", entry)}
+                                </div>
+                            }
+                        })
+                        .collect::<Html>()
+                }
+                </Box>
+                <div class={classes!("flex", "flex-wrap", "w-64")}>
+                {
+                    (0..COLORS as u8)
+                        .map(|entry| {
+                            let onclick = {
+                                let entries = entries.clone();
+                                let slot = *active_entry;
+                                Callback::from(move |_: MouseEvent| {
+                                    let mut updated = *entries;
+                                    updated[slot] = entry;
+                                    entries.set(updated);
+                                })
+                            };
                             html! {
-                                <>
-                                    <Box class={classes!("grow-0", "justify-center", "inline-flex")}>
-                                        {idx}
-                                    </Box>
-                                    <ColorInput
-                                        class={classes!("grow-0")}
-                                        value={rgba_color_to_rgb_hex_string(color)}
-                                        on_change={color_callback(idx)}/>
-                                </>
+                                <div
+                                    class={classes!("w-4", "h-4", "cursor-pointer", "hover:ring-1")}
+                                    title={format!("${:02X}", entry)}
+                                    style={swatch_style(master_palette.color(entry, *emphasis))}
+                                    {onclick}/>
                             }
                         })
                         .collect::<Html>()
                 }
+                </div>
+                {editor.unwrap_or_default()}
             </Box>
         </Box>
     }
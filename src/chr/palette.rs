@@ -0,0 +1,298 @@
+use std::{error, f64::consts::PI, fmt};
+
+pub const COLORS: usize = 64;
+pub const EMPHASES: usize = 8;
+
+/// Signal levels of the 2C02 for luminances 0 to 3, low then high, in volts.
+const LOW_LEVELS: [f64; 4] = [0.228, 0.312, 0.552, 0.880];
+const HIGH_LEVELS: [f64; 4] = [0.616, 0.840, 1.100, 1.100];
+const BLACK: f64 = 0.312;
+const WHITE: f64 = 1.100;
+/// How much an emphasis bit attenuates the signal while its color is in phase.
+const ATTENUATION: f64 = 0.746;
+
+/// 2C03 colors, one octal digit per red, green and blue channel.
+const RGB_COLORS: [u16; COLORS] = [
+    0o333, 0o014, 0o006, 0o326, 0o403, 0o503, 0o510, 0o420, 0o320, 0o120, 0o031, 0o040, 0o022,
+    0o000, 0o000, 0o000, 0o555, 0o036, 0o027, 0o407, 0o507, 0o704, 0o700, 0o630, 0o430, 0o140,
+    0o040, 0o053, 0o044, 0o000, 0o000, 0o000, 0o777, 0o357, 0o447, 0o637, 0o707, 0o737, 0o740,
+    0o750, 0o660, 0o360, 0o070, 0o276, 0o077, 0o000, 0o000, 0o000, 0o777, 0o567, 0o657, 0o757,
+    0o747, 0o755, 0o764, 0o772, 0o773, 0o572, 0o473, 0o276, 0o467, 0o000, 0o000, 0o000,
+];
+
+/// 2C03 colors behind each entry of the 2C04-0001 to 2C04-0004, which scramble the palette so
+/// Vs. System games only look right on the PPU they shipped with.
+const VS_COLORS: [[u8; COLORS]; 4] = [
+    [
+        0x35, 0x23, 0x16, 0x22, 0x1C, 0x09, 0x1D, 0x15, 0x20, 0x00, 0x27, 0x05, 0x04, 0x28, 0x08,
+        0x20, 0x21, 0x3E, 0x1F, 0x29, 0x3C, 0x32, 0x36, 0x12, 0x3F, 0x2B, 0x2E, 0x1E, 0x3D, 0x2D,
+        0x24, 0x01, 0x0E, 0x31, 0x33, 0x2A, 0x2C, 0x0C, 0x1B, 0x14, 0x2E, 0x07, 0x34, 0x06, 0x13,
+        0x02, 0x26, 0x2E, 0x2E, 0x19, 0x10, 0x0A, 0x39, 0x03, 0x37, 0x17, 0x0F, 0x11, 0x0B, 0x0D,
+        0x38, 0x25, 0x18, 0x3A,
+    ],
+    [
+        0x2E, 0x27, 0x18, 0x39, 0x3A, 0x25, 0x1C, 0x31, 0x16, 0x13, 0x38, 0x34, 0x20, 0x23, 0x3C,
+        0x0B, 0x0F, 0x21, 0x06, 0x3D, 0x1B, 0x29, 0x1E, 0x22, 0x1D, 0x24, 0x0E, 0x2B, 0x32, 0x08,
+        0x2E, 0x03, 0x04, 0x36, 0x26, 0x33, 0x11, 0x1F, 0x10, 0x02, 0x14, 0x3F, 0x00, 0x09, 0x12,
+        0x2E, 0x28, 0x20, 0x3E, 0x0D, 0x2A, 0x17, 0x0C, 0x01, 0x15, 0x19, 0x2E, 0x2C, 0x07, 0x37,
+        0x35, 0x05, 0x0A, 0x2D,
+    ],
+    [
+        0x14, 0x25, 0x3A, 0x10, 0x0B, 0x20, 0x31, 0x09, 0x01, 0x2E, 0x36, 0x08, 0x15, 0x3D, 0x3E,
+        0x3C, 0x22, 0x1C, 0x05, 0x12, 0x19, 0x18, 0x17, 0x1B, 0x00, 0x03, 0x2E, 0x02, 0x16, 0x06,
+        0x34, 0x35, 0x23, 0x0F, 0x0E, 0x37, 0x0D, 0x27, 0x26, 0x20, 0x29, 0x04, 0x21, 0x24, 0x11,
+        0x2D, 0x2E, 0x1F, 0x2C, 0x1E, 0x39, 0x33, 0x07, 0x2A, 0x28, 0x1D, 0x0A, 0x2E, 0x32, 0x38,
+        0x13, 0x2B, 0x3F, 0x0C,
+    ],
+    [
+        0x18, 0x03, 0x1C, 0x28, 0x2E, 0x35, 0x01, 0x17, 0x10, 0x1F, 0x2A, 0x0E, 0x36, 0x37, 0x0B,
+        0x39, 0x25, 0x1E, 0x12, 0x34, 0x2E, 0x1D, 0x06, 0x26, 0x3E, 0x1B, 0x22, 0x19, 0x04, 0x2E,
+        0x3A, 0x21, 0x05, 0x0A, 0x07, 0x02, 0x13, 0x14, 0x00, 0x15, 0x0C, 0x3D, 0x11, 0x0F, 0x0D,
+        0x38, 0x2D, 0x24, 0x33, 0x20, 0x08, 0x16, 0x3F, 0x2B, 0x20, 0x3C, 0x2E, 0x27, 0x23, 0x31,
+        0x29, 0x32, 0x2C, 0x09,
+    ],
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidPaletteError {
+    pub len: usize,
+}
+
+impl fmt::Display for InvalidPaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "A .pal file holds 64 or 512 RGB colors, this one has {} bytes",
+            self.len
+        )
+    }
+}
+
+impl error::Error for InvalidPaletteError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Ppu {
+    Ntsc,
+    Pal,
+    Rgb,
+    Vs0001,
+    Vs0002,
+    Vs0003,
+    Vs0004,
+}
+
+impl Ppu {
+    pub const ALL: [Ppu; 7] = [
+        Ppu::Ntsc,
+        Ppu::Pal,
+        Ppu::Rgb,
+        Ppu::Vs0001,
+        Ppu::Vs0002,
+        Ppu::Vs0003,
+        Ppu::Vs0004,
+    ];
+}
+
+impl fmt::Display for Ppu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ppu::Ntsc => write!(f, "2C02 NTSC"),
+            Ppu::Pal => write!(f, "2C07 PAL"),
+            Ppu::Rgb => write!(f, "2C03 RGB"),
+            Ppu::Vs0001 => write!(f, "2C04-0001 RGB"),
+            Ppu::Vs0002 => write!(f, "2C04-0002 RGB"),
+            Ppu::Vs0003 => write!(f, "2C04-0003 RGB"),
+            Ppu::Vs0004 => write!(f, "2C04-0004 RGB"),
+        }
+    }
+}
+
+/// RGBA colors of the 64 palette entries under each of the 8 emphasis settings.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct MasterPalette {
+    colors: Vec<u32>,
+}
+
+fn rgba(red: f64, green: f64, blue: f64) -> u32 {
+    let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u32;
+    (channel(red) << 24) | (channel(green) << 16) | (channel(blue) << 8) | 0xFF
+}
+
+/// Decodes the composite signal of `color` the way a TV would. `emphasis` has the red, green
+/// and blue bits of PPUMASK as seen by the 2C02, `hue` shifts the decoded hue in degrees.
+fn composite(color: usize, emphasis: usize, hue: f64) -> u32 {
+    let phase = color & 0x0F;
+    let level = if phase > 13 { 1 } else { (color >> 4) & 3 };
+    let high = match phase {
+        13.. => LOW_LEVELS[level],
+        _ => HIGH_LEVELS[level],
+    };
+    let low = match phase {
+        0 => HIGH_LEVELS[level],
+        _ => LOW_LEVELS[level],
+    };
+    let in_phase = |phase: usize, sample: usize| (phase + sample) % 12 < 6;
+
+    let (mut y, mut u, mut v) = (0.0, 0.0, 0.0);
+    for sample in 0..12 {
+        let mut signal = if in_phase(phase, sample) { high } else { low };
+        let attenuated = [0x0C, 0x04, 0x08]
+            .iter()
+            .enumerate()
+            .any(|(bit, &color)| emphasis & (1 << bit) != 0 && in_phase(color, sample));
+        if phase < 14 && attenuated {
+            signal *= ATTENUATION;
+        }
+        let signal = (signal - BLACK) / (WHITE - BLACK);
+        let angle = hue.to_radians() - PI * sample as f64 / 6.0;
+        y += signal / 12.0;
+        u += signal * angle.cos() / 6.0;
+        v += signal * angle.sin() / 6.0;
+    }
+    rgba(
+        y + 1.139883 * v,
+        y - 0.394642 * u - 0.580622 * v,
+        y + 2.032062 * u,
+    )
+}
+
+/// A 2C03 color, emphasis turns its channels fully on.
+fn rgb(color: usize, emphasis: usize) -> u32 {
+    let digits = RGB_COLORS[color];
+    let channel = |shift: u16, bit: usize| match emphasis & bit {
+        0 => ((digits >> shift) & 7) as f64 / 7.0,
+        _ => 1.0,
+    };
+    rgba(channel(6, 1), channel(3, 2), channel(0, 4))
+}
+
+impl MasterPalette {
+    pub fn generate(ppu: Ppu) -> MasterPalette {
+        let colors = (0..EMPHASES * COLORS)
+            .map(|index| {
+                let (emphasis, color) = (index / COLORS, index % COLORS);
+                match ppu {
+                    Ppu::Ntsc => composite(color, emphasis, 0.0),
+                    // The 2C07 swaps the red and green emphasis bits, and its alternating lines
+                    // cancel the 2C02's lag behind the color burst.
+                    Ppu::Pal => {
+                        let emphasis =
+                            (emphasis & 4) | ((emphasis & 1) << 1) | ((emphasis & 2) >> 1);
+                        composite(color, emphasis, 15.0)
+                    }
+                    Ppu::Rgb => rgb(color, emphasis),
+                    Ppu::Vs0001 => rgb(VS_COLORS[0][color] as usize, emphasis),
+                    Ppu::Vs0002 => rgb(VS_COLORS[1][color] as usize, emphasis),
+                    Ppu::Vs0003 => rgb(VS_COLORS[2][color] as usize, emphasis),
+                    Ppu::Vs0004 => rgb(VS_COLORS[3][color] as usize, emphasis),
+                }
+            })
+            .collect();
+        MasterPalette { colors }
+    }
+
+    /// Reads a .pal file of 64 colors, or 512 with every emphasis setting. Emphasis on a 64
+    /// color palette dims the other channels.
+    pub fn parse(bytes: &[u8]) -> Result<MasterPalette, InvalidPaletteError> {
+        if bytes.len() != COLORS * 3 && bytes.len() != EMPHASES * COLORS * 3 {
+            return Err(InvalidPaletteError { len: bytes.len() });
+        }
+        let dims = bytes.len() == COLORS * 3;
+        let colors = bytes
+            .chunks(3)
+            .cycle()
+            .take(EMPHASES * COLORS)
+            .enumerate()
+            .map(|(index, channels)| {
+                let emphasis = index / COLORS;
+                let channel = |value: u8, bit: usize| {
+                    let dim = dims && emphasis != 0 && emphasis & bit == 0;
+                    value as f64 / 255.0 * if dim { ATTENUATION } else { 1.0 }
+                };
+                rgba(
+                    channel(channels[0], 1),
+                    channel(channels[1], 2),
+                    channel(channels[2], 4),
+                )
+            })
+            .collect();
+        Ok(MasterPalette { colors })
+    }
+
+    /// RGBA color of palette entry `color` with the emphasis bits of PPUMASK shifted down.
+    pub fn color(&self, color: u8, emphasis: u8) -> u32 {
+        let index = (emphasis as usize & 7) * COLORS + (color as usize & 0x3F);
+        self.colors[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channels(color: u32) -> (u32, u32, u32) {
+        (color >> 24, (color >> 16) & 0xFF, (color >> 8) & 0xFF)
+    }
+
+    #[test]
+    fn generate_palettes() {
+        let ntsc = MasterPalette::generate(Ppu::Ntsc);
+        assert_eq!(ntsc.color(0x0F, 0), 0x000000FF);
+        assert_eq!(ntsc.color(0x30, 0), 0xFFFFFFFF);
+        let (red, green, blue) = channels(ntsc.color(0x16, 0));
+        assert!(red > 150 && green < 80 && blue < 80);
+        let (red, green, blue) = channels(ntsc.color(0x12, 0));
+        assert!(blue > 150 && red < blue && green < blue);
+        let (red, green, blue) = channels(ntsc.color(0x2A, 0));
+        assert!(green > 150 && red < green && blue < green);
+        // Red emphasis darkens blue more than red.
+        let (red, _, blue) = channels(ntsc.color(0x20, 1));
+        assert!(red > blue);
+        let (red, _, blue) = channels(MasterPalette::generate(Ppu::Pal).color(0x20, 2));
+        assert!(red > blue);
+
+        let rgb = MasterPalette::generate(Ppu::Rgb);
+        assert_eq!(rgb.color(0x16, 0), 0xFF0000FF);
+        assert_eq!(rgb.color(0x0F, 5), 0xFF00FFFF);
+        assert_eq!(
+            MasterPalette::generate(Ppu::Vs0001).color(0x00, 0),
+            0xFFB6B6FF
+        );
+        assert_eq!(
+            MasterPalette::generate(Ppu::Vs0002).color(0x01, 0),
+            0xFFB600FF
+        );
+        assert_eq!(
+            MasterPalette::generate(Ppu::Vs0003).color(0x00, 0),
+            0xB600FFFF
+        );
+        assert_eq!(
+            MasterPalette::generate(Ppu::Vs0004).color(0x00, 0),
+            0x926D00FF
+        );
+    }
+
+    #[test]
+    fn parse_pal_files() {
+        let mut bytes = vec![0u8; COLORS * 3];
+        bytes[3..6].copy_from_slice(&[0x10, 0x20, 0x30]);
+        let palette = MasterPalette::parse(&bytes).unwrap();
+        assert_eq!(palette.color(0x01, 0), 0x102030FF);
+        assert_eq!(palette.color(0x41, 0), 0x102030FF);
+        assert_eq!(palette.color(0x01, 4), 0x0C1830FF);
+
+        let mut bytes = vec![0u8; EMPHASES * COLORS * 3];
+        bytes[COLORS * 3..COLORS * 3 + 3].copy_from_slice(&[0xAA, 0xBB, 0xCC]);
+        let palette = MasterPalette::parse(&bytes).unwrap();
+        assert_eq!(palette.color(0x00, 1), 0xAABBCCFF);
+
+        assert_eq!(
+            MasterPalette::parse(&[0; 10]),
+            Err(InvalidPaletteError { len: 10 })
+        );
+    }
+}
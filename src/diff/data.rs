@@ -0,0 +1,399 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+};
+
+use super::super::prg::{
+    cfg::ControlFlowGraph,
+    data::{Location, PrgData},
+    instructions::{Instruction, Mnemonic, Operand},
+    labels::LabelKind,
+};
+
+/// Instructions compared for matching, that far into a subroutine, when the whole of it
+/// doesn't match.
+const PREFIX_LENGTH: usize = 8;
+const ROM_ADDRESS: u16 = 0x8000;
+
+/// Instruction with the parts that change when code moves taken out: branch targets are
+/// relative to the subroutine and other ROM addresses are dropped.
+type Normalized = (Mnemonic, Option<Operand>);
+
+fn normalize(instruction: &Instruction, entry: u16) -> Normalized {
+    let rom = |address: u16| address >= ROM_ADDRESS;
+    let operand = instruction.operand.map(|operand| match operand {
+        Operand::Absolute { address } if instruction.is_branch() => Operand::Absolute {
+            address: address.wrapping_sub(entry),
+        },
+        Operand::Absolute { address } if rom(address) => Operand::Absolute { address: 0 },
+        Operand::AbsoluteIndexed { address, index } if rom(address) => {
+            Operand::AbsoluteIndexed { address: 0, index }
+        }
+        Operand::Indirect { address } if rom(address) => Operand::Indirect { address: 0 },
+        operand => operand,
+    });
+    (instruction.mnemonic, operand)
+}
+
+struct Subroutine {
+    entry: Location,
+    instructions: Vec<(u16, Instruction)>,
+    signature: Vec<Normalized>,
+}
+
+fn subroutines(prg_data: &PrgData) -> Vec<Subroutine> {
+    prg_data
+        .labels
+        .iter()
+        .filter(|(location, label)| label.kind == LabelKind::Subroutine && location.bank.is_some())
+        .map(|(entry, _)| {
+            let cfg = ControlFlowGraph::build(prg_data, *entry);
+            let instructions = cfg
+                .blocks
+                .into_values()
+                .flat_map(|block| block.instructions)
+                .collect::<Vec<(u16, Instruction)>>();
+            let signature = instructions
+                .iter()
+                .map(|(_, instruction)| normalize(instruction, entry.address))
+                .collect();
+            Subroutine {
+                entry: *entry,
+                instructions,
+                signature,
+            }
+        })
+        .collect()
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum LineKind {
+    Same,
+    Added,
+    Removed,
+    Changed,
+}
+
+/// Line of an aligned diff, with the instruction on either side.
+#[derive(PartialEq, Debug, Clone)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    pub old: Option<(u16, Instruction)>,
+    pub new: Option<(u16, Instruction)>,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum DiffStatus {
+    Changed,
+    Added,
+    Removed,
+    Moved,
+    Unchanged,
+}
+
+impl DiffStatus {
+    pub const ALL: [DiffStatus; 5] = [
+        DiffStatus::Changed,
+        DiffStatus::Added,
+        DiffStatus::Removed,
+        DiffStatus::Moved,
+        DiffStatus::Unchanged,
+    ];
+}
+
+impl fmt::Display for DiffStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stringified = match self {
+            DiffStatus::Changed => "changed",
+            DiffStatus::Added => "added",
+            DiffStatus::Removed => "removed",
+            DiffStatus::Moved => "moved",
+            DiffStatus::Unchanged => "unchanged",
+        };
+        write!(f, "{}", stringified)
+    }
+}
+
+/// A subroutine of either ROM and how it changed, matched with its counterpart if any.
+#[derive(PartialEq, Debug, Clone)]
+pub struct SubroutineDiff {
+    pub old: Option<Location>,
+    pub new: Option<Location>,
+    pub status: DiffStatus,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Aligns two instruction lists on their longest common subsequence. A removal followed
+/// by an addition of the same instruction kind shows as a change.
+fn diff_lines(old: &Subroutine, new: &Subroutine) -> Vec<DiffLine> {
+    let (old_length, new_length) = (old.signature.len(), new.signature.len());
+    let mut common = vec![vec![0usize; new_length + 1]; old_length + 1];
+    for i in (0..old_length).rev() {
+        for j in (0..new_length).rev() {
+            common[i][j] = if old.signature[i] == new.signature[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines: Vec<DiffLine> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old_length || j < new_length {
+        let line = if i < old_length && j < new_length && old.signature[i] == new.signature[j] {
+            i += 1;
+            j += 1;
+            DiffLine {
+                kind: LineKind::Same,
+                old: Some(old.instructions[i - 1].clone()),
+                new: Some(new.instructions[j - 1].clone()),
+            }
+        } else if i < old_length && (j == new_length || common[i + 1][j] >= common[i][j + 1]) {
+            i += 1;
+            DiffLine {
+                kind: LineKind::Removed,
+                old: Some(old.instructions[i - 1].clone()),
+                new: None,
+            }
+        } else {
+            j += 1;
+            DiffLine {
+                kind: LineKind::Added,
+                old: None,
+                new: Some(new.instructions[j - 1].clone()),
+            }
+        };
+        // Pair an addition up with a removal of the same kind right before it
+        let removals = lines
+            .iter()
+            .rposition(|line| line.kind != LineKind::Removed)
+            .map_or(0, |index| index + 1);
+        let removal = lines[removals..].iter_mut().find(|removed| {
+            line.kind == LineKind::Added
+                && removed.old.as_ref().map(|(_, old)| old.mnemonic)
+                    == line.new.as_ref().map(|(_, new)| new.mnemonic)
+        });
+        match removal {
+            Some(removal) => {
+                removal.kind = LineKind::Changed;
+                removal.new = line.new;
+            }
+            None => lines.push(line),
+        }
+    }
+    lines
+}
+
+/// Matches the subroutines of two ROMs and diffs their instructions. Subroutines are
+/// matched by their instructions wherever they are, then by their first instructions,
+/// then by being called from the same place of matched ones, and what's left by address.
+pub fn diff(old: &PrgData, new: &PrgData) -> Vec<SubroutineDiff> {
+    let old_subroutines = subroutines(old);
+    let new_subroutines = subroutines(new);
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    let mut old_matched = BTreeSet::new();
+    let mut new_matched = BTreeSet::new();
+
+    let keys: [fn(&Subroutine) -> &[Normalized]; 2] = [
+        |subroutine| &subroutine.signature,
+        |subroutine| &subroutine.signature[..subroutine.signature.len().min(PREFIX_LENGTH)],
+    ];
+    for key in keys {
+        let mut candidates: HashMap<&[Normalized], (Vec<usize>, Vec<usize>)> = HashMap::new();
+        for (index, subroutine) in old_subroutines.iter().enumerate() {
+            if !old_matched.contains(&index) {
+                candidates.entry(key(subroutine)).or_default().0.push(index);
+            }
+        }
+        for (index, subroutine) in new_subroutines.iter().enumerate() {
+            if !new_matched.contains(&index) {
+                candidates.entry(key(subroutine)).or_default().1.push(index);
+            }
+        }
+        for (olds, news) in candidates.into_values() {
+            // Only a match that isn't ambiguous counts
+            if let ([old_index], [new_index]) = (olds.as_slice(), news.as_slice()) {
+                pairs.push((*old_index, *new_index));
+                old_matched.insert(*old_index);
+                new_matched.insert(*new_index);
+            }
+        }
+    }
+
+    // Subroutines called from the same place of matching ones match as well
+    let entries = |subroutines: &[Subroutine]| {
+        subroutines
+            .iter()
+            .enumerate()
+            .map(|(index, subroutine)| (subroutine.entry, index))
+            .collect::<BTreeMap<Location, usize>>()
+    };
+    let (old_entries, new_entries) = (entries(&old_subroutines), entries(&new_subroutines));
+    let mut next = 0;
+    while let Some((old_index, new_index)) = pairs.get(next).copied() {
+        next += 1;
+        let (old_subroutine, new_subroutine) =
+            (&old_subroutines[old_index], &new_subroutines[new_index]);
+        for line in diff_lines(old_subroutine, new_subroutine) {
+            let (Some((_, old_call)), Some((_, new_call))) = (line.old, line.new) else {
+                continue;
+            };
+            let targets = old_call.jump_target().zip(new_call.jump_target());
+            let (Some((old_target, new_target)), Some(old_bank), Some(new_bank)) = (
+                targets,
+                old_subroutine.entry.bank,
+                new_subroutine.entry.bank,
+            ) else {
+                continue;
+            };
+            let called = old_entries
+                .get(&old.resolve(old_bank, old_target))
+                .zip(new_entries.get(&new.resolve(new_bank, new_target)));
+            if let Some((old_called, new_called)) = called {
+                if !old_matched.contains(old_called) && !new_matched.contains(new_called) {
+                    pairs.push((*old_called, *new_called));
+                    old_matched.insert(*old_called);
+                    new_matched.insert(*new_called);
+                }
+            }
+        }
+    }
+
+    for (old_index, subroutine) in old_subroutines.iter().enumerate() {
+        if old_matched.contains(&old_index) {
+            continue;
+        }
+        let new_index = new_entries
+            .get(&subroutine.entry)
+            .filter(|new_index| !new_matched.contains(new_index));
+        if let Some(new_index) = new_index {
+            pairs.push((old_index, *new_index));
+            old_matched.insert(old_index);
+            new_matched.insert(*new_index);
+        }
+    }
+
+    let mut diffs = pairs
+        .into_iter()
+        .map(|(old_index, new_index)| {
+            let (old, new) = (&old_subroutines[old_index], &new_subroutines[new_index]);
+            let lines = diff_lines(old, new);
+            let status = if lines.iter().any(|line| line.kind != LineKind::Same) {
+                DiffStatus::Changed
+            } else if old.entry != new.entry {
+                DiffStatus::Moved
+            } else {
+                DiffStatus::Unchanged
+            };
+            SubroutineDiff {
+                old: Some(old.entry),
+                new: Some(new.entry),
+                status,
+                lines,
+            }
+        })
+        .collect::<Vec<SubroutineDiff>>();
+    let unmatched = |subroutines: &[Subroutine], matched: &BTreeSet<usize>, added: bool| {
+        subroutines
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !matched.contains(index))
+            .map(|(_, subroutine)| {
+                let lines = subroutine
+                    .instructions
+                    .iter()
+                    .map(|instruction| DiffLine {
+                        kind: if added {
+                            LineKind::Added
+                        } else {
+                            LineKind::Removed
+                        },
+                        old: (!added).then(|| instruction.clone()),
+                        new: added.then(|| instruction.clone()),
+                    })
+                    .collect();
+                SubroutineDiff {
+                    old: (!added).then_some(subroutine.entry),
+                    new: added.then_some(subroutine.entry),
+                    status: if added {
+                        DiffStatus::Added
+                    } else {
+                        DiffStatus::Removed
+                    },
+                    lines,
+                }
+            })
+            .collect::<Vec<SubroutineDiff>>()
+    };
+    diffs.extend(unmatched(&old_subroutines, &old_matched, false));
+    diffs.extend(unmatched(&new_subroutines, &new_matched, true));
+    diffs.sort_by_key(|diff| (diff.status, diff.new.or(diff.old)));
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prg::data::nrom_prg;
+
+    fn prg_data(code: &[u8]) -> PrgData {
+        PrgData::parse(nrom_prg(code)).unwrap()
+    }
+
+    fn location(address: u16) -> Option<Location> {
+        Some(Location {
+            bank: Some(0),
+            address,
+        })
+    }
+
+    #[test]
+    fn diff_revisions() {
+        let old = prg_data(&[
+            0x20, 0x07, 0xC0, // JSR $C007
+            0x20, 0x0C, 0xC0, // JSR $C00C
+            0x60, // RTS
+            0xA9, 0x01, // $C007: LDA #$01
+            0x85, 0x10, // STA $10
+            0x60, // RTS
+            0xA2, 0x02, // $C00C: LDX #$02
+            0x60, // RTS
+        ]);
+        // The first subroutine moves, the second one changes
+        let new = prg_data(&[
+            0x20, 0x08, 0xC0, // JSR $C008
+            0x20, 0x0D, 0xC0, // JSR $C00D
+            0x60, // RTS
+            0xEA, // NOP
+            0xA9, 0x01, // $C008: LDA #$01
+            0x85, 0x10, // STA $10
+            0x60, // RTS
+            0xA2, 0x03, // $C00D: LDX #$03
+            0xE8, // INX
+            0x60, // RTS
+        ]);
+        let diffs = diff(&old, &new);
+        let summary = diffs
+            .iter()
+            .map(|diff| (diff.status, diff.old, diff.new))
+            .collect::<Vec<(DiffStatus, Option<Location>, Option<Location>)>>();
+        assert_eq!(
+            summary,
+            vec![
+                (DiffStatus::Changed, location(0xC00C), location(0xC00D)),
+                (DiffStatus::Moved, location(0xC007), location(0xC008)),
+                (DiffStatus::Unchanged, location(0xC000), location(0xC000)),
+            ]
+        );
+        let kinds = diffs[0]
+            .lines
+            .iter()
+            .map(|line| line.kind)
+            .collect::<Vec<LineKind>>();
+        assert_eq!(
+            kinds,
+            vec![LineKind::Changed, LineKind::Added, LineKind::Same]
+        );
+    }
+}
@@ -0,0 +1,134 @@
+pub mod data;
+
+use super::prg::{
+    data::{Location, PrgData},
+    instructions::Instruction,
+};
+use data::{diff, DiffStatus, LineKind, SubroutineDiff};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct DiffProps {
+    /// The ROM compared against.
+    pub old: PrgData,
+    pub new: PrgData,
+}
+
+/// Address and text of an instruction of the subroutine at `subroutine`.
+fn instruction_text(
+    prg_data: &PrgData,
+    subroutine: Option<Location>,
+    instruction: Option<&(u16, Instruction)>,
+) -> (String, String) {
+    let (Some((address, instruction)), Some(bank)) =
+        (instruction, subroutine.and_then(|location| location.bank))
+    else {
+        return (String::new(), String::new());
+    };
+    let operand = instruction.operand.map_or(String::new(), |operand| {
+        let label = operand
+            .address()
+            .and_then(|target| prg_data.operand_label(bank, target));
+        operand.to_string_with_label(label.as_deref())
+    });
+    (
+        format!("{:04X}", address),
+        format!("{} {}", instruction.mnemonic, operand),
+    )
+}
+
+fn subroutine_name(props: &DiffProps, diff: &SubroutineDiff) -> String {
+    let name = |prg_data: &PrgData, location| {
+        prg_data
+            .labels
+            .name(&location)
+            .map_or(location.to_string(), str::to_string)
+    };
+    match (diff.old, diff.new) {
+        (Some(old), Some(new)) if old != new => {
+            format!("{} -> {}", name(&props.old, old), name(&props.new, new))
+        }
+        (_, Some(new)) => name(&props.new, new),
+        (Some(old), None) => name(&props.old, old),
+        (None, None) => String::new(),
+    }
+}
+
+/// Subroutines that differ between two ROMs, with the aligned instructions of the selected one.
+#[function_component(Diff)]
+pub fn diff_view(props: &DiffProps) -> Html {
+    let diffs = use_memo((props.old.clone(), props.new.clone()), |(old, new)| {
+        diff(old, new)
+    });
+    let selected = use_state(|| None::<usize>);
+
+    let summary = DiffStatus::ALL
+        .iter()
+        .map(|status| {
+            let count = diffs.iter().filter(|diff| diff.status == *status).count();
+            format!("{} {}", count, status)
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    html! {
+        <div class={classes!("flex", "gap-4", "font-mono", "text-sm")}>
+            <div class={classes!("w-1/3")}>
+                <div class={classes!("font-semibold", "mb-2")}>{summary}</div>
+                <div class={classes!("table", "w-full")}>
+                {
+                    diffs
+                        .iter()
+                        .enumerate()
+                        .map(|(index, diff)| {
+                            let onclick = {
+                                let selected = selected.clone();
+                                Callback::from(move |_: MouseEvent| selected.set(Some(index)))
+                            };
+                            let highlight = (*selected == Some(index)).then_some("bg-base-300");
+                            html! {
+                                <div class={classes!("table-row", "cursor-pointer", "hover:bg-base-200", highlight)} {onclick}>
+                                    <div class={classes!("table-cell")}>{subroutine_name(props, diff)}</div>
+                                    <div class={classes!("table-cell", "text-secondary")}>{diff.status.to_string()}</div>
+                                </div>
+                            }
+                        })
+                        .collect::<Html>()
+                }
+                </div>
+            </div>
+            {
+                selected
+                    .and_then(|index| diffs.get(index))
+                    .map(|diff| html! {
+                        <div class={classes!("table", "w-2/3")}>
+                        {
+                            diff.lines
+                                .iter()
+                                .map(|line| {
+                                    let color = match line.kind {
+                                        LineKind::Same => None,
+                                        LineKind::Added => Some("text-success"),
+                                        LineKind::Removed => Some("text-error"),
+                                        LineKind::Changed => Some("text-warning"),
+                                    };
+                                    let (old_address, old) = instruction_text(&props.old, diff.old, line.old.as_ref());
+                                    let (new_address, new) = instruction_text(&props.new, diff.new, line.new.as_ref());
+                                    html! {
+                                        <div class={classes!("table-row", color)}>
+                                            <div class={classes!("table-cell")}>{old_address}</div>
+                                            <div class={classes!("table-cell")}>{old}</div>
+                                            <div class={classes!("table-cell")}>{new_address}</div>
+                                            <div class={classes!("table-cell")}>{new}</div>
+                                        </div>
+                                    }
+                                })
+                                .collect::<Html>()
+                        }
+                        </div>
+                    })
+                    .unwrap_or_default()
+            }
+        </div>
+    }
+}
@@ -0,0 +1,79 @@
+use super::zip::crc32;
+
+const HEADER: &[u8] = b"BPS1";
+const SOURCE_READ: usize = 0;
+const TARGET_READ: usize = 1;
+
+/// Appends `value` as a BPS variable-length number.
+fn write_number(patch: &mut Vec<u8>, value: usize) {
+    let mut value = value;
+    loop {
+        let low = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            patch.push(0x80 | low);
+            break;
+        }
+        patch.push(low);
+        value -= 1;
+    }
+}
+
+/// Builds a BPS patch that turns `source` into `target`. Bytes that are unchanged are
+/// copied from the source, the others are stored in the patch.
+pub fn bps(source: &[u8], target: &[u8]) -> Vec<u8> {
+    let unchanged = |offset: usize| source.get(offset) == Some(&target[offset]);
+    let mut patch = HEADER.to_vec();
+    write_number(&mut patch, source.len());
+    write_number(&mut patch, target.len());
+    // No metadata
+    write_number(&mut patch, 0);
+    let mut offset = 0;
+    while offset < target.len() {
+        let copied = unchanged(offset);
+        let mut end = offset + 1;
+        while end < target.len() && unchanged(end) == copied {
+            end += 1;
+        }
+        let action = if copied { SOURCE_READ } else { TARGET_READ };
+        write_number(&mut patch, ((end - offset - 1) << 2) | action);
+        if !copied {
+            patch.extend_from_slice(&target[offset..end]);
+        }
+        offset = end;
+    }
+    patch.extend_from_slice(&crc32(source).to_le_bytes());
+    patch.extend_from_slice(&crc32(target).to_le_bytes());
+    let patch_crc = crc32(&patch);
+    patch.extend_from_slice(&patch_crc.to_le_bytes());
+    patch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bps_actions() {
+        let source = [0x00, 0x01, 0x02, 0x03];
+        let target = [0x00, 0x01, 0xFF, 0x03, 0xFE];
+        let patch = bps(&source, &target);
+        // Sizes, no metadata, then copy 2, store 1, copy 1, store 1
+        assert_eq!(
+            patch[4..13],
+            [0x84, 0x85, 0x80, 0x84, 0x81, 0xFF, 0x80, 0x81, 0xFE]
+        );
+        assert_eq!(patch[13..17], crc32(&source).to_le_bytes());
+        assert_eq!(patch[17..21], crc32(&target).to_le_bytes());
+        let patch_crc = u32::from_le_bytes(patch[patch.len() - 4..].try_into().unwrap());
+        assert_eq!(patch_crc, crc32(&patch[..patch.len() - 4]));
+    }
+
+    #[test]
+    fn bps_numbers() {
+        let mut bytes = Vec::new();
+        write_number(&mut bytes, 0x7F);
+        write_number(&mut bytes, 0x80);
+        assert_eq!(bytes, vec![0xFF, 0x00, 0x80]);
+    }
+}
@@ -0,0 +1,61 @@
+const HEADER: &[u8] = b"PATCH";
+const FOOTER: &[u8] = b"EOF";
+// A record at this offset would be read as the footer
+const EOF_OFFSET: usize = 0x454F46;
+const MAX_RECORD_SIZE: usize = 0xFFFF;
+
+/// Builds an IPS patch that turns `original` into `patched`. Bytes past the end of
+/// `original` are written as well, IPS can't shrink a file.
+pub fn ips(original: &[u8], patched: &[u8]) -> Vec<u8> {
+    let differs = |offset: usize| original.get(offset) != Some(&patched[offset]);
+    let mut patch = HEADER.to_vec();
+    let mut offset = 0;
+    while offset < patched.len() {
+        if !differs(offset) {
+            offset += 1;
+            continue;
+        }
+        let start = if offset == EOF_OFFSET {
+            offset - 1
+        } else {
+            offset
+        };
+        let mut end = offset;
+        while end < patched.len() && end - start < MAX_RECORD_SIZE && differs(end) {
+            end += 1;
+        }
+        patch.extend_from_slice(&(start as u32).to_be_bytes()[1..]);
+        patch.extend_from_slice(&((end - start) as u16).to_be_bytes());
+        patch.extend_from_slice(&patched[start..end]);
+        offset = end;
+    }
+    patch.extend_from_slice(FOOTER);
+    patch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ips_records() {
+        let original = [0x00, 0x01, 0x02, 0x03, 0x04];
+        let patched = [0x00, 0xFF, 0xFE, 0x03, 0xFD, 0xFC];
+        let mut expected = b"PATCH".to_vec();
+        expected.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xFF, 0xFE]);
+        expected.extend_from_slice(&[0x00, 0x00, 0x04, 0x00, 0x02, 0xFD, 0xFC]);
+        expected.extend_from_slice(b"EOF");
+        assert_eq!(ips(&original, &patched), expected);
+        assert_eq!(ips(&original, &original), b"PATCHEOF".to_vec());
+    }
+
+    #[test]
+    fn ips_record_at_eof_offset() {
+        let original = vec![0u8; EOF_OFFSET + 2];
+        let mut patched = original.clone();
+        patched[EOF_OFFSET] = 0x01;
+        let patch = ips(&original, &patched);
+        assert_eq!(patch[5..10], [0x45, 0x4F, 0x45, 0x00, 0x02]);
+        assert_eq!(patch[10..12], [0x00, 0x01]);
+    }
+}
@@ -0,0 +1,943 @@
+pub mod bps;
+pub mod ips;
+pub mod png;
+pub mod text_dump;
+pub mod zip;
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    error, fmt,
+};
+
+use super::{
+    prg::{
+        data::{PrgBank, PrgData, PRG_BANK_SIZE},
+        instructions::{AddressingMode, Instruction},
+        listing::{bank_listing, FieldValue, ListingRow},
+        regions::text_operand,
+        structs::StructLayout,
+        tables::{Pointer, PointerPart},
+        text_table::TextTable,
+    },
+    reader::RomReaderResult,
+};
+
+const CHR_BANK_SIZE: usize = 0x2000;
+const NESASM_BANK_SIZE: usize = 0x2000;
+const TRAINER_ADDRESS: u16 = 0x7000;
+// Shorter runs of data are easier to follow as .byte lines
+const MIN_INCBIN_SIZE: usize = 32;
+const BYTES_PER_LINE: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportError {
+    UnsupportedHeader(Dialect),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::UnsupportedHeader(dialect) => {
+                write!(f, "The iNES header can't be reproduced with {}", dialect)
+            }
+        }
+    }
+}
+
+impl error::Error for ExportError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Dialect {
+    Ca65,
+    Asm6,
+    Nesasm,
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Dialect::Ca65 => write!(f, "ca65"),
+            Dialect::Asm6 => write!(f, "asm6"),
+            Dialect::Nesasm => write!(f, "NESASM"),
+        }
+    }
+}
+
+impl Dialect {
+    pub const ALL: [Dialect; 3] = [Dialect::Ca65, Dialect::Asm6, Dialect::Nesasm];
+
+    fn source_extension(&self) -> &'static str {
+        match self {
+            Dialect::Ca65 => "s",
+            Dialect::Asm6 | Dialect::Nesasm => "asm",
+        }
+    }
+
+    fn byte_directive(&self) -> &'static str {
+        match self {
+            Dialect::Ca65 => ".byte",
+            Dialect::Asm6 | Dialect::Nesasm => ".db",
+        }
+    }
+
+    fn word_directive(&self) -> &'static str {
+        match self {
+            Dialect::Ca65 => ".word",
+            Dialect::Asm6 | Dialect::Nesasm => ".dw",
+        }
+    }
+
+    fn incbin_directive(&self) -> Option<&'static str> {
+        match self {
+            Dialect::Ca65 => Some(".incbin"),
+            Dialect::Asm6 => Some("incbin"),
+            // NESASM can only include whole files
+            Dialect::Nesasm => None,
+        }
+    }
+
+    /// Formats the instruction so that the assembler picks the same opcode again.
+    /// Returns `None` when that can't be expressed and the bytes have to be emitted as data.
+    fn instruction(&self, instruction: &Instruction, label: Option<&str>) -> Option<String> {
+        use AddressingMode::*;
+        if !instruction.is_official() {
+            return None;
+        }
+        let mode = instruction.addressing_mode();
+        let Some(operand) = instruction.operand else {
+            return Some(match mode {
+                Accumulator => format!("{} A", instruction.mnemonic),
+                _ => instruction.mnemonic.to_string(),
+            });
+        };
+        let address = operand.address().unwrap_or_default();
+        let value = match (label, mode) {
+            (Some(label), _) => label.to_string(),
+            (None, ZeroPage | ZeroPageX | ZeroPageY | IndirectX | IndirectY) => {
+                format!("${:02X}", address)
+            }
+            (None, _) => format!("${:04X}", address),
+        };
+        let zero_page = matches!(mode, ZeroPage | ZeroPageX | ZeroPageY);
+        let forced_absolute = matches!(mode, Absolute | AbsoluteX | AbsoluteY) && address < 0x100;
+        let value = match self {
+            Dialect::Ca65 if forced_absolute => format!("a:{}", value),
+            Dialect::Asm6 if forced_absolute => return None,
+            Dialect::Nesasm if zero_page => format!("<{}", value),
+            _ => value,
+        };
+        let (open, close) = match self {
+            Dialect::Nesasm => ("[", "]"),
+            _ => ("(", ")"),
+        };
+        let operand = match mode {
+            Immediate => operand.to_string(),
+            ZeroPageX | AbsoluteX => format!("{}, X", value),
+            ZeroPageY | AbsoluteY => format!("{}, Y", value),
+            Indirect => format!("{}{}{}", open, value, close),
+            IndirectX => format!("{}{}, X{}", open, value, close),
+            IndirectY => format!("{}{}{}, Y", open, value, close),
+            _ => value,
+        };
+        Some(format!("{} {}", instruction.mnemonic, operand))
+    }
+
+    fn pointer(&self, pointer: &Pointer, label: &str) -> String {
+        let value = match pointer.adjust {
+            0 => label.to_string(),
+            adjust => format!("{}-{}", label, adjust),
+        };
+        let (directive, operand) = match (self, pointer.part) {
+            (_, PointerPart::Word) => (self.word_directive(), value),
+            (Dialect::Nesasm, PointerPart::Low) => (".db", format!("LOW({})", value)),
+            (Dialect::Nesasm, PointerPart::High) => (".db", format!("HIGH({})", value)),
+            (_, PointerPart::Low) => (self.byte_directive(), format!("<({})", value)),
+            (_, PointerPart::High) => (self.byte_directive(), format!(">({})", value)),
+        };
+        format!("{} {}", directive, operand)
+    }
+
+    /// Fields of a struct record, with consecutive fields of the same size on one line.
+    fn record_lines(
+        &self,
+        values: &[FieldValue],
+        defined_labels: &HashSet<String>,
+    ) -> Vec<(String, usize)> {
+        let mut lines: Vec<(usize, Vec<String>)> = Vec::new();
+        for value in values {
+            let text = match value {
+                FieldValue::Pointer {
+                    target_label: Some(label),
+                    ..
+                } if defined_labels.contains(label) => label.clone(),
+                FieldValue::Pointer { value, .. } => format!("${:04X}", value),
+                FieldValue::Enum { value, .. } => format!("${:02X}", value),
+                _ => value.to_string(),
+            };
+            match lines.last_mut() {
+                Some((size, texts)) if *size == value.size() => texts.push(text),
+                _ => lines.push((value.size(), vec![text])),
+            }
+        }
+        lines
+            .into_iter()
+            .map(|(size, texts)| {
+                let directive = match size {
+                    1 => self.byte_directive(),
+                    _ => self.word_directive(),
+                };
+                (
+                    format!("{} {}", directive, texts.join(", ")),
+                    size * texts.len(),
+                )
+            })
+            .collect()
+    }
+
+    /// Definition of the offsets of the fields of `layout`. Only ca65 has structs, the other
+    /// assemblers get a constant per field.
+    fn struct_definition(&self, layout: &StructLayout) -> Vec<String> {
+        let mut lines = Vec::new();
+        match self {
+            Dialect::Ca65 => {
+                lines.push(format!(".struct {}", layout.name));
+                for field in layout.fields.iter() {
+                    let directive = match field.field_type.size() {
+                        1 => ".byte",
+                        _ => ".word",
+                    };
+                    lines.push(format!("    {} {}", field.name, directive));
+                }
+                lines.push(".endstruct".to_string());
+            }
+            Dialect::Asm6 | Dialect::Nesasm => {
+                lines.push(format!("; struct {}", layout.name));
+                for (offset, field) in layout.offsets() {
+                    lines.push(format!("{}_{} = ${:02X}", layout.name, field.name, offset));
+                }
+                lines.push(format!("{}_size = ${:02X}", layout.name, layout.size()));
+            }
+        }
+        lines
+    }
+
+    fn byte_lines(&self, bytes: &[u8]) -> Vec<String> {
+        bytes
+            .chunks(BYTES_PER_LINE)
+            .map(|chunk| {
+                let values = chunk
+                    .iter()
+                    .map(|v| format!("${:02X}", v))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("    {} {}", self.byte_directive(), values)
+            })
+            .collect()
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ExportedFile {
+    pub name: String,
+    pub contents: Vec<u8>,
+}
+
+impl ExportedFile {
+    fn text(name: String, lines: Vec<String>) -> ExportedFile {
+        ExportedFile {
+            name,
+            contents: (lines.join("\n") + "\n").into_bytes(),
+        }
+    }
+}
+
+struct BankWriter<'a> {
+    dialect: Dialect,
+    bank: &'a PrgBank,
+    lines: Vec<String>,
+    offset: usize,
+    nesasm_bank_offset: Option<usize>,
+}
+
+impl BankWriter<'_> {
+    fn bin_file_name(bank_number: usize) -> String {
+        format!("prg_{:02}.bin", bank_number)
+    }
+
+    /// NESASM works with 8KiB banks, a new one has to be started every 8KiB.
+    fn start_nesasm_bank(&mut self) {
+        if self.dialect == Dialect::Nesasm
+            && self.offset.is_multiple_of(NESASM_BANK_SIZE)
+            && self.nesasm_bank_offset != Some(self.offset)
+        {
+            let nesasm_bank = (self.bank.number * PRG_BANK_SIZE + self.offset) / NESASM_BANK_SIZE;
+            let address = self.bank.base_address as usize + self.offset;
+            self.lines.push(format!("    .bank {}", nesasm_bank));
+            self.lines.push(format!("    .org ${:04X}", address));
+            self.nesasm_bank_offset = Some(self.offset);
+        }
+    }
+
+    fn bytes_until_bank_switch(&self) -> usize {
+        match self.dialect {
+            Dialect::Nesasm => NESASM_BANK_SIZE - self.offset % NESASM_BANK_SIZE,
+            _ => self.bank.bytes.len() - self.offset,
+        }
+    }
+
+    fn write_comment(&mut self, comment: &str) {
+        self.start_nesasm_bank();
+        for line in comment.lines() {
+            self.lines.push(format!("; {}", line));
+        }
+    }
+
+    fn write_label(&mut self, name: &str) {
+        self.start_nesasm_bank();
+        self.lines.push(format!("{}:", name));
+    }
+
+    fn write_code(&mut self, text: Option<String>, length: usize) {
+        self.start_nesasm_bank();
+        match text {
+            Some(text) if length <= self.bytes_until_bank_switch() => {
+                self.lines.push(format!("    {}", text));
+                self.offset += length;
+            }
+            _ => self.write_data(length),
+        }
+    }
+
+    fn write_data(&mut self, length: usize) {
+        let mut remaining = length;
+        while remaining > 0 {
+            self.start_nesasm_bank();
+            let length = remaining.min(self.bytes_until_bank_switch());
+            let bytes = &self.bank.bytes[self.offset..self.offset + length];
+            match self.dialect.incbin_directive() {
+                Some(directive) if length >= MIN_INCBIN_SIZE => {
+                    self.lines.push(format!(
+                        "    {} \"{}\", ${:04X}, ${:04X}",
+                        directive,
+                        BankWriter::bin_file_name(self.bank.number),
+                        self.offset,
+                        length
+                    ));
+                }
+                _ => {
+                    let lines = self.dialect.byte_lines(bytes);
+                    self.lines.extend(lines);
+                }
+            }
+            self.offset += length;
+            remaining -= length;
+        }
+    }
+}
+
+/// Labels that end up defined somewhere in the project: the ones emitted as constants
+/// and the ones starting a row of any bank listing.
+fn defined_labels(prg_data: &PrgData, listings: &[Vec<ListingRow>]) -> HashSet<String> {
+    let constants = prg_data
+        .labels
+        .iter()
+        .filter(|(location, _)| location.bank.is_none())
+        .map(|(_, label)| label.name.clone());
+    let rows = listings.iter().flatten().filter_map(|row| match row {
+        ListingRow::Label { name, .. } => Some(name.clone()),
+        _ => None,
+    });
+    constants.chain(rows).collect()
+}
+
+fn bank_source(
+    dialect: Dialect,
+    bank: &PrgBank,
+    listing: &[ListingRow],
+    defined_labels: &HashSet<String>,
+    text_table: Option<&TextTable>,
+) -> Vec<String> {
+    let mut writer = BankWriter {
+        dialect,
+        bank,
+        lines: Vec::new(),
+        offset: 0,
+        nesasm_bank_offset: None,
+    };
+    if dialect == Dialect::Asm6 {
+        writer
+            .lines
+            .push(format!("    .base ${:04X}", bank.base_address));
+    }
+    let mut pending_data = 0usize;
+    for row in listing {
+        if let ListingRow::Data { bytes, comment, .. } = row {
+            if comment.is_none() {
+                pending_data += bytes.len();
+                continue;
+            }
+        }
+        writer.write_data(pending_data);
+        pending_data = 0;
+        match row {
+            ListingRow::Segment { name } => writer.write_comment(&format!("Segment {}", name)),
+            ListingRow::Label { name, .. } => writer.write_label(name),
+            ListingRow::Instruction {
+                instruction,
+                operand_label,
+                comment,
+                ..
+            } => {
+                if let Some(comment) = comment {
+                    writer.write_comment(comment);
+                }
+                // Labels of the second byte of a .word pointer come with a +1
+                let label = operand_label.as_deref().filter(|label| {
+                    defined_labels.contains(label.split('+').next().unwrap_or_default())
+                });
+                let text = dialect.instruction(instruction, label);
+                if text.is_none() {
+                    writer.write_comment(&format!(
+                        "{} {}",
+                        instruction.mnemonic,
+                        instruction
+                            .operand
+                            .map(|o| o.to_string())
+                            .unwrap_or_default()
+                    ));
+                }
+                writer.write_code(text, instruction.len());
+            }
+            ListingRow::Pointer {
+                pointer,
+                target_label,
+                comment,
+                ..
+            } => {
+                if let Some(comment) = comment {
+                    writer.write_comment(comment);
+                }
+                let text = target_label
+                    .as_deref()
+                    .filter(|label| defined_labels.contains(*label))
+                    .map(|label| dialect.pointer(pointer, label));
+                writer.write_code(text, pointer.part.len());
+            }
+            ListingRow::Word { value, comment, .. } => {
+                if let Some(comment) = comment {
+                    writer.write_comment(comment);
+                }
+                let text = format!("{} ${:04X}", dialect.word_directive(), value);
+                writer.write_code(Some(text), 2);
+            }
+            ListingRow::Text { bytes, comment, .. } => {
+                if let Some(comment) = comment {
+                    writer.write_comment(comment);
+                }
+                // Quoted text would be assembled as ASCII
+                match text_table {
+                    Some(table) => {
+                        writer.write_comment(&format!("\"{}\"", table.decode(bytes)));
+                        writer.write_data(bytes.len());
+                    }
+                    None => {
+                        let text = format!("{} {}", dialect.byte_directive(), text_operand(bytes));
+                        writer.write_code(Some(text), bytes.len());
+                    }
+                }
+            }
+            ListingRow::Record {
+                layout,
+                index,
+                values,
+                comment,
+                ..
+            } => {
+                if let Some(comment) = comment {
+                    writer.write_comment(comment);
+                }
+                writer.write_comment(&format!("{} {}", layout.name, index));
+                for (text, length) in dialect.record_lines(values, defined_labels) {
+                    writer.write_code(Some(text), length);
+                }
+            }
+            ListingRow::Data { bytes, comment, .. } => {
+                if let Some(comment) = comment {
+                    writer.write_comment(comment);
+                }
+                pending_data = bytes.len();
+            }
+        }
+    }
+    writer.write_data(pending_data);
+    writer.lines
+}
+
+fn constants_source(dialect: Dialect, prg_data: &PrgData) -> Vec<String> {
+    let constants = prg_data
+        .labels
+        .iter()
+        .filter(|(location, _)| location.bank.is_none())
+        .map(|(location, label)| {
+            if location.address < 0x100 {
+                format!("{} = ${:02X}", label.name, location.address)
+            } else {
+                format!("{} = ${:04X}", label.name, location.address)
+            }
+        });
+    // Tables sharing a layout only need it defined once
+    let layouts = prg_data
+        .structs
+        .values()
+        .map(|table| (table.layout.name.clone(), &table.layout))
+        .collect::<BTreeMap<String, &StructLayout>>();
+    let structs = layouts
+        .values()
+        .flat_map(|layout| dialect.struct_definition(layout));
+    constants.chain(structs).collect()
+}
+
+fn ca65_linker_config(rom: &RomReaderResult, chr_size: usize) -> Vec<String> {
+    let mut memory = vec!["    HEADER: start = $0000, size = $0010, fill = yes;".to_string()];
+    let mut segments = vec!["    HEADER: load = HEADER, type = ro;".to_string()];
+    if rom.trainer.is_some() {
+        memory.push(format!(
+            "    TRAINER: start = ${:04X}, size = $0200, fill = yes;",
+            TRAINER_ADDRESS
+        ));
+        segments.push("    TRAINER: load = TRAINER, type = ro;".to_string());
+    }
+    for bank in rom.prg_data.banks.iter() {
+        memory.push(format!(
+            "    BANK_{:02}: start = ${:04X}, size = ${:04X}, fill = yes;",
+            bank.number,
+            bank.base_address,
+            bank.bytes.len()
+        ));
+        segments.push(format!(
+            "    BANK_{:02}: load = BANK_{:02}, type = ro;",
+            bank.number, bank.number
+        ));
+    }
+    if chr_size > 0 {
+        memory.push(format!(
+            "    CHR: start = $0000, size = ${:04X}, fill = yes;",
+            chr_size
+        ));
+        segments.push("    CHR: load = CHR, type = ro;".to_string());
+    }
+    if !rom.misc_rom.is_empty() {
+        memory.push(format!(
+            "    MISC: start = $0000, size = ${:04X}, fill = yes;",
+            rom.misc_rom.len()
+        ));
+        segments.push("    MISC: load = MISC, type = ro;".to_string());
+    }
+    [
+        vec!["MEMORY {".to_string()],
+        memory,
+        vec!["}".to_string(), "SEGMENTS {".to_string()],
+        segments,
+        vec!["}".to_string()],
+    ]
+    .concat()
+}
+
+/// NESASM writes the header itself from four directives, which only cover iNES 1.0 headers
+/// without a trainer.
+fn nesasm_header(rom: &RomReaderResult) -> Result<Vec<String>, ExportError> {
+    let header = &rom.header_bytes;
+    if header[7] & 0x0F != 0 || header[8..].iter().any(|b| *b != 0) || rom.trainer.is_some() {
+        return Err(ExportError::UnsupportedHeader(Dialect::Nesasm));
+    }
+    Ok(vec![
+        format!("    .inesprg {}", header[4]),
+        format!("    .ineschr {}", header[5]),
+        format!("    .inesmap {}", (header[6] >> 4) | (header[7] & 0xF0)),
+        format!("    .inesmir {}", header[6] & 0x0F),
+    ])
+}
+
+/// Generates an assembler project that builds back into exactly the loaded ROM.
+pub fn export_project(
+    rom: &RomReaderResult,
+    dialect: Dialect,
+) -> Result<Vec<ExportedFile>, ExportError> {
+    let prg_data = &rom.prg_data;
+    let chr = rom.chr_data.to_bytes();
+    let chr_size = chr.len();
+    let extension = dialect.source_extension();
+    let listings: Vec<Vec<ListingRow>> = (0..prg_data.banks.len())
+        .map(|bank_number| bank_listing(prg_data, bank_number))
+        .collect();
+    let defined_labels = defined_labels(prg_data, &listings);
+
+    let mut files = Vec::new();
+    let mut main = Vec::new();
+    match dialect {
+        Dialect::Ca65 => {
+            main.push(format!(
+                "; Build: ca65 main.{0} -o main.o && ld65 -C nes.cfg main.o -o rom.nes",
+                extension
+            ));
+            main.push(format!(".include \"constants.{}\"", extension));
+            main.push(".segment \"HEADER\"".to_string());
+            main.extend(dialect.byte_lines(&rom.header_bytes));
+            if rom.trainer.is_some() {
+                main.push(".segment \"TRAINER\"".to_string());
+                main.push("    .incbin \"trainer.bin\"".to_string());
+            }
+        }
+        Dialect::Asm6 => {
+            main.push(format!("; Build: asm6 main.{} rom.nes", extension));
+            main.push(format!("    .include \"constants.{}\"", extension));
+            main.extend(dialect.byte_lines(&rom.header_bytes));
+            if rom.trainer.is_some() {
+                main.push("    incbin \"trainer.bin\"".to_string());
+            }
+        }
+        Dialect::Nesasm => {
+            main.push(format!("; Build: nesasm main.{}", extension));
+            main.extend(nesasm_header(rom)?);
+            main.push(format!("    .include \"constants.{}\"", extension));
+        }
+    }
+
+    for (bank, listing) in prg_data.banks.iter().zip(listings.iter()) {
+        let file_name = format!("bank_{:02}.{}", bank.number, extension);
+        if dialect == Dialect::Ca65 {
+            main.push(format!(".segment \"BANK_{:02}\"", bank.number));
+        }
+        main.push(format!("    .include \"{}\"", file_name));
+        files.push(ExportedFile::text(
+            file_name,
+            bank_source(
+                dialect,
+                bank,
+                listing,
+                &defined_labels,
+                prg_data.text_table.as_ref(),
+            ),
+        ));
+        if dialect.incbin_directive().is_some() {
+            files.push(ExportedFile {
+                name: BankWriter::bin_file_name(bank.number),
+                contents: bank.bytes.clone(),
+            });
+        }
+    }
+
+    if dialect == Dialect::Nesasm {
+        let first_chr_bank = prg_data.banks.len() * PRG_BANK_SIZE / NESASM_BANK_SIZE;
+        for (index, chr_bank) in chr.chunks(CHR_BANK_SIZE).enumerate() {
+            let file_name = format!("chr_{:02}.bin", index);
+            main.push(format!("    .bank {}", first_chr_bank + index));
+            main.push("    .org $0000".to_string());
+            main.push(format!("    .incbin \"{}\"", file_name));
+            files.push(ExportedFile {
+                name: file_name,
+                contents: chr_bank.to_vec(),
+            });
+        }
+    } else {
+        let directive = dialect.incbin_directive().unwrap_or_default();
+        if !chr.is_empty() {
+            if dialect == Dialect::Ca65 {
+                main.push(".segment \"CHR\"".to_string());
+            }
+            main.push(format!("    {} \"chr.bin\"", directive));
+            files.push(ExportedFile {
+                name: "chr.bin".to_string(),
+                contents: chr,
+            });
+        }
+        if !rom.misc_rom.is_empty() {
+            if dialect == Dialect::Ca65 {
+                main.push(".segment \"MISC\"".to_string());
+            }
+            main.push(format!("    {} \"misc.bin\"", directive));
+            files.push(ExportedFile {
+                name: "misc.bin".to_string(),
+                contents: rom.misc_rom.clone(),
+            });
+        }
+    }
+
+    if let Some(trainer) = rom.trainer.as_ref() {
+        files.push(ExportedFile {
+            name: "trainer.bin".to_string(),
+            contents: trainer.clone(),
+        });
+    }
+    if dialect == Dialect::Ca65 {
+        files.push(ExportedFile::text(
+            "nes.cfg".to_string(),
+            ca65_linker_config(rom, chr_size),
+        ));
+    }
+    files.push(ExportedFile::text(
+        format!("constants.{}", extension),
+        constants_source(dialect, prg_data),
+    ));
+    files.insert(0, ExportedFile::text(format!("main.{}", extension), main));
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prg::{
+            data::{mmc1_prg, Location},
+            regions::{Region, RegionKind},
+            structs::StructTable,
+        },
+        reader::RomReader,
+    };
+
+    fn test_rom(header_flags: [u8; 4]) -> Vec<u8> {
+        let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 0x02, 0x01];
+        rom.extend_from_slice(&header_flags);
+        rom.extend_from_slice(&[0u8; 6]);
+        let mut prg: Vec<u8> = (0..PRG_BANK_SIZE * 2).map(|i| (i % 251) as u8).collect();
+        let code = [
+            0xA5, 0x10, // LDA $10
+            0xAD, 0x10, 0x00, // LDA $0010
+            0x8D, 0x00, 0x03, // STA $0300
+            0x20, 0x0E, 0xC0, // JSR $C00E
+            0x4C, 0x08, 0xC0, // JMP $C008
+            0x07, 0x10, // SLO $10
+            0x0A, // ASL A
+            0x6C, 0x00, 0x03, // JMP ($0300)
+        ];
+        prg[PRG_BANK_SIZE..PRG_BANK_SIZE + code.len()].copy_from_slice(&code);
+        prg[PRG_BANK_SIZE * 2 - 6..].copy_from_slice(&[0x00, 0xC0, 0x00, 0xC0, 0x00, 0xC0]);
+        rom.extend_from_slice(&prg);
+        rom.extend((0..CHR_BANK_SIZE).map(|i| (i % 13) as u8));
+        rom
+    }
+
+    /// A 4 bank MMC1 ROM whose switchable banks 0 and 1 both have code and data at $8000.
+    fn mmc1_rom() -> Vec<u8> {
+        let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 0x04, 0x01, 0x10, 0x00];
+        rom.extend_from_slice(&[0u8; 8]);
+        // LDA $8010; JMP $8000 in banks 0 and 1, LDA $8010; RTS in the fixed bank
+        let switchable: &[u8] = &[0xAD, 0x10, 0x80, 0x4C, 0x00, 0x80];
+        let fixed: &[u8] = &[0xAD, 0x10, 0x80, 0x60];
+        rom.extend(mmc1_prg([switchable, switchable, &[0x60], fixed]));
+        rom.extend((0..CHR_BANK_SIZE).map(|i| (i % 13) as u8));
+        rom
+    }
+
+    /// Reads `rom_bytes`, disassembling the switchable banks of `mmc1_rom` from $8000.
+    fn read_rom(rom_bytes: Vec<u8>) -> RomReaderResult {
+        let mut rom = RomReader::read(rom_bytes).unwrap();
+        if rom.prg_data.banks.len() > 2 {
+            rom.prg_data.import_regions([0, 1].map(|bank| {
+                let location = Location {
+                    bank: Some(bank),
+                    address: 0x8000,
+                };
+                let region = Region {
+                    kind: RegionKind::Code,
+                    length: 1,
+                };
+                (location, region)
+            }));
+        }
+        rom
+    }
+
+    fn file_text(files: &[ExportedFile], name: &str) -> String {
+        let file = files.iter().find(|file| file.name == name).unwrap();
+        String::from_utf8(file.contents.clone()).unwrap()
+    }
+
+    #[test]
+    fn export_ca65_project() {
+        let rom = RomReader::read(test_rom([0x01, 0x00, 0x00, 0x00])).unwrap();
+        let files = export_project(&rom, Dialect::Ca65).unwrap();
+
+        let main = file_text(&files, "main.s");
+        assert!(main.contains("    .byte $4E, $45, $53, $1A, $02, $01, $01, $00"));
+        assert!(main.contains(".segment \"BANK_01\"\n    .include \"bank_01.s\""));
+        assert!(main.contains(".segment \"CHR\"\n    .incbin \"chr.bin\""));
+
+        let bank = file_text(&files, "bank_01.s");
+        assert!(bank.starts_with(concat!(
+            "sub_C000:\n",
+            "    LDA data_0010\n",
+            "    LDA a:data_0010\n",
+            "    STA data_0300\n",
+            "loc_C008:\n",
+            "    JSR sub_C00E\n",
+            "    JMP loc_C008\n",
+            "sub_C00E:\n",
+            "; SLO $10\n",
+            "    .byte $07, $10\n",
+            "    ASL A\n",
+            "    JMP (data_0300)\n",
+            "    .incbin \"prg_01.bin\", $0014, $3FEC\n",
+        )));
+
+        let constants = file_text(&files, "constants.s");
+        assert_eq!(constants, "data_0010 = $10\ndata_0300 = $0300\n");
+        let config = file_text(&files, "nes.cfg");
+        assert!(config.contains("    BANK_01: start = $C000, size = $4000, fill = yes;"));
+    }
+
+    #[test]
+    fn export_regions() {
+        let mut rom = RomReader::read(test_rom([0x01, 0x00, 0x00, 0x00])).unwrap();
+        let location = |address| Location {
+            bank: Some(1),
+            address,
+        };
+        rom.prg_data
+            .set_region(location(0xC014), 2, Some(RegionKind::Word));
+        rom.prg_data
+            .set_region(location(0xC016), 2, Some(RegionKind::Text));
+        let files = export_project(&rom, Dialect::Asm6).unwrap();
+
+        let bank = file_text(&files, "bank_01.asm");
+        assert!(bank.contains("    JMP (data_0300)\n    .dw $5A59\n    .db \"[\", $5C\n"));
+    }
+
+    #[test]
+    fn export_struct_tables() {
+        let mut rom = RomReader::read(test_rom([0x01, 0x00, 0x00, 0x00])).unwrap();
+        let location = Location {
+            bank: Some(1),
+            address: 0xC014,
+        };
+        let table = StructTable::parse("Entry[2] kind:u8 count:u8 value:u16", 0).unwrap();
+        rom.prg_data.set_struct_table(location, table);
+
+        let files = export_project(&rom, Dialect::Asm6).unwrap();
+        let bank = file_text(&files, "bank_01.asm");
+        assert!(bank
+            .contains("; Entry 0\n    .db $59, $5A\n    .dw $5C5B\n; Entry 1\n    .db $5D, $5E\n"));
+        let constants = file_text(&files, "constants.asm");
+        assert!(constants.contains("; struct Entry\nEntry_kind = $00\nEntry_count = $01\nEntry_value = $02\nEntry_size = $04\n"));
+
+        let files = export_project(&rom, Dialect::Ca65).unwrap();
+        let constants = file_text(&files, "constants.s");
+        assert!(constants.contains(
+            ".struct Entry\n    kind .byte\n    count .byte\n    value .word\n.endstruct\n"
+        ));
+    }
+
+    #[test]
+    fn export_nesasm_project() {
+        let rom = RomReader::read(test_rom([0x01, 0x00, 0x00, 0x00])).unwrap();
+        let files = export_project(&rom, Dialect::Nesasm).unwrap();
+
+        let main = file_text(&files, "main.asm");
+        assert!(main.contains("    .inesprg 2\n    .ineschr 1\n    .inesmap 0\n    .inesmir 1\n"));
+        assert!(main.contains("    .bank 4\n    .org $0000\n    .incbin \"chr_00.bin\""));
+
+        let bank = file_text(&files, "bank_01.asm");
+        assert!(bank.starts_with("    .bank 2\n    .org $C000\nsub_C000:\n    LDA <data_0010\n"));
+        assert!(bank.contains("    JMP [data_0300]\n"));
+        assert!(bank.contains("    .bank 3\n    .org $E000\n"));
+    }
+
+    #[test]
+    fn nesasm_rejects_nes2_header() {
+        let rom = RomReader::read(test_rom([0x01, 0x08, 0x00, 0x00])).unwrap();
+        assert_eq!(
+            export_project(&rom, Dialect::Nesasm),
+            Err(ExportError::UnsupportedHeader(Dialect::Nesasm))
+        );
+    }
+
+    #[test]
+    fn export_labels_once() {
+        let rom = read_rom(mmc1_rom());
+        for dialect in Dialect::ALL {
+            let files = export_project(&rom, dialect).unwrap();
+            let mut defined = HashSet::new();
+            let definitions = files
+                .iter()
+                .filter_map(|file| String::from_utf8(file.contents.clone()).ok())
+                .flat_map(|text| {
+                    text.lines()
+                        .filter_map(|line| {
+                            let name = line
+                                .strip_suffix(':')
+                                .or_else(|| line.split_once(" = ").map(|(name, _)| name))?;
+                            (!name.starts_with([' ', ';'])).then(|| name.to_string())
+                        })
+                        .collect::<Vec<String>>()
+                });
+            for name in definitions {
+                assert!(
+                    defined.insert(name.clone()),
+                    "{} defines {} twice",
+                    dialect,
+                    name
+                );
+            }
+            assert!(defined.contains("loc_00_8000") && defined.contains("loc_01_8000"));
+            assert!(defined.contains("data_8010"));
+        }
+    }
+
+    fn assemble_and_compare(dialect: Dialect, commands: &[&[&str]]) {
+        let fixtures = [
+            ("nrom", test_rom([0x01, 0x00, 0x00, 0x00])),
+            ("mmc1", mmc1_rom()),
+        ];
+        for (fixture, rom_bytes) in fixtures {
+            let rom = read_rom(rom_bytes.clone());
+            let directory =
+                std::env::temp_dir().join(format!("nes-cart-reader-{}-{}", dialect, fixture));
+            std::fs::create_dir_all(&directory).unwrap();
+            for file in export_project(&rom, dialect).unwrap() {
+                std::fs::write(directory.join(&file.name), &file.contents).unwrap();
+            }
+            for command in commands {
+                let status = std::process::Command::new(command[0])
+                    .args(&command[1..])
+                    .current_dir(&directory)
+                    .status()
+                    .unwrap();
+                assert!(status.success(), "{:?} failed on {}", command, fixture);
+            }
+            let assembled = std::fs::read(directory.join("rom.nes")).unwrap();
+            assert_eq!(zip::crc32(&assembled), zip::crc32(&rom_bytes));
+            assert_eq!(assembled, rom_bytes, "{} doesn't reassemble", fixture);
+        }
+    }
+
+    #[test]
+    #[ignore = "requires ca65 and ld65 in PATH"]
+    fn round_trip_ca65() {
+        assemble_and_compare(
+            Dialect::Ca65,
+            &[
+                &["ca65", "main.s", "-o", "main.o"],
+                &["ld65", "-C", "nes.cfg", "main.o", "-o", "rom.nes"],
+            ],
+        );
+    }
+
+    #[test]
+    #[ignore = "requires asm6 in PATH"]
+    fn round_trip_asm6() {
+        assemble_and_compare(Dialect::Asm6, &[&["asm6", "main.asm", "rom.nes"]]);
+    }
+
+    #[test]
+    #[ignore = "requires nesasm in PATH"]
+    fn round_trip_nesasm() {
+        assemble_and_compare(
+            Dialect::Nesasm,
+            &[&["nesasm", "main.asm"], &["mv", "main.nes", "rom.nes"]],
+        );
+    }
+}
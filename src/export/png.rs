@@ -0,0 +1,642 @@
+use std::{error, fmt};
+
+use super::zip::crc32;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const COLOR_TYPE_INDEXED: u8 = 3;
+const COLOR_TYPE_RGBA: u8 = 6;
+/// Largest stored deflate block.
+const MAX_BLOCK_SIZE: usize = 0xFFFF;
+
+const LENGTH_BASE: [usize; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [usize; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order the code length code lengths of a dynamic block come in.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidPngError {
+    Signature,
+    Truncated,
+    Checksum,
+    Compression,
+    Unsupported(String),
+}
+
+impl fmt::Display for InvalidPngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidPngError::Signature => write!(f, "Not a PNG file"),
+            InvalidPngError::Truncated => write!(f, "The PNG file is truncated"),
+            InvalidPngError::Checksum => write!(f, "The PNG file is corrupted"),
+            InvalidPngError::Compression => write!(f, "The PNG image data can't be decompressed"),
+            InvalidPngError::Unsupported(feature) => {
+                write!(f, "PNG {} aren't supported", feature)
+            }
+        }
+    }
+}
+
+impl error::Error for InvalidPngError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    const MODULO: u32 = 65521;
+    let (a, b) = bytes.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % MODULO;
+        (a, (b + a) % MODULO)
+    });
+    (b << 16) | a
+}
+
+/// Wraps `bytes` in a zlib stream of uncompressed blocks.
+fn zlib_stored(bytes: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let block_count = bytes.len().div_ceil(MAX_BLOCK_SIZE).max(1);
+    for index in 0..block_count {
+        let block = &bytes[(index * MAX_BLOCK_SIZE).min(bytes.len())
+            ..((index + 1) * MAX_BLOCK_SIZE).min(bytes.len())];
+        stream.push((index + 1 == block_count) as u8);
+        stream.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        stream.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(bytes).to_be_bytes());
+    stream
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Builds a PNG from `rows` of already packed pixels, each prefixed with the "none" filter.
+fn encode(
+    width: usize,
+    height: usize,
+    header: (u8, u8),
+    palette: Option<&[u32]>,
+    rows: Vec<&[u8]>,
+) -> Vec<u8> {
+    let (bit_depth, color_type) = header;
+    let mut png = SIGNATURE.to_vec();
+    let ihdr = [
+        (width as u32).to_be_bytes().as_slice(),
+        &(height as u32).to_be_bytes(),
+        &[bit_depth, color_type, 0, 0, 0],
+    ]
+    .concat();
+    chunk(&mut png, b"IHDR", &ihdr);
+    if let Some(palette) = palette {
+        let plte = palette
+            .iter()
+            .flat_map(|color| [(color >> 24) as u8, (color >> 16) as u8, (color >> 8) as u8])
+            .collect::<Vec<u8>>();
+        chunk(&mut png, b"PLTE", &plte);
+    }
+    let scanlines = rows
+        .into_iter()
+        .flat_map(|row| std::iter::once(&0u8).chain(row.iter()).copied())
+        .collect::<Vec<u8>>();
+    chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Encodes 2 bit palette indices, one per pixel, with a PLTE of the RGBA `palette` colors.
+pub fn encode_indexed(width: usize, height: usize, indices: &[u8], palette: &[u32]) -> Vec<u8> {
+    let packed = indices
+        .chunks(width.max(1))
+        .map(|row| {
+            row.chunks(4)
+                .map(|pixels| {
+                    pixels.iter().enumerate().fold(0u8, |byte, (index, pixel)| {
+                        byte | (pixel & 3) << (6 - index * 2)
+                    })
+                })
+                .collect::<Vec<u8>>()
+        })
+        .collect::<Vec<Vec<u8>>>();
+    let rows = packed.iter().map(Vec::as_slice).collect();
+    encode(width, height, (2, COLOR_TYPE_INDEXED), Some(palette), rows)
+}
+
+/// Encodes 8 bit RGBA pixels.
+pub fn encode_rgba(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    let rows = pixels.chunks((width * 4).max(1)).collect();
+    encode(width, height, (8, COLOR_TYPE_RGBA), None, rows)
+}
+
+/// Reads the bits of a deflate stream, least significant first.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<usize, InvalidPngError> {
+        let mut value = 0;
+        for bit in 0..count {
+            let byte = self
+                .bytes
+                .get(self.position / 8)
+                .ok_or(InvalidPngError::Compression)?;
+            value |= ((byte >> (self.position % 8)) as usize & 1) << bit;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+}
+
+/// Canonical Huffman code, as the number of codes of each length and the symbols in code order.
+struct Huffman {
+    counts: [usize; 16],
+    symbols: Vec<usize>,
+}
+
+impl Huffman {
+    fn new(lengths: &[usize]) -> Huffman {
+        let mut counts = [0; 16];
+        for length in lengths {
+            counts[*length] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols = (0..lengths.len())
+            .filter(|symbol| lengths[*symbol] != 0)
+            .collect::<Vec<usize>>();
+        symbols.sort_by_key(|symbol| lengths[*symbol]);
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<usize, InvalidPngError> {
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for count in &self.counts[1..] {
+            code |= reader.bits(1)?;
+            if code < first + count {
+                return self
+                    .symbols
+                    .get(index + code - first)
+                    .copied()
+                    .ok_or(InvalidPngError::Compression);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(InvalidPngError::Compression)
+    }
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), InvalidPngError> {
+    let literal_count = reader.bits(5)? + 257;
+    let distance_count = reader.bits(5)? + 1;
+    let code_length_count = reader.bits(4)? + 4;
+    let mut code_lengths = [0; 19];
+    for symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[*symbol] = reader.bits(3)?;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code_length_code.decode(reader)? {
+            16 => (
+                *lengths.last().ok_or(InvalidPngError::Compression)?,
+                reader.bits(2)? + 3,
+            ),
+            17 => (0, reader.bits(3)? + 3),
+            18 => (0, reader.bits(7)? + 11),
+            length => (length, 1),
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat));
+    }
+    if lengths.len() != literal_count + distance_count {
+        return Err(InvalidPngError::Compression);
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let lengths = (0..288)
+        .map(|symbol| match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        })
+        .collect::<Vec<usize>>();
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+/// Decompresses a zlib stream.
+fn inflate(stream: &[u8]) -> Result<Vec<u8>, InvalidPngError> {
+    let header = stream.get(..2).ok_or(InvalidPngError::Compression)?;
+    if header[0] & 0x0F != 8
+        || header[1] & 0x20 != 0
+        || u16::from_be_bytes([header[0], header[1]]) % 31 != 0
+    {
+        return Err(InvalidPngError::Compression);
+    }
+    let mut reader = BitReader {
+        bytes: &stream[2..],
+        position: 0,
+    };
+    let mut output = Vec::new();
+    loop {
+        let last = reader.bits(1)? == 1;
+        let (literals, distances) = match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let start = reader.position / 8;
+                let header = reader
+                    .bytes
+                    .get(start..start + 4)
+                    .ok_or(InvalidPngError::Compression)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                if len != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err(InvalidPngError::Compression);
+                }
+                let block = reader
+                    .bytes
+                    .get(start + 4..start + 4 + len as usize)
+                    .ok_or(InvalidPngError::Compression)?;
+                output.extend_from_slice(block);
+                reader.position = (start + 4 + len as usize) * 8;
+                if last {
+                    break;
+                }
+                continue;
+            }
+            1 => fixed_codes(),
+            2 => dynamic_codes(&mut reader)?,
+            _ => return Err(InvalidPngError::Compression),
+        };
+        loop {
+            let symbol = literals.decode(&mut reader)?;
+            match symbol {
+                0..=255 => output.push(symbol as u8),
+                256 => break,
+                _ => {
+                    let index = symbol - 257;
+                    let length = LENGTH_BASE.get(index).ok_or(InvalidPngError::Compression)?
+                        + reader.bits(LENGTH_EXTRA_BITS[index])?;
+                    let index = distances.decode(&mut reader)?;
+                    let distance = DISTANCE_BASE
+                        .get(index)
+                        .ok_or(InvalidPngError::Compression)?
+                        + reader.bits(DISTANCE_EXTRA_BITS[index])?;
+                    let start = output
+                        .len()
+                        .checked_sub(distance)
+                        .ok_or(InvalidPngError::Compression)?;
+                    for offset in 0..length {
+                        output.push(output[start + offset]);
+                    }
+                }
+            }
+        }
+        if last {
+            break;
+        }
+    }
+    let end = reader.position.div_ceil(8);
+    let checksum = reader
+        .bytes
+        .get(end..end + 4)
+        .ok_or(InvalidPngError::Compression)?;
+    if checksum != adler32(&output).to_be_bytes() {
+        return Err(InvalidPngError::Checksum);
+    }
+    Ok(output)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let distances = [left, up, up_left].map(|value| (estimate - value as i16).abs());
+    if distances[0] <= distances[1] && distances[0] <= distances[2] {
+        left
+    } else if distances[1] <= distances[2] {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Reverses the filter of each scanline, returning the raw rows.
+fn unfilter(
+    data: &[u8],
+    stride: usize,
+    height: usize,
+    pixel_size: usize,
+) -> Result<Vec<Vec<u8>>, InvalidPngError> {
+    let mut rows: Vec<Vec<u8>> = Vec::with_capacity(height);
+    for scanline in data.chunks(stride + 1).take(height) {
+        let (&filter, bytes) = scanline.split_first().ok_or(InvalidPngError::Truncated)?;
+        if bytes.len() != stride {
+            return Err(InvalidPngError::Truncated);
+        }
+        let previous = rows.last().cloned().unwrap_or_else(|| vec![0; stride]);
+        let mut row = bytes.to_vec();
+        for index in 0..stride {
+            let left = index.checked_sub(pixel_size).map_or(0, |left| row[left]);
+            let up_left = index
+                .checked_sub(pixel_size)
+                .map_or(0, |left| previous[left]);
+            let up = previous[index];
+            let prediction = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(InvalidPngError::Unsupported(format!("filter {}", filter))),
+            };
+            row[index] = row[index].wrapping_add(prediction);
+        }
+        rows.push(row);
+    }
+    if rows.len() != height {
+        return Err(InvalidPngError::Truncated);
+    }
+    Ok(rows)
+}
+
+/// Decodes a non-interlaced PNG into its width, height and the RGBA color of each pixel.
+pub fn decode(png: &[u8]) -> Result<(usize, usize, Vec<u32>), InvalidPngError> {
+    if png.get(..8) != Some(&SIGNATURE[..]) {
+        return Err(InvalidPngError::Signature);
+    }
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut data = Vec::new();
+    let mut offset = 8;
+    while offset < png.len() {
+        let len = png
+            .get(offset..offset + 4)
+            .map(|len| u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize)
+            .ok_or(InvalidPngError::Truncated)?;
+        let end = (offset + 12)
+            .checked_add(len)
+            .ok_or(InvalidPngError::Truncated)?;
+        let body = png
+            .get(offset + 4..end - 4)
+            .ok_or(InvalidPngError::Truncated)?;
+        let crc = png.get(end - 4..end).ok_or(InvalidPngError::Truncated)?;
+        if crc != crc32(body).to_be_bytes() {
+            return Err(InvalidPngError::Checksum);
+        }
+        let (kind, contents) = body.split_at(4);
+        match kind {
+            b"IHDR" if contents.len() == 13 => header = Some(contents.to_vec()),
+            b"PLTE" if !contents.len().is_multiple_of(3) => return Err(InvalidPngError::Truncated),
+            b"PLTE" => {
+                palette = contents
+                    .chunks_exact(3)
+                    .map(|rgb| u32::from_be_bytes([rgb[0], rgb[1], rgb[2], 0xFF]))
+                    .collect()
+            }
+            b"tRNS" => {
+                for (color, alpha) in palette.iter_mut().zip(contents) {
+                    *color = (*color & !0xFF) | *alpha as u32;
+                }
+            }
+            b"IDAT" => data.extend_from_slice(contents),
+            b"IEND" => break,
+            _ => {}
+        }
+        offset = end;
+    }
+
+    let header = header.ok_or(InvalidPngError::Truncated)?;
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let (bit_depth, color_type, interlace) = (header[8] as usize, header[9], header[12]);
+    if interlace != 0 {
+        return Err(InvalidPngError::Unsupported(
+            "interlaced images".to_string(),
+        ));
+    }
+    let channels = match (color_type, bit_depth) {
+        (0, 1 | 2 | 4 | 8 | 16) | (3, 1 | 2 | 4 | 8) => 1,
+        (4, 8 | 16) => 2,
+        (2, 8 | 16) => 3,
+        (6, 8 | 16) => 4,
+        _ => {
+            return Err(InvalidPngError::Unsupported(format!(
+                "images of color type {} and bit depth {}",
+                color_type, bit_depth
+            )))
+        }
+    };
+    // The size comes from the file, it has to fit the image data before anything is allocated.
+    let stride = width
+        .checked_mul(channels * bit_depth)
+        .ok_or(InvalidPngError::Truncated)?
+        .div_ceil(8);
+    let pixel_size = (channels * bit_depth / 8).max(1);
+    let image_data = inflate(&data)?;
+    if (stride + 1)
+        .checked_mul(height)
+        .is_none_or(|len| len > image_data.len())
+    {
+        return Err(InvalidPngError::Truncated);
+    }
+    let rows = unfilter(&image_data, stride, height, pixel_size)?;
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in rows {
+        for x in 0..width {
+            // Samples of the pixel, keeping the high byte of 16 bit ones.
+            let sample = |channel: usize| match bit_depth {
+                16 => row[(x * channels + channel) * 2],
+                8 => row[x * channels + channel],
+                _ => {
+                    let bit = x * bit_depth;
+                    let value =
+                        (row[bit / 8] >> (8 - bit_depth - bit % 8)) & ((1 << bit_depth) - 1);
+                    match color_type {
+                        3 => value,
+                        _ => (value as usize * 255 / ((1 << bit_depth) - 1)) as u8,
+                    }
+                }
+            };
+            let [red, green, blue, alpha] = match color_type {
+                0 => [sample(0), sample(0), sample(0), 0xFF],
+                2 => [sample(0), sample(1), sample(2), 0xFF],
+                3 => palette
+                    .get(sample(0) as usize)
+                    .ok_or(InvalidPngError::Truncated)?
+                    .to_be_bytes(),
+                4 => [sample(0), sample(0), sample(0), sample(1)],
+                _ => [sample(0), sample(1), sample(2), sample(3)],
+            };
+            pixels.push(u32::from_be_bytes([red, green, blue, alpha]));
+        }
+    }
+    Ok((width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chunks of `png` as (type, data), checking their CRCs.
+    fn chunks(png: &[u8]) -> Vec<(String, Vec<u8>)> {
+        assert_eq!(png[..8], SIGNATURE);
+        let mut chunks = Vec::new();
+        let mut offset = 8;
+        while offset < png.len() {
+            let len = u32::from_be_bytes(png[offset..offset + 4].try_into().unwrap()) as usize;
+            let body = &png[offset + 4..offset + 8 + len];
+            let crc =
+                u32::from_be_bytes(png[offset + 8 + len..offset + 12 + len].try_into().unwrap());
+            assert_eq!(crc32(body), crc);
+            chunks.push((
+                String::from_utf8(body[..4].to_vec()).unwrap(),
+                body[4..].to_vec(),
+            ));
+            offset += 12 + len;
+        }
+        chunks
+    }
+
+    #[test]
+    fn encode_indexed_image() {
+        let indices = [0, 1, 2, 3, 3, 2, 1, 0, 1, 1];
+        let palette = [0x000000FF, 0xFF0000FF, 0x00FF00FF, 0x0000FFFF];
+        let chunks = chunks(&encode_indexed(5, 2, &indices, &palette));
+        let kinds = chunks
+            .iter()
+            .map(|(kind, _)| kind.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(kinds, ["IHDR", "PLTE", "IDAT", "IEND"]);
+        assert_eq!(chunks[0].1, [0, 0, 0, 5, 0, 0, 0, 2, 2, 3, 0, 0, 0]);
+        assert_eq!(chunks[1].1[3..6], [0xFF, 0, 0]);
+        let idat = &chunks[2].1;
+        // zlib header, one final stored block of two filtered rows of two bytes
+        assert_eq!(idat[..7], [0x78, 0x01, 0x01, 6, 0, !6, 0xFF]);
+        assert_eq!(idat[7..13], [0, 0x1B, 0xC0, 0, 0x91, 0x40]);
+        assert_eq!(idat[13..], adler32(&idat[7..13]).to_be_bytes());
+    }
+
+    #[test]
+    fn encode_large_rgba_image() {
+        let pixels = vec![0x7F; 256 * 128 * 4];
+        let chunks = chunks(&encode_rgba(256, 128, &pixels));
+        assert_eq!(chunks[0].1[8..10], [8, 6]);
+        let idat = &chunks[1].1;
+        let scanlines = 128 * (1 + 256 * 4);
+        // Two full blocks and the final one, each with a five byte header.
+        assert_eq!(idat.len(), 2 + scanlines + 3 * 5 + 4);
+        assert_eq!(idat[2], 0);
+        assert_eq!(idat[2 + 5 + MAX_BLOCK_SIZE], 0);
+        assert_eq!(idat[2 + 2 * (5 + MAX_BLOCK_SIZE)], 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+    }
+
+    #[test]
+    fn inflate_streams() {
+        let fixed = [
+            0x78, 0x9C, 0x4B, 0x4C, 0x4A, 0x4E, 0x44, 0x42, 0x11, 0x00, 0x34, 0x0C, 0x06, 0x17,
+        ];
+        assert_eq!(inflate(&fixed).unwrap(), b"abcabcabcabcabcX");
+        let dynamic = [
+            0x78, 0xDA, 0x2D, 0x8F, 0xD9, 0x0D, 0x44, 0x31, 0x08, 0xC4, 0x6A, 0x03, 0x12, 0x20,
+            0xE1, 0x0A, 0xFD, 0x37, 0xB3, 0xAC, 0xF4, 0xE6, 0xDF, 0xB2, 0x07, 0x00, 0x68, 0x6D,
+            0xBD, 0x5E, 0xB0, 0xD4, 0xDF, 0xD2, 0x40, 0x09, 0x3A, 0xC5, 0x41, 0x4E, 0x46, 0xBE,
+            0x52, 0xDA, 0x56, 0xDD, 0xF5, 0x5C, 0xF0, 0xB9, 0x32, 0x75, 0x85, 0xDD, 0xA3, 0x2A,
+            0xA2, 0x7A, 0xCC, 0xE3, 0x01, 0xC9, 0x8D, 0x1E, 0x12, 0xD8, 0x9B, 0x03, 0x4F, 0x4B,
+            0x72, 0xEE, 0x92, 0x77, 0xD0, 0xB9, 0x8D, 0xDB, 0x95, 0x5E, 0x9C, 0x4D, 0x9D, 0x61,
+            0x47, 0x85, 0x67, 0x43, 0x5F, 0xFB, 0x58, 0xD8, 0x27, 0xC7, 0x8A, 0x5A, 0xCB, 0x86,
+            0x3E, 0xAD, 0x70, 0x31, 0xF8, 0xDD, 0xF1, 0xEE, 0x0E, 0x5D, 0x90, 0x76, 0x98, 0xB0,
+            0xAB, 0x32, 0x66, 0x59, 0x0F, 0x70, 0xC9, 0xF1, 0x87, 0xFB, 0x26, 0xC8, 0x58, 0x1D,
+            0xB4, 0x76, 0x4C, 0xB0, 0x51, 0xEC, 0x54, 0xB0, 0xBF, 0x17, 0xC6, 0x09, 0x65, 0xD3,
+            0x0C, 0x13, 0x1D, 0x6E, 0xEE, 0x3E, 0x70, 0x7F, 0x2C, 0x5B, 0xE1, 0x44, 0x6B, 0xD2,
+            0x6D, 0x29, 0x9E, 0x66, 0x7E, 0x0A, 0xBE, 0xE7, 0x6C, 0x0D, 0x9C, 0x77, 0xBE, 0xA6,
+            0x1F, 0xE1, 0x45, 0x84, 0x80, 0x88, 0x3F, 0x48, 0x4C, 0x55, 0x79,
+        ];
+        let expected = (0..300usize)
+            .map(|i| ((i * i / 7 + i / 3) % 17 + 65) as u8)
+            .collect::<Vec<u8>>();
+        assert_eq!(inflate(&dynamic).unwrap(), expected);
+        assert_eq!(inflate(&fixed[..12]), Err(InvalidPngError::Compression));
+    }
+
+    #[test]
+    fn decode_round_trip() {
+        let palette = [0x000000FF, 0xFF0000FF, 0x00FF00FF, 0x0000FFFF];
+        let indices = [0, 1, 2, 3, 3, 2, 1, 0, 1, 1];
+        let expected = indices.map(|index| palette[index as usize]).to_vec();
+        let png = encode_indexed(5, 2, &indices, &palette);
+        assert_eq!(decode(&png), Ok((5, 2, expected.clone())));
+
+        let pixels = expected
+            .iter()
+            .flat_map(|color| color.to_be_bytes())
+            .collect::<Vec<u8>>();
+        assert_eq!(decode(&encode_rgba(5, 2, &pixels)), Ok((5, 2, expected)));
+        assert_eq!(decode(b"GIF89a"), Err(InvalidPngError::Signature));
+    }
+
+    #[test]
+    fn decode_malformed_images() {
+        let mut png = SIGNATURE.to_vec();
+        chunk(&mut png, b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 3, 0, 0, 0]);
+        chunk(&mut png, b"PLTE", &[0, 0, 0, 0]);
+        assert_eq!(decode(&png), Err(InvalidPngError::Truncated));
+
+        let mut png = SIGNATURE.to_vec();
+        let size = [0xFF; 4];
+        chunk(
+            &mut png,
+            b"IHDR",
+            &[&size[..], &size, &[16, 6, 0, 0, 0]].concat(),
+        );
+        chunk(&mut png, b"IDAT", &zlib_stored(&[0; 16]));
+        assert_eq!(decode(&png), Err(InvalidPngError::Truncated));
+    }
+
+    #[test]
+    fn unfilter_rows() {
+        // Sub, up, average and Paeth filters on 2 byte pixels.
+        let data = [
+            1, 10, 20, 5, 5, //
+            2, 1, 1, 1, 1, //
+            3, 4, 4, 4, 4, //
+            4, 1, 1, 1, 1,
+        ];
+        let rows = unfilter(&data, 4, 4, 2).unwrap();
+        assert_eq!(rows[0], [10, 20, 15, 25]);
+        assert_eq!(rows[1], [11, 21, 16, 26]);
+        assert_eq!(rows[2], [9, 14, 16, 24]);
+        assert_eq!(rows[3], [10, 15, 17, 25]);
+    }
+}
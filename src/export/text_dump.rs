@@ -0,0 +1,58 @@
+use crate::prg::{
+    data::{Location, PrgData},
+    regions::RegionKind,
+    text_table::TextTable,
+};
+
+/// Lists every string of the text regions after its location, one per line. Strings are
+/// split on the end codes of the text table, line breaks within them are written as `\n`.
+pub fn text_dump(prg_data: &PrgData) -> String {
+    let ascii = TextTable::ascii();
+    let table = prg_data.text_table.as_ref().unwrap_or(&ascii);
+    let mut dump = String::new();
+    for (start, region) in prg_data.regions.iter() {
+        let Some(bank) = start.bank.filter(|_| region.kind == RegionKind::Text) else {
+            continue;
+        };
+        let bank = &prg_data.banks[bank];
+        let first = (start.address - bank.base_address) as usize;
+        let end = (first + region.length).min(bank.bytes.len());
+        let mut offset = first;
+        while offset < end {
+            let bytes = &bank.bytes[offset..end];
+            let length = table.string_length(bytes).unwrap_or(bytes.len());
+            let location = Location {
+                bank: start.bank,
+                address: bank.base_address + offset as u16,
+            };
+            let text = table.decode(&bytes[..length]).replace('\n', "\\n");
+            dump.push_str(&format!("{}: {}\n", location, text));
+            offset += length;
+        }
+    }
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prg::data::nrom_prg;
+
+    #[test]
+    fn dump_strings_of_text_regions() {
+        let mut prg = nrom_prg(&[]);
+        prg[0x100..0x107].copy_from_slice(&[0x07, 0x00, 0xFE, 0x0C, 0xFF, 0x0C, 0xFF]);
+        let mut prg_data = PrgData::parse(prg).unwrap();
+        let location = Location {
+            bank: Some(0),
+            address: 0xC100,
+        };
+        prg_data.set_region(location, 7, Some(RegionKind::Text));
+        prg_data.text_table = Some(TextTable::parse("00=A\n07=H\n0C=M\n*FE\n/FF=<end>").unwrap());
+
+        assert_eq!(
+            text_dump(&prg_data),
+            "00:C100: HA\\nM<end>\n00:C105: M<end>\n"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034B50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x02014B50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054B50;
+const VERSION: u16 = 20;
+// 1980-01-01, the earliest date a zip entry can hold
+const DOS_DATE: u16 = 0x21;
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Packs `files` into an uncompressed zip archive.
+pub fn zip(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    let mut central_directory = Vec::new();
+    for (name, contents) in files {
+        let offset = archive.len() as u32;
+        let crc = crc32(contents);
+        let size = contents.len() as u32;
+
+        archive.extend_from_slice(&LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes());
+        archive.extend_from_slice(&VERSION.to_le_bytes());
+        let common_fields = [
+            0u16.to_le_bytes(), // flags
+            0u16.to_le_bytes(), // stored, no compression
+            0u16.to_le_bytes(), // time
+            DOS_DATE.to_le_bytes(),
+        ]
+        .concat();
+        archive.extend_from_slice(&common_fields);
+        archive.extend_from_slice(&crc.to_le_bytes());
+        archive.extend_from_slice(&size.to_le_bytes());
+        archive.extend_from_slice(&size.to_le_bytes());
+        archive.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&0u16.to_le_bytes());
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(contents);
+
+        central_directory.extend_from_slice(&CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        central_directory.extend_from_slice(&VERSION.to_le_bytes());
+        central_directory.extend_from_slice(&VERSION.to_le_bytes());
+        central_directory.extend_from_slice(&common_fields);
+        central_directory.extend_from_slice(&crc.to_le_bytes());
+        central_directory.extend_from_slice(&size.to_le_bytes());
+        central_directory.extend_from_slice(&size.to_le_bytes());
+        central_directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        // Extra field, comment, disk number, internal and external attributes
+        central_directory.extend_from_slice(&[0u8; 12]);
+        central_directory.extend_from_slice(&offset.to_le_bytes());
+        central_directory.extend_from_slice(name.as_bytes());
+    }
+
+    let central_directory_offset = archive.len() as u32;
+    archive.extend_from_slice(&central_directory);
+    archive.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+    archive.extend_from_slice(&[0u8; 4]); // disk numbers
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(central_directory.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&central_directory_offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    archive
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn zip_layout() {
+        let archive = zip(&[("a.txt".to_string(), b"abc".to_vec())]);
+        assert_eq!(&archive[0..4], &LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes());
+        assert_eq!(&archive[30..35], b"a.txt");
+        assert_eq!(&archive[35..38], b"abc");
+        let end = archive.len() - 22;
+        assert_eq!(
+            &archive[end..end + 4],
+            &END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes()
+        );
+        // Central directory starts right after the only entry
+        assert_eq!(&archive[end + 16..end + 20], &38u32.to_le_bytes());
+    }
+}
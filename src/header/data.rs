@@ -124,7 +124,7 @@ impl HeaderData {
             chr_rom_size: header_data[5] as u64 * CHR_ROM_BANK_SIZE,
             mapper: ((flags6 >> 4) | (flags7 & 0xF0)) as u32,
             submapper: 0,
-            trainer_present: ((flags6 >> 3) & 1) > 0,
+            trainer_present: ((flags6 >> 2) & 1) > 0,
             mirroring: HeaderData::nametable_layout(&flags6),
             nes2: false,
             prg_ram_size: (if flags8 == 0 { 1 } else { flags8 }) as u64 * PRG_RAM_BANK_SIZE,
@@ -182,7 +182,7 @@ impl HeaderData {
             chr_rom_size: HeaderData::ines2_rom_size(chr_rom_size_bytes, CHR_ROM_BANK_SIZE),
             mapper: ((flags6 >> 4) | (flags7 & 0xF0) | (flags8 << 4)) as u32,
             submapper: (flags8 >> 4) as u32,
-            trainer_present: ((flags6 >> 3) & 1) > 0,
+            trainer_present: ((flags6 >> 2) & 1) > 0,
             mirroring: HeaderData::nametable_layout(&flags6),
             nes2: true,
             prg_ram_size: HeaderData::ines2_ram_size(flags10 & 0xF),
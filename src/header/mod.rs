@@ -7,6 +7,9 @@ use yew::prelude::*;
 #[derive(Properties, PartialEq)]
 pub struct HeaderProps {
     pub rom_header: Option<HeaderData>,
+    /// Engines and routines recognised in the code.
+    #[prop_or_default]
+    pub detected: Vec<String>,
 }
 
 fn header_data_to_list(header: &Option<HeaderData>) -> Vec<(&'static str, String)> {
@@ -114,7 +117,13 @@ fn header_data_to_list(header: &Option<HeaderData>) -> Vec<(&'static str, String
 
 #[function_component(Header)]
 pub fn header(props: &HeaderProps) -> Html {
-    let header_list = header_data_to_list(&props.rom_header);
+    let mut header_list = header_data_to_list(&props.rom_header);
+    header_list.extend(
+        props
+            .detected
+            .iter()
+            .map(|description| ("Detected", description.clone())),
+    );
     html! {
         <Box class={classes!("border", "border-base-300", "box-border")}>
             <Box class={classes!("text-xs")}>
@@ -1,38 +1,141 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
-use chr::Chr;
+use cdl::CodeDataLog;
+use chr::{
+    data::{import_png, Tile, TILES_PER_PATTERN_TABLE},
+    palette::MasterPalette,
+    Chr, PngImport,
+};
+use diff::Diff;
+use export::{bps::bps, export_project, ips::ips, text_dump::text_dump, zip::zip, Dialect};
 use gloo::file::{
-    callbacks::{read_as_bytes, FileReader},
+    callbacks::{read_as_bytes, read_as_text, FileReader},
     File,
 };
 use header::Header;
-use prg::Prg;
+use prg::{
+    data::{Location, PrgData},
+    regions::RegionKind,
+    script::{dump_script, insert_script},
+    signatures::Signature,
+    structs::StructTable,
+    symbols::SymbolFile,
+    text_table::TextTable,
+    Prg,
+};
+use project::{
+    indexed_db::{self, StorageError},
+    Project,
+};
 use reader::{RomReader, RomReaderResult};
 use ui::{
+    button::{Button, ButtonColor},
     dialog::{AlertDialog, Dialog},
+    download::download,
     input::FileInput,
     navbar::Navbar,
     r#box::Box,
+    select::Select,
 };
 use uuid::Uuid;
 use yew::prelude::*;
 
+mod cdl;
 mod chr;
+mod diff;
+mod export;
 mod header;
 mod prg;
+mod project;
 mod reader;
 mod ui;
 
 pub struct App {
     readers: HashMap<String, FileReader>,
     result: Option<RomReaderResult>,
+    /// Another revision of the ROM to diff against.
+    comparison: Option<PrgData>,
+    /// Master palette loaded from a .pal file.
+    palette: Option<MasterPalette>,
     error: String,
+    export_dialect: Dialect,
 }
 
 pub enum AppMessage {
     Uploaded(File),
     LoadSuccess(String, Vec<u8>),
     LoadFailure(String, String),
+    ComparisonUploaded(File),
+    ComparisonLoaded(String, Vec<u8>),
+    RenameLabel(Location, String),
+    SetComment(Location, String),
+    SetRegion(Location, usize, Option<RegionKind>),
+    SetStructTable(Location, StructTable),
+    Patch(Location, Vec<u8>),
+    SymbolsUploaded(File),
+    SymbolsLoaded(String, String, String),
+    ImportFailure(String, String),
+    CodeDataLogUploaded(File),
+    CodeDataLogLoaded(String, Vec<u8>),
+    PaletteUploaded(File),
+    ChrPngUploaded(File, PngImport),
+    EditTile(usize, usize, Tile),
+    ChrPngLoaded(String, Vec<u8>, PngImport),
+    PaletteLoaded(String, Vec<u8>),
+    TextTableUploaded(File),
+    TextTableLoaded(String, String),
+    SignaturesUploaded(File),
+    SignaturesLoaded(String, String),
+    ScriptUploaded(File),
+    ScriptLoaded(String, String),
+    ProjectRestored(String, Result<Option<String>, StorageError>),
+    ProjectSaved(Result<(), StorageError>),
+    ProjectUploaded(File),
+    ProjectLoaded(String, String),
+    ExportProject,
+    SelectExportDialect(usize),
+    Export,
+    DownloadRom,
+    DownloadIps,
+    DownloadBps,
+    DownloadTextDump,
+    DownloadScript,
+}
+
+impl App {
+    /// Stores the annotations of the loaded ROM so they come back the next time it's loaded.
+    fn save_project(&self, ctx: &Context<Self>) {
+        let Some(result) = self.result.as_ref() else {
+            return;
+        };
+        let rom_hash = result.rom_hash.clone();
+        let json = Project::new(rom_hash.clone(), &result.prg_data).to_json();
+        ctx.link().send_future(async move {
+            AppMessage::ProjectSaved(indexed_db::save(rom_hash, json).await)
+        });
+    }
+
+    /// Applies a project to the loaded ROM, reporting what couldn't be applied.
+    fn apply_project(&mut self, json: &str) -> Result<(), String> {
+        let Some(result) = self.result.as_mut() else {
+            return Err("No ROM loaded".into());
+        };
+        let project = Project::from_json(json, &result.rom_hash).map_err(|e| e.to_string())?;
+        let errors = project.apply(&mut result.prg_data);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Skipped {} label(s) of the project: {}",
+                errors.len(),
+                errors
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ))
+        }
+    }
 }
 
 impl Component for App {
@@ -43,7 +146,10 @@ impl Component for App {
         Self {
             readers: HashMap::with_capacity(1),
             result: None,
+            comparison: None,
+            palette: None,
             error: String::new(),
+            export_dialect: Dialect::Ca65,
         }
     }
 
@@ -69,6 +175,11 @@ impl Component for App {
                 let link = ctx.link().clone();
                 match RomReader::read(bytes) {
                     Ok(result) => {
+                        let rom_hash = result.rom_hash.clone();
+                        ctx.link().send_future(async move {
+                            let project = indexed_db::load(rom_hash.clone()).await;
+                            AppMessage::ProjectRestored(rom_hash, project)
+                        });
                         self.result = Some(result);
                         self.readers.remove(&uuid);
                         true
@@ -83,7 +194,486 @@ impl Component for App {
                 self.result = None;
                 self.error = message;
                 self.readers.remove(&uuid);
-                Dialog::open_modal("romLoadDialog".into());
+                Dialog::open_modal("errorDialog".into());
+                true
+            }
+            AppMessage::ComparisonUploaded(file) => {
+                let link = ctx.link().clone();
+                let uuid = Uuid::new_v4().to_string();
+                let task = {
+                    let uuid = uuid.clone();
+                    read_as_bytes(&file, move |result| {
+                        let msg = match result {
+                            Ok(data) => AppMessage::ComparisonLoaded(uuid, data),
+                            Err(err) => AppMessage::ImportFailure(uuid, err.to_string()),
+                        };
+                        link.send_message(msg);
+                    })
+                };
+                self.readers.insert(uuid, task);
+                false
+            }
+            AppMessage::ComparisonLoaded(uuid, bytes) => {
+                self.readers.remove(&uuid);
+                match RomReader::read(bytes) {
+                    Ok(result) => {
+                        self.comparison = Some(result.prg_data);
+                        true
+                    }
+                    Err(error) => {
+                        let link = ctx.link().clone();
+                        link.send_message(AppMessage::ImportFailure(uuid, error.to_string()));
+                        false
+                    }
+                }
+            }
+            AppMessage::SymbolsUploaded(file) => {
+                let link = ctx.link().clone();
+                let uuid = Uuid::new_v4().to_string();
+                let task = {
+                    let uuid = uuid.clone();
+                    let file_name = file.name();
+                    read_as_text(&file, move |result| {
+                        let msg = match result {
+                            Ok(text) => AppMessage::SymbolsLoaded(uuid, file_name, text),
+                            Err(err) => AppMessage::ImportFailure(uuid, err.to_string()),
+                        };
+                        link.send_message(msg);
+                    })
+                };
+                self.readers.insert(uuid, task);
+                false
+            }
+            AppMessage::SymbolsLoaded(uuid, file_name, text) => {
+                self.readers.remove(&uuid);
+                let link = ctx.link().clone();
+                let Some(result) = self.result.as_mut() else {
+                    link.send_message(AppMessage::ImportFailure(uuid, "No ROM loaded".into()));
+                    return false;
+                };
+                let prg_rom_start = result.prg_rom_start();
+                match SymbolFile::parse(&file_name, &text, &result.prg_data, prg_rom_start) {
+                    Ok(symbol_file) => {
+                        let errors = result.prg_data.import_symbols(symbol_file);
+                        self.save_project(ctx);
+                        if !errors.is_empty() {
+                            let message = format!(
+                                "Skipped {} symbol(s) from {}: {}",
+                                errors.len(),
+                                file_name,
+                                errors
+                                    .iter()
+                                    .map(|e| e.to_string())
+                                    .collect::<Vec<String>>()
+                                    .join(", ")
+                            );
+                            link.send_message(AppMessage::ImportFailure(uuid, message));
+                        }
+                        true
+                    }
+                    Err(error) => {
+                        link.send_message(AppMessage::ImportFailure(uuid, error.to_string()));
+                        false
+                    }
+                }
+            }
+            AppMessage::CodeDataLogUploaded(file) => {
+                let link = ctx.link().clone();
+                let uuid = Uuid::new_v4().to_string();
+                let task = {
+                    let uuid = uuid.clone();
+                    read_as_bytes(&file, move |result| {
+                        let msg = match result {
+                            Ok(data) => AppMessage::CodeDataLogLoaded(uuid, data),
+                            Err(err) => AppMessage::ImportFailure(uuid, err.to_string()),
+                        };
+                        link.send_message(msg);
+                    })
+                };
+                self.readers.insert(uuid, task);
+                false
+            }
+            AppMessage::CodeDataLogLoaded(uuid, bytes) => {
+                self.readers.remove(&uuid);
+                let link = ctx.link().clone();
+                let Some(result) = self.result.as_mut() else {
+                    link.send_message(AppMessage::ImportFailure(uuid, "No ROM loaded".into()));
+                    return false;
+                };
+                let prg_size = result.header.prg_rom_size as usize;
+                let chr_size = result.header.chr_rom_size as usize;
+                match CodeDataLog::parse(&bytes, prg_size, chr_size) {
+                    Ok(code_data_log) => {
+                        result
+                            .prg_data
+                            .apply_code_data_log(code_data_log.prg.clone());
+                        result.code_data_log = Some(code_data_log);
+                        true
+                    }
+                    Err(error) => {
+                        link.send_message(AppMessage::ImportFailure(uuid, error.to_string()));
+                        false
+                    }
+                }
+            }
+            AppMessage::ChrPngUploaded(file, import) => {
+                let link = ctx.link().clone();
+                let uuid = Uuid::new_v4().to_string();
+                let task = {
+                    let uuid = uuid.clone();
+                    read_as_bytes(&file, move |result| {
+                        let msg = match result {
+                            Ok(data) => AppMessage::ChrPngLoaded(uuid, data, import),
+                            Err(err) => AppMessage::ImportFailure(uuid, err.to_string()),
+                        };
+                        link.send_message(msg);
+                    })
+                };
+                self.readers.insert(uuid, task);
+                false
+            }
+            AppMessage::ChrPngLoaded(uuid, bytes, import) => {
+                self.readers.remove(&uuid);
+                let link = ctx.link().clone();
+                let Some(result) = self.result.as_mut().filter(|_| !import.tables.is_empty())
+                else {
+                    link.send_message(AppMessage::ImportFailure(uuid, "No CHR ROM loaded".into()));
+                    return false;
+                };
+                let count = import.tables.len() * TILES_PER_PATTERN_TABLE;
+                match import_png(&bytes, import.arrangement, &import.colors, count) {
+                    Ok((tiles, approximated)) => {
+                        result.chr_data.replace_tiles(import.tables, &tiles);
+                        if approximated > 0 {
+                            let message = format!(
+                                "{} pixel(s) of the image didn't match the palette and took the nearest color",
+                                approximated
+                            );
+                            link.send_message(AppMessage::ImportFailure(uuid, message));
+                        }
+                        true
+                    }
+                    Err(error) => {
+                        link.send_message(AppMessage::ImportFailure(uuid, error.to_string()));
+                        false
+                    }
+                }
+            }
+            AppMessage::EditTile(table, index, tile) => {
+                let Some(result) = self.result.as_mut() else {
+                    return false;
+                };
+                result.chr_data.set_tile(table, index, tile);
+                true
+            }
+            AppMessage::PaletteUploaded(file) => {
+                let link = ctx.link().clone();
+                let uuid = Uuid::new_v4().to_string();
+                let task = {
+                    let uuid = uuid.clone();
+                    read_as_bytes(&file, move |result| {
+                        let msg = match result {
+                            Ok(data) => AppMessage::PaletteLoaded(uuid, data),
+                            Err(err) => AppMessage::ImportFailure(uuid, err.to_string()),
+                        };
+                        link.send_message(msg);
+                    })
+                };
+                self.readers.insert(uuid, task);
+                false
+            }
+            AppMessage::PaletteLoaded(uuid, bytes) => {
+                self.readers.remove(&uuid);
+                match MasterPalette::parse(&bytes) {
+                    Ok(palette) => {
+                        self.palette = Some(palette);
+                        true
+                    }
+                    Err(error) => {
+                        ctx.link()
+                            .send_message(AppMessage::ImportFailure(uuid, error.to_string()));
+                        false
+                    }
+                }
+            }
+            AppMessage::ProjectRestored(rom_hash, project) => {
+                let loaded = self
+                    .result
+                    .as_ref()
+                    .is_some_and(|result| result.rom_hash == rom_hash);
+                match project {
+                    // Another ROM may have been loaded in the meantime
+                    Ok(Some(json)) if loaded => {
+                        if let Err(message) = self.apply_project(&json) {
+                            self.error = message;
+                            Dialog::open_modal("errorDialog".into());
+                        }
+                        true
+                    }
+                    Ok(_) => false,
+                    Err(error) => {
+                        log::warn!("{}", error);
+                        false
+                    }
+                }
+            }
+            AppMessage::ProjectSaved(saved) => {
+                if let Err(error) = saved {
+                    log::warn!("{}", error);
+                }
+                false
+            }
+            AppMessage::ProjectUploaded(file) => {
+                let link = ctx.link().clone();
+                let uuid = Uuid::new_v4().to_string();
+                let task = {
+                    let uuid = uuid.clone();
+                    read_as_text(&file, move |result| {
+                        let msg = match result {
+                            Ok(text) => AppMessage::ProjectLoaded(uuid, text),
+                            Err(err) => AppMessage::ImportFailure(uuid, err.to_string()),
+                        };
+                        link.send_message(msg);
+                    })
+                };
+                self.readers.insert(uuid, task);
+                false
+            }
+            AppMessage::ProjectLoaded(uuid, text) => {
+                self.readers.remove(&uuid);
+                let applied = self.apply_project(&text);
+                self.save_project(ctx);
+                if let Err(message) = applied {
+                    ctx.link()
+                        .send_message(AppMessage::ImportFailure(uuid, message));
+                }
+                true
+            }
+            AppMessage::ExportProject => {
+                if let Some(result) = self.result.as_ref() {
+                    let json = Project::new(result.rom_hash.clone(), &result.prg_data).to_json();
+                    let file_name = format!("{}.json", result.rom_hash);
+                    download(&file_name, "application/json", json.as_bytes());
+                }
+                false
+            }
+            AppMessage::ImportFailure(uuid, message) => {
+                self.error = message;
+                self.readers.remove(&uuid);
+                Dialog::open_modal("errorDialog".into());
+                true
+            }
+            AppMessage::SelectExportDialect(index) => {
+                self.export_dialect = Dialect::ALL[index];
+                false
+            }
+            AppMessage::Export => {
+                let Some(result) = self.result.as_ref() else {
+                    return false;
+                };
+                match export_project(result, self.export_dialect) {
+                    Ok(files) => {
+                        let files = files
+                            .into_iter()
+                            .map(|file| (file.name, file.contents))
+                            .collect::<Vec<(String, Vec<u8>)>>();
+                        download("disassembly.zip", "application/zip", &zip(&files));
+                        false
+                    }
+                    Err(error) => {
+                        self.error = error.to_string();
+                        Dialog::open_modal("errorDialog".into());
+                        true
+                    }
+                }
+            }
+            AppMessage::Patch(location, bytes) => {
+                let Some(result) = self.result.as_mut() else {
+                    return false;
+                };
+                if let Err(error) = result.prg_data.patch(location, &bytes) {
+                    self.error = error.to_string();
+                    Dialog::open_modal("errorDialog".into());
+                }
+                true
+            }
+            AppMessage::DownloadRom => {
+                if let Some(result) = self.result.as_ref() {
+                    download(
+                        "patched.nes",
+                        "application/octet-stream",
+                        &result.to_bytes(),
+                    );
+                }
+                false
+            }
+            AppMessage::DownloadBps => {
+                if let Some(result) = self.result.as_ref() {
+                    let patch = bps(&result.original_bytes, &result.to_bytes());
+                    download("patch.bps", "application/octet-stream", &patch);
+                }
+                false
+            }
+            AppMessage::DownloadScript => {
+                if let Some(result) = self.result.as_ref() {
+                    let script = dump_script(&result.prg_data);
+                    download("script.txt", "text/plain", script.as_bytes());
+                }
+                false
+            }
+            AppMessage::ScriptUploaded(file) => {
+                let link = ctx.link().clone();
+                let uuid = Uuid::new_v4().to_string();
+                let task = {
+                    let uuid = uuid.clone();
+                    read_as_text(&file, move |result| {
+                        let msg = match result {
+                            Ok(text) => AppMessage::ScriptLoaded(uuid, text),
+                            Err(err) => AppMessage::ImportFailure(uuid, err.to_string()),
+                        };
+                        link.send_message(msg);
+                    })
+                };
+                self.readers.insert(uuid, task);
+                false
+            }
+            AppMessage::ScriptLoaded(uuid, text) => {
+                self.readers.remove(&uuid);
+                let link = ctx.link().clone();
+                let Some(result) = self.result.as_mut() else {
+                    link.send_message(AppMessage::ImportFailure(uuid, "No ROM loaded".into()));
+                    return false;
+                };
+                match insert_script(&mut result.prg_data, &text) {
+                    Ok(()) => true,
+                    Err(error) => {
+                        link.send_message(AppMessage::ImportFailure(uuid, error.to_string()));
+                        false
+                    }
+                }
+            }
+            AppMessage::DownloadTextDump => {
+                if let Some(result) = self.result.as_ref() {
+                    let dump = text_dump(&result.prg_data);
+                    download("text.txt", "text/plain", dump.as_bytes());
+                }
+                false
+            }
+            AppMessage::TextTableUploaded(file) => {
+                let link = ctx.link().clone();
+                let uuid = Uuid::new_v4().to_string();
+                let task = {
+                    let uuid = uuid.clone();
+                    read_as_text(&file, move |result| {
+                        let msg = match result {
+                            Ok(text) => AppMessage::TextTableLoaded(uuid, text),
+                            Err(err) => AppMessage::ImportFailure(uuid, err.to_string()),
+                        };
+                        link.send_message(msg);
+                    })
+                };
+                self.readers.insert(uuid, task);
+                false
+            }
+            AppMessage::TextTableLoaded(uuid, text) => {
+                self.readers.remove(&uuid);
+                let link = ctx.link().clone();
+                let Some(result) = self.result.as_mut() else {
+                    link.send_message(AppMessage::ImportFailure(uuid, "No ROM loaded".into()));
+                    return false;
+                };
+                match TextTable::parse(&text) {
+                    Ok(table) => {
+                        result.prg_data.text_table = Some(table);
+                        self.save_project(ctx);
+                        true
+                    }
+                    Err(error) => {
+                        link.send_message(AppMessage::ImportFailure(uuid, error.to_string()));
+                        false
+                    }
+                }
+            }
+            AppMessage::SignaturesUploaded(file) => {
+                let link = ctx.link().clone();
+                let uuid = Uuid::new_v4().to_string();
+                let task = {
+                    let uuid = uuid.clone();
+                    read_as_text(&file, move |result| {
+                        let msg = match result {
+                            Ok(text) => AppMessage::SignaturesLoaded(uuid, text),
+                            Err(err) => AppMessage::ImportFailure(uuid, err.to_string()),
+                        };
+                        link.send_message(msg);
+                    })
+                };
+                self.readers.insert(uuid, task);
+                false
+            }
+            AppMessage::SignaturesLoaded(uuid, text) => {
+                self.readers.remove(&uuid);
+                let link = ctx.link().clone();
+                let Some(result) = self.result.as_mut() else {
+                    link.send_message(AppMessage::ImportFailure(uuid, "No ROM loaded".into()));
+                    return false;
+                };
+                match Signature::parse_all(&text) {
+                    Ok(signatures) => {
+                        result.prg_data.set_signatures(signatures);
+                        self.save_project(ctx);
+                        true
+                    }
+                    Err(error) => {
+                        link.send_message(AppMessage::ImportFailure(uuid, error.to_string()));
+                        false
+                    }
+                }
+            }
+            AppMessage::DownloadIps => {
+                if let Some(result) = self.result.as_ref() {
+                    let patch = ips(&result.original_bytes, &result.to_bytes());
+                    download("patch.ips", "application/octet-stream", &patch);
+                }
+                false
+            }
+            AppMessage::RenameLabel(location, name) => {
+                let Some(result) = self.result.as_mut() else {
+                    return false;
+                };
+                if let Err(error) = result.prg_data.labels.rename(location, name) {
+                    self.error = error.to_string();
+                    Dialog::open_modal("errorDialog".into());
+                }
+                self.save_project(ctx);
+                true
+            }
+            AppMessage::SetComment(location, comment) => {
+                let Some(result) = self.result.as_mut() else {
+                    return false;
+                };
+                let comment = comment.trim().to_string();
+                if comment.is_empty() {
+                    result.prg_data.comments.remove(&location);
+                } else {
+                    result.prg_data.comments.insert(location, comment);
+                }
+                self.save_project(ctx);
+                true
+            }
+            AppMessage::SetRegion(location, length, kind) => {
+                let Some(result) = self.result.as_mut() else {
+                    return false;
+                };
+                result.prg_data.set_region(location, length, kind);
+                self.save_project(ctx);
+                true
+            }
+            AppMessage::SetStructTable(location, table) => {
+                let Some(result) = self.result.as_mut() else {
+                    return false;
+                };
+                result.prg_data.set_struct_table(location, table);
+                self.save_project(ctx);
                 true
             }
         }
@@ -91,27 +681,189 @@ impl Component for App {
 
     fn view(&self, ctx: &Context<Self>) -> Html {
         let on_change = ctx.link().callback(|f: File| AppMessage::Uploaded(f));
+        let on_symbols_change = ctx
+            .link()
+            .callback(|f: File| AppMessage::SymbolsUploaded(f));
+        let on_code_data_log_change = ctx
+            .link()
+            .callback(|f: File| AppMessage::CodeDataLogUploaded(f));
+        let on_dialect_change = ctx.link().callback(AppMessage::SelectExportDialect);
+        let on_export = ctx.link().callback(|_: MouseEvent| AppMessage::Export);
+        let dialects = Dialect::ALL
+            .iter()
+            .map(|dialect| dialect.to_string())
+            .collect::<Vec<String>>();
+        let selected_dialect = Dialect::ALL
+            .iter()
+            .position(|dialect| *dialect == self.export_dialect)
+            .unwrap_or_default();
+        let on_rename = ctx
+            .link()
+            .callback(|(location, name)| AppMessage::RenameLabel(location, name));
+        let on_patch = ctx
+            .link()
+            .callback(|(location, bytes)| AppMessage::Patch(location, bytes));
+        let on_comment = ctx
+            .link()
+            .callback(|(location, comment)| AppMessage::SetComment(location, comment));
+        let on_set_region = ctx
+            .link()
+            .callback(|(location, length, kind)| AppMessage::SetRegion(location, length, kind));
+        let on_set_struct = ctx
+            .link()
+            .callback(|(location, table)| AppMessage::SetStructTable(location, table));
+        let on_project_change = ctx
+            .link()
+            .callback(|f: File| AppMessage::ProjectUploaded(f));
+        let on_export_project = ctx
+            .link()
+            .callback(|_: MouseEvent| AppMessage::ExportProject);
+        let on_download_rom = ctx.link().callback(|_: MouseEvent| AppMessage::DownloadRom);
+        let on_download_ips = ctx.link().callback(|_: MouseEvent| AppMessage::DownloadIps);
+        let on_text_table_change = ctx
+            .link()
+            .callback(|f: File| AppMessage::TextTableUploaded(f));
+        let on_import_png = ctx
+            .link()
+            .callback(|(file, import)| AppMessage::ChrPngUploaded(file, import));
+        let on_edit_tile = ctx
+            .link()
+            .callback(|(table, index, tile)| AppMessage::EditTile(table, index, tile));
+        let on_palette_change = ctx
+            .link()
+            .callback(|f: File| AppMessage::PaletteUploaded(f));
+        let on_signatures_change = ctx
+            .link()
+            .callback(|f: File| AppMessage::SignaturesUploaded(f));
+        let on_download_text_dump = ctx
+            .link()
+            .callback(|_: MouseEvent| AppMessage::DownloadTextDump);
+        let on_download_bps = ctx.link().callback(|_: MouseEvent| AppMessage::DownloadBps);
+        let on_download_script = ctx
+            .link()
+            .callback(|_: MouseEvent| AppMessage::DownloadScript);
+        let on_script_change = ctx.link().callback(|f: File| AppMessage::ScriptUploaded(f));
+        let on_comparison_change = ctx
+            .link()
+            .callback(|f: File| AppMessage::ComparisonUploaded(f));
 
         let error_message = self.error.clone();
         let header_data_clone = self.result.as_ref().map(|v| v.header.clone());
+        let detected = self.result.as_ref().map_or(Vec::new(), |v| {
+            v.prg_data
+                .detected
+                .iter()
+                .map(|found| found.description.clone())
+                .collect::<BTreeSet<String>>()
+                .into_iter()
+                .collect()
+        });
         let chr_data_clone = self.result.as_ref().map(|v| v.chr_data.clone());
+        let drawn_tiles = self
+            .result
+            .as_ref()
+            .and_then(|v| v.code_data_log.as_ref())
+            .map(|code_data_log| code_data_log.drawn_tiles());
         let prg_data_clone = self.result.as_ref().map(|v| v.prg_data.clone());
+        let prg_rom = self
+            .result
+            .as_ref()
+            .map_or(Vec::new(), |v| v.prg_data.to_bytes());
+        let mapper = self.result.as_ref().map_or(0, |v| v.header.mapper);
 
         html! {
             <>
                 <Navbar>
                     <FileInput id="rom-input" prompt="Load ROM" placeholder="Choose ROM" {on_change}/>
+                    <FileInput
+                        id="symbols-input"
+                        prompt="Load symbols"
+                        placeholder=".dbg, .nl or .mlb"
+                        multiple={true}
+                        on_change={on_symbols_change}/>
+                    <FileInput
+                        id="cdl-input"
+                        prompt="Load CDL"
+                        placeholder=".cdl"
+                        on_change={on_code_data_log_change}/>
+                    <FileInput
+                        id="palette-input"
+                        prompt="Load palette"
+                        placeholder=".pal"
+                        on_change={on_palette_change}/>
+                    <FileInput
+                        id="text-table-input"
+                        prompt="Load table"
+                        placeholder=".tbl"
+                        on_change={on_text_table_change}/>
+                    <FileInput
+                        id="signatures-input"
+                        prompt="Load signatures"
+                        placeholder=".sig"
+                        on_change={on_signatures_change}/>
+                    <FileInput
+                        id="script-input"
+                        prompt="Insert script"
+                        placeholder=".txt"
+                        on_change={on_script_change}/>
+                    <FileInput
+                        id="comparison-input"
+                        prompt="Compare ROM"
+                        placeholder="Other revision"
+                        on_change={on_comparison_change}/>
+                    <FileInput
+                        id="project-input"
+                        prompt="Load project"
+                        placeholder=".json"
+                        on_change={on_project_change}/>
+                    <Button color={ButtonColor::Primary} onclick={on_export_project}>
+                        {"Save project"}
+                    </Button>
+                    <div class={classes!("join")}>
+                        <Select
+                            class={classes!("join-item")}
+                            options={dialects}
+                            selected={selected_dialect}
+                            on_change={on_dialect_change}/>
+                        <Button class={classes!("join-item")} color={ButtonColor::Primary} onclick={on_export}>
+                            {"Export source"}
+                        </Button>
+                    </div>
+                    <div class={classes!("join")}>
+                        <Button class={classes!("join-item")} color={ButtonColor::Primary} onclick={on_download_rom.clone()}>
+                            {"Patched ROM"}
+                        </Button>
+                        <Button class={classes!("join-item")} color={ButtonColor::Primary} onclick={on_download_ips}>
+                            {"IPS patch"}
+                        </Button>
+                        <Button class={classes!("join-item")} color={ButtonColor::Primary} onclick={on_download_bps}>
+                            {"BPS patch"}
+                        </Button>
+                        <Button class={classes!("join-item")} color={ButtonColor::Primary} onclick={on_download_text_dump}>
+                            {"Text dump"}
+                        </Button>
+                        <Button class={classes!("join-item")} color={ButtonColor::Primary} onclick={on_download_script}>
+                            {"Script"}
+                        </Button>
+                    </div>
                 </Navbar>
                 <main class={classes!("flex")}>
                     <Box class={classes!("grow-0")}>
-                        <Header rom_header={ header_data_clone }/>
-                        <Chr chr_data={ chr_data_clone }/>
+                        <Header rom_header={ header_data_clone } {detected}/>
+                        <Chr chr_data={ chr_data_clone } {drawn_tiles} palette={self.palette.clone()} {prg_rom} {on_import_png} {on_edit_tile} {on_download_rom}/>
                     </Box>
                     <Box class={classes!("grow")}>
-                        <Prg prg_data={ prg_data_clone }/>
+                        <Prg prg_data={ prg_data_clone } {mapper} {on_rename} {on_patch} {on_comment} {on_set_region} {on_set_struct}/>
+                        {
+                            self.comparison
+                                .clone()
+                                .zip(self.result.as_ref().map(|v| v.prg_data.clone()))
+                                .map(|(old, new)| html! { <Diff {old} {new}/> })
+                                .unwrap_or_default()
+                        }
                     </Box>
                 </main>
-                <AlertDialog id="romLoadDialog" title="Error" message={error_message}/>
+                <AlertDialog id="errorDialog" title="Error" message={error_message}/>
             </>
         }
     }
@@ -0,0 +1,234 @@
+use std::{error, fmt};
+
+use super::instructions::{AddressingMode, Instruction, Mnemonic, OPCODES};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssemblerError {
+    UnknownMnemonic(String),
+    InvalidOperand(String),
+    UnsupportedAddressingMode(String),
+    BranchOutOfRange(u16),
+}
+
+impl fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssemblerError::UnknownMnemonic(mnemonic) => {
+                write!(f, "Unknown mnemonic \"{}\"", mnemonic)
+            }
+            AssemblerError::InvalidOperand(operand) => {
+                write!(f, "Invalid operand \"{}\"", operand)
+            }
+            AssemblerError::UnsupportedAddressingMode(instruction) => {
+                write!(
+                    f,
+                    "\"{}\" doesn't support this addressing mode",
+                    instruction
+                )
+            }
+            AssemblerError::BranchOutOfRange(target) => {
+                write!(f, "Branch target ${:04X} is out of range", target)
+            }
+        }
+    }
+}
+
+impl error::Error for AssemblerError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Number literal in hexadecimal (`$FF`), binary (`%1010`) or decimal, or a label name.
+fn parse_value(value: &str, resolve: &impl Fn(&str) -> Option<u16>) -> Option<u16> {
+    if let Some(hex) = value.strip_prefix('$') {
+        u16::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = value.strip_prefix('%') {
+        u16::from_str_radix(binary, 2).ok()
+    } else if value.starts_with(|c: char| c.is_ascii_digit()) {
+        value.parse().ok()
+    } else {
+        resolve(value)
+    }
+}
+
+/// Opcode for `mnemonic` in `mode`, the documented one when the table has duplicates.
+fn opcode(mnemonic: Mnemonic, mode: AddressingMode) -> Option<u8> {
+    let mut candidates =
+        (0..=u8::MAX).filter(|opcode| OPCODES[*opcode as usize] == (mnemonic, mode));
+    let first = candidates.next()?;
+    let official = |opcode: &u8| {
+        Instruction::decode(&[*opcode, 0, 0], 0)
+            .is_some_and(|instruction| instruction.is_official())
+    };
+    Some(
+        std::iter::once(first)
+            .chain(candidates)
+            .find(official)
+            .unwrap_or(first),
+    )
+}
+
+/// Encodes a single line of assembly, such as `LDA #$05`, to be located at `address`.
+/// `resolve` looks up the address of label names used as operands. Values that fit in a byte
+/// use zero page addressing when the instruction has it.
+pub fn assemble(
+    source: &str,
+    address: u16,
+    resolve: impl Fn(&str) -> Option<u16>,
+) -> Result<Vec<u8>, AssemblerError> {
+    use AddressingMode::*;
+
+    let source = source.trim();
+    let (mnemonic_text, operand_text) = source
+        .split_once(char::is_whitespace)
+        .unwrap_or((source, ""));
+    let mnemonic = OPCODES
+        .iter()
+        .map(|(mnemonic, _)| *mnemonic)
+        .find(|mnemonic| mnemonic.to_string().eq_ignore_ascii_case(mnemonic_text))
+        .ok_or_else(|| AssemblerError::UnknownMnemonic(mnemonic_text.to_string()))?;
+    let supports = |mode: AddressingMode| opcode(mnemonic, mode).is_some();
+
+    let operand: String = operand_text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let upper = operand.to_ascii_uppercase();
+    let invalid = || AssemblerError::InvalidOperand(operand_text.trim().to_string());
+    let value = |text: &str| parse_value(text, &resolve).ok_or_else(invalid);
+    let byte = |value: u16| u8::try_from(value).map_err(|_| invalid());
+    let zero_page_or = |value: u16, zero_page: AddressingMode, absolute: AddressingMode| {
+        if value <= u8::MAX as u16 && supports(zero_page) {
+            zero_page
+        } else {
+            absolute
+        }
+    };
+
+    let (mode, value) = if operand.is_empty() {
+        (
+            if supports(Implied) {
+                Implied
+            } else {
+                Accumulator
+            },
+            0,
+        )
+    } else if upper == "A" && supports(Accumulator) {
+        (Accumulator, 0)
+    } else if let Some(immediate) = operand.strip_prefix('#') {
+        (Immediate, byte(value(immediate)?)? as u16)
+    } else if upper.starts_with('(') && upper.ends_with(",X)") {
+        (
+            IndirectX,
+            byte(value(&operand[1..operand.len() - 3])?)? as u16,
+        )
+    } else if upper.starts_with('(') && upper.ends_with("),Y") {
+        (
+            IndirectY,
+            byte(value(&operand[1..operand.len() - 3])?)? as u16,
+        )
+    } else if upper.starts_with('(') && upper.ends_with(')') {
+        (Indirect, value(&operand[1..operand.len() - 1])?)
+    } else if upper.ends_with(",X") {
+        let value = value(&operand[..operand.len() - 2])?;
+        (zero_page_or(value, ZeroPageX, AbsoluteX), value)
+    } else if upper.ends_with(",Y") {
+        let value = value(&operand[..operand.len() - 2])?;
+        (zero_page_or(value, ZeroPageY, AbsoluteY), value)
+    } else if supports(Relative) {
+        (Relative, value(&operand)?)
+    } else {
+        let value = value(&operand)?;
+        (zero_page_or(value, ZeroPage, Absolute), value)
+    };
+
+    let opcode = opcode(mnemonic, mode)
+        .ok_or_else(|| AssemblerError::UnsupportedAddressingMode(source.to_string()))?;
+    let [low, high] = value.to_le_bytes();
+    let bytes = match mode {
+        Relative => {
+            let offset = value as i32 - address.wrapping_add(2) as i32;
+            let offset =
+                i8::try_from(offset).map_err(|_| AssemblerError::BranchOutOfRange(value))?;
+            vec![opcode, offset as u8]
+        }
+        _ => [opcode, low, high][..1 + mode.operand_size()].to_vec(),
+    };
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assemble_at(source: &str, address: u16) -> Result<Vec<u8>, AssemblerError> {
+        assemble(source, address, |name| {
+            (name == "loc_C010").then_some(0xC010)
+        })
+    }
+
+    #[test]
+    fn assemble_addressing_modes() {
+        assert_eq!(assemble_at("LDA #$05", 0xC000), Ok(vec![0xA9, 0x05]));
+        assert_eq!(assemble_at("lda $10", 0xC000), Ok(vec![0xA5, 0x10]));
+        assert_eq!(
+            assemble_at("LDA $0300, x", 0xC000),
+            Ok(vec![0xBD, 0x00, 0x03])
+        );
+        assert_eq!(assemble_at("LDX $10,Y", 0xC000), Ok(vec![0xB6, 0x10]));
+        assert_eq!(assemble_at("STA ($FB), Y", 0xC000), Ok(vec![0x91, 0xFB]));
+        assert_eq!(assemble_at("LDA ($10,X)", 0xC000), Ok(vec![0xA1, 0x10]));
+        assert_eq!(
+            assemble_at("JMP ($0300)", 0xC000),
+            Ok(vec![0x6C, 0x00, 0x03])
+        );
+        assert_eq!(
+            assemble_at("JSR loc_C010", 0xC000),
+            Ok(vec![0x20, 0x10, 0xC0])
+        );
+        assert_eq!(assemble_at("ASL", 0xC000), Ok(vec![0x0A]));
+        assert_eq!(assemble_at("ASL A", 0xC000), Ok(vec![0x0A]));
+        assert_eq!(assemble_at("NOP", 0xC000), Ok(vec![0xEA]));
+        assert_eq!(assemble_at("SBC #%11", 0xC000), Ok(vec![0xE9, 0x03]));
+        assert_eq!(assemble_at("LAX $10", 0xC000), Ok(vec![0xA7, 0x10]));
+    }
+
+    #[test]
+    fn assemble_branches() {
+        assert_eq!(assemble_at("BNE loc_C010", 0xC000), Ok(vec![0xD0, 0x0E]));
+        assert_eq!(assemble_at("BEQ $C000", 0xC010), Ok(vec![0xF0, 0xEE]));
+        let bytes = assemble_at("BCC $BFF0", 0xC000).unwrap();
+        assert_eq!(
+            Instruction::decode(&bytes, 0xC000).unwrap().jump_target(),
+            Some(0xBFF0)
+        );
+        assert_eq!(
+            assemble_at("BNE $C100", 0xC000),
+            Err(AssemblerError::BranchOutOfRange(0xC100))
+        );
+    }
+
+    #[test]
+    fn assemble_errors() {
+        assert_eq!(
+            assemble_at("FOO #1", 0xC000),
+            Err(AssemblerError::UnknownMnemonic("FOO".to_string()))
+        );
+        assert_eq!(
+            assemble_at("LDA #$100", 0xC000),
+            Err(AssemblerError::InvalidOperand("#$100".to_string()))
+        );
+        assert_eq!(
+            assemble_at("LDA missing", 0xC000),
+            Err(AssemblerError::InvalidOperand("missing".to_string()))
+        );
+        assert_eq!(
+            assemble_at("STA #$05", 0xC000),
+            Err(AssemblerError::UnsupportedAddressingMode(
+                "STA #$05".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,522 @@
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet, VecDeque};
+
+use super::{
+    data::{Location, PrgData},
+    instructions::{Instruction, Mnemonic},
+    labels::LabelKind,
+};
+
+const CHAR_WIDTH: u32 = 8;
+const LINE_HEIGHT: u32 = 14;
+const NODE_PADDING: u32 = 6;
+const NODE_GAP: u32 = 24;
+const LAYER_GAP: u32 = 40;
+const BACK_EDGE_GAP: u32 = 12;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum EdgeKind {
+    Taken,
+    NotTaken,
+    Unconditional,
+}
+
+impl EdgeKind {
+    pub fn color(&self) -> &'static str {
+        match self {
+            EdgeKind::Taken => "#16a34a",
+            EdgeKind::NotTaken => "#dc2626",
+            EdgeKind::Unconditional => "#2563eb",
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Edge {
+    pub to: Location,
+    pub kind: EdgeKind,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct BasicBlock {
+    pub start: Location,
+    pub instructions: Vec<(u16, Instruction)>,
+    pub edges: Vec<Edge>,
+}
+
+impl BasicBlock {
+    /// Fewest and most cycles it takes to run through the block.
+    pub fn cycles(&self) -> (u32, u32) {
+        self.instructions
+            .iter()
+            .map(|(address, instruction)| instruction.cycles(*address))
+            .fold((0, 0), |(min, max), cycles| {
+                (min + cycles.min(), max + cycles.max())
+            })
+    }
+}
+
+/// Basic blocks of a subroutine. Jumps to other subroutines are tail calls and
+/// end the graph, just like returns do; `JSR` doesn't split blocks.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ControlFlowGraph {
+    pub entry: Location,
+    pub blocks: BTreeMap<Location, BasicBlock>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct NodeLayout {
+    pub location: Location,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub lines: Vec<String>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct EdgeLayout {
+    pub kind: EdgeKind,
+    pub points: Vec<(u32, u32)>,
+}
+
+/// Blocks placed in layers by their distance from the entry point, ready to be drawn.
+#[derive(PartialEq, Debug, Clone)]
+pub struct GraphLayout {
+    pub width: u32,
+    pub height: u32,
+    pub nodes: Vec<NodeLayout>,
+    pub edges: Vec<EdgeLayout>,
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Subroutine the code at `location` belongs to, the closest subroutine label before it.
+pub fn subroutine_containing(prg_data: &PrgData, location: Location) -> Option<Location> {
+    prg_data
+        .labels
+        .iter()
+        .filter(|(label_location, label)| {
+            label.kind == LabelKind::Subroutine
+                && label_location.bank == location.bank
+                && label_location.address <= location.address
+        })
+        .map(|(label_location, _)| *label_location)
+        .last()
+}
+
+fn instruction_at(prg_data: &PrgData, location: Location) -> Option<&Instruction> {
+    prg_data
+        .banks
+        .get(location.bank?)?
+        .instructions
+        .get(&location.address)
+}
+
+impl ControlFlowGraph {
+    pub fn build(prg_data: &PrgData, entry: Location) -> ControlFlowGraph {
+        let mut leaders = BTreeSet::from([entry]);
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![entry];
+        while let Some(location) = pending.pop() {
+            if !visited.insert(location) {
+                continue;
+            }
+            let Some(instruction) = instruction_at(prg_data, location) else {
+                continue;
+            };
+            let edges = ControlFlowGraph::successors(prg_data, entry, location, instruction);
+            if instruction.is_branch() || instruction.mnemonic == Mnemonic::Jmp {
+                leaders.extend(edges.iter().map(|edge| edge.to));
+            }
+            pending.extend(edges.iter().map(|edge| edge.to));
+        }
+
+        let mut blocks = BTreeMap::new();
+        for leader in leaders.iter() {
+            let mut location = *leader;
+            let mut instructions = Vec::new();
+            let edges = loop {
+                let Some(instruction) = instruction_at(prg_data, location) else {
+                    break Vec::new();
+                };
+                instructions.push((location.address, instruction.clone()));
+                let edges = ControlFlowGraph::successors(prg_data, entry, location, instruction);
+                match edges.as_slice() {
+                    [Edge {
+                        to,
+                        kind: EdgeKind::Unconditional,
+                    }] if !instruction.is_branch()
+                        && instruction.mnemonic != Mnemonic::Jmp
+                        && !leaders.contains(to)
+                        && visited.contains(to) =>
+                    {
+                        location = *to;
+                    }
+                    _ => break edges,
+                }
+            };
+            if !instructions.is_empty() {
+                blocks.insert(
+                    *leader,
+                    BasicBlock {
+                        start: *leader,
+                        instructions,
+                        edges,
+                    },
+                );
+            }
+        }
+
+        // Edges into bytes that weren't disassembled lead nowhere
+        let starts = blocks.keys().copied().collect::<BTreeSet<Location>>();
+        for block in blocks.values_mut() {
+            block.edges.retain(|edge| starts.contains(&edge.to));
+        }
+        ControlFlowGraph { entry, blocks }
+    }
+
+    fn successors(
+        prg_data: &PrgData,
+        entry: Location,
+        location: Location,
+        instruction: &Instruction,
+    ) -> Vec<Edge> {
+        let next = Location {
+            bank: location.bank,
+            address: location.address.wrapping_add(instruction.len() as u16),
+        };
+        let target = instruction
+            .jump_target()
+            .zip(location.bank)
+            .map(|(target, bank)| prg_data.resolve(bank, target));
+        let tail_call = target.is_some_and(|target| {
+            target != entry
+                && prg_data
+                    .labels
+                    .get(&target)
+                    .is_some_and(|label| label.kind == LabelKind::Subroutine)
+        });
+        match target {
+            Some(to) if instruction.is_branch() => vec![
+                Edge {
+                    to,
+                    kind: EdgeKind::Taken,
+                },
+                Edge {
+                    to: next,
+                    kind: EdgeKind::NotTaken,
+                },
+            ],
+            Some(to) if instruction.mnemonic == Mnemonic::Jmp && !tail_call => vec![Edge {
+                to,
+                kind: EdgeKind::Unconditional,
+            }],
+            _ if instruction.ends_flow() => Vec::new(),
+            _ => vec![Edge {
+                to: next,
+                kind: EdgeKind::Unconditional,
+            }],
+        }
+    }
+
+    fn block_lines(&self, prg_data: &PrgData, block: &BasicBlock) -> Vec<String> {
+        let bank = block.start.bank.unwrap_or_default();
+        let label = prg_data
+            .labels
+            .name(&block.start)
+            .map(|name| format!("{}:", name));
+        let instructions = block.instructions.iter().map(|(address, instruction)| {
+            let operand = instruction.operand.map_or(String::new(), |operand| {
+                let label = operand
+                    .address()
+                    .and_then(|target| prg_data.operand_label(bank, target));
+                operand.to_string_with_label(label.as_deref())
+            });
+            format!("{:04X}  {} {}", address, instruction.mnemonic, operand)
+                .trim_end()
+                .to_string()
+        });
+        let cycles = match block.cycles() {
+            (min, max) if min == max => format!("{} cycles", min),
+            (min, max) => format!("{}-{} cycles", min, max),
+        };
+        label
+            .into_iter()
+            .chain(instructions)
+            .chain([cycles])
+            .collect()
+    }
+
+    pub fn layout(&self, prg_data: &PrgData) -> GraphLayout {
+        if !self.blocks.contains_key(&self.entry) {
+            return GraphLayout {
+                width: 0,
+                height: 0,
+                nodes: Vec::new(),
+                edges: Vec::new(),
+            };
+        }
+        // Layer of each block is its distance from the entry point
+        let mut layers: BTreeMap<Location, usize> = BTreeMap::from([(self.entry, 0)]);
+        let mut pending = VecDeque::from([self.entry]);
+        while let Some(location) = pending.pop_front() {
+            let layer = layers[&location];
+            for edge in self.blocks[&location].edges.iter() {
+                if let Entry::Vacant(entry) = layers.entry(edge.to) {
+                    entry.insert(layer + 1);
+                    pending.push_back(edge.to);
+                }
+            }
+        }
+
+        let layer_count = layers.values().max().map_or(0, |layer| layer + 1);
+        let mut rows: Vec<Vec<NodeLayout>> = vec![Vec::new(); layer_count];
+        for (location, layer) in layers.iter() {
+            let lines = self.block_lines(prg_data, &self.blocks[location]);
+            let longest = lines.iter().map(String::len).max().unwrap_or_default() as u32;
+            rows[*layer].push(NodeLayout {
+                location: *location,
+                x: 0,
+                y: 0,
+                width: longest * CHAR_WIDTH + 2 * NODE_PADDING,
+                height: lines.len() as u32 * LINE_HEIGHT + 2 * NODE_PADDING,
+                lines,
+            });
+        }
+
+        let row_width = |row: &[NodeLayout]| {
+            row.iter().map(|node| node.width + NODE_GAP).sum::<u32>() - NODE_GAP
+        };
+        let graph_width = rows
+            .iter()
+            .map(|row| row_width(row))
+            .max()
+            .unwrap_or_default();
+        let mut y = 0;
+        for row in rows.iter_mut() {
+            let mut x = (graph_width - row_width(row)) / 2;
+            for node in row.iter_mut() {
+                node.x = x;
+                node.y = y;
+                x += node.width + NODE_GAP;
+            }
+            y += row.iter().map(|node| node.height).max().unwrap_or_default() + LAYER_GAP;
+        }
+        let nodes = rows.into_iter().flatten().collect::<Vec<NodeLayout>>();
+
+        let node = |location: &Location| {
+            nodes
+                .iter()
+                .find(|node| node.location == *location)
+                .unwrap()
+        };
+        let mut back_edge_x = graph_width;
+        let mut edges = Vec::new();
+        for from in nodes.iter() {
+            for edge in self.blocks[&from.location].edges.iter() {
+                let to = node(&edge.to);
+                let points = if to.y > from.y {
+                    vec![
+                        (from.x + from.width / 2, from.y + from.height),
+                        (to.x + to.width / 2, to.y),
+                    ]
+                } else {
+                    // Loops go back up along the right side of the graph
+                    back_edge_x += BACK_EDGE_GAP;
+                    vec![
+                        (from.x + from.width, from.y + from.height / 2),
+                        (back_edge_x, from.y + from.height / 2),
+                        (back_edge_x, to.y + to.height / 2),
+                        (to.x + to.width, to.y + to.height / 2),
+                    ]
+                };
+                edges.push(EdgeLayout {
+                    kind: edge.kind,
+                    points,
+                });
+            }
+        }
+
+        GraphLayout {
+            width: back_edge_x + BACK_EDGE_GAP,
+            height: y.saturating_sub(LAYER_GAP),
+            nodes,
+            edges,
+        }
+    }
+
+    pub fn to_dot(&self, prg_data: &PrgData) -> String {
+        let node_id = |location: &Location| format!("block_{}", location).replace(['$', ':'], "");
+        let mut dot = vec![
+            "digraph cfg {".to_string(),
+            "    node [shape=box, fontname=\"monospace\"];".to_string(),
+        ];
+        for block in self.blocks.values() {
+            let label = self
+                .block_lines(prg_data, block)
+                .iter()
+                .map(|line| format!("{}\\l", escape_dot(line)))
+                .collect::<String>();
+            dot.push(format!(
+                "    {} [label=\"{}\"];",
+                node_id(&block.start),
+                label
+            ));
+        }
+        for block in self.blocks.values() {
+            for edge in block.edges.iter() {
+                dot.push(format!(
+                    "    {} -> {} [color=\"{}\"];",
+                    node_id(&block.start),
+                    node_id(&edge.to),
+                    edge.kind.color()
+                ));
+            }
+        }
+        dot.push("}".to_string());
+        dot.join("\n") + "\n"
+    }
+}
+
+impl GraphLayout {
+    pub fn to_svg(&self) -> String {
+        let mut svg = vec![
+            format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"12\">",
+                self.width, self.height
+            ),
+            "<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto-start-reverse\"><path d=\"M 0 0 L 10 5 L 0 10 z\"/></marker></defs>".to_string(),
+        ];
+        for edge in self.edges.iter() {
+            svg.push(format!(
+                "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" marker-end=\"url(#arrow)\"/>",
+                edge.svg_points(),
+                edge.kind.color()
+            ));
+        }
+        for node in self.nodes.iter() {
+            svg.push(format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"white\" stroke=\"black\"/>",
+                node.x, node.y, node.width, node.height
+            ));
+            for (y, line) in node.line_positions() {
+                svg.push(format!(
+                    "<text x=\"{}\" y=\"{}\" xml:space=\"preserve\">{}</text>",
+                    node.x + NODE_PADDING,
+                    y,
+                    escape_xml(line)
+                ));
+            }
+        }
+        svg.push("</svg>".to_string());
+        svg.join("\n") + "\n"
+    }
+}
+
+impl NodeLayout {
+    /// Baseline of every line of text in the node.
+    pub fn line_positions(&self) -> impl Iterator<Item = (u32, &String)> {
+        self.lines.iter().enumerate().map(|(index, line)| {
+            let y = self.y + NODE_PADDING + (index as u32 + 1) * LINE_HEIGHT - 3;
+            (y, line)
+        })
+    }
+
+    pub fn text_x(&self) -> u32 {
+        self.x + NODE_PADDING
+    }
+}
+
+impl EdgeLayout {
+    pub fn svg_points(&self) -> String {
+        self.points
+            .iter()
+            .map(|(x, y)| format!("{},{}", x, y))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prg::data::nrom_prg;
+
+    fn prg_data(code: &[u8]) -> PrgData {
+        PrgData::parse(nrom_prg(code)).unwrap()
+    }
+
+    fn location(address: u16) -> Location {
+        Location {
+            bank: Some(0),
+            address,
+        }
+    }
+
+    // LDX #$08; DEX; BNE $C002; JSR $C00C; JMP $C00C; RTS; RTS
+    const LOOP: [u8; 15] = [
+        0xA2, 0x08, 0xCA, 0xD0, 0xFD, 0x20, 0x0C, 0xC0, 0x4C, 0x0C, 0xC0, 0x60, 0x60, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn basic_blocks() {
+        let prg = prg_data(&LOOP);
+        let cfg = ControlFlowGraph::build(&prg, location(0xC000));
+        assert_eq!(
+            cfg.blocks.keys().copied().collect::<Vec<Location>>(),
+            vec![location(0xC000), location(0xC002), location(0xC005)]
+        );
+        assert_eq!(
+            cfg.blocks[&location(0xC000)].edges,
+            vec![Edge {
+                to: location(0xC002),
+                kind: EdgeKind::Unconditional
+            }]
+        );
+        assert_eq!(
+            cfg.blocks[&location(0xC002)].edges,
+            vec![
+                Edge {
+                    to: location(0xC002),
+                    kind: EdgeKind::Taken
+                },
+                Edge {
+                    to: location(0xC005),
+                    kind: EdgeKind::NotTaken
+                }
+            ]
+        );
+        // The JSR stays in the block, the JMP to another subroutine is a tail call
+        let last = &cfg.blocks[&location(0xC005)];
+        assert_eq!(last.instructions.len(), 2);
+        assert!(last.edges.is_empty());
+    }
+
+    #[test]
+    fn dot_and_svg() {
+        let prg = prg_data(&LOOP);
+        let cfg = ControlFlowGraph::build(&prg, location(0xC000));
+        let dot = cfg.to_dot(&prg);
+        assert!(dot.starts_with("digraph cfg {"));
+        assert!(dot.contains("block_00C000 [label=\"sub_C000:\\lC000  LDX #$08\\l2 cycles\\l\"];"));
+        assert!(dot.contains("block_00C002 -> block_00C002 [color=\"#16a34a\"];"));
+
+        let layout = cfg.layout(&prg);
+        assert_eq!(layout.nodes.len(), 3);
+        assert_eq!(layout.edges.len(), 3);
+        // The loop edge goes around the right of the graph
+        assert_eq!(layout.edges[1].points.len(), 4);
+        let svg = layout.to_svg();
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert!(svg.contains("JSR sub_C00C"));
+    }
+}
@@ -1,6 +1,29 @@
-use std::{collections::HashMap, error, fmt};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error, fmt,
+};
 
-use super::instructions::Instruction;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    super::cdl::{PRG_CODE, PRG_DATA},
+    instructions::Instruction,
+    labels::{InvalidLabelError, Labels},
+    regions::{Region, RegionKind},
+    signatures::{find_signatures, Signature, SignatureMatch},
+    structs::{FieldType, StructTable},
+    symbols::SymbolFile,
+    tables::{find_pointers, Pointer, PointerPart},
+    text_table::TextTable,
+    values::{infer_values, Inferred},
+    xrefs::CrossReferences,
+};
+
+pub const PRG_BANK_SIZE: usize = 0x4000;
+const FIXED_BANK_ADDRESS: u16 = 0xC000;
+const SWITCHABLE_BANK_ADDRESS: u16 = 0x8000;
+const VECTORS_ADDRESS: u16 = 0xFFFA;
+const VECTOR_COUNT: usize = 3;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct InvalidPrgDataError;
@@ -17,16 +40,802 @@ impl error::Error for InvalidPrgDataError {
     }
 }
 
+/// CPU address, qualified by the PRG bank it belongs to. Addresses outside of
+/// PRG ROM (RAM, registers) and addresses whose bank can't be determined have no bank.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Location {
+    pub bank: Option<usize>,
+    pub address: u16,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bank {
+            Some(bank) => write!(f, "{:02X}:{:04X}", bank, self.address),
+            None => write!(f, "${:04X}", self.address),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct PrgBank {
+    pub number: usize,
+    pub base_address: u16,
+    pub fixed: bool,
+    pub bytes: Vec<u8>,
+    pub instructions: BTreeMap<u16, Instruction>,
+}
+
+impl PrgBank {
+    pub fn contains(&self, address: u16) -> bool {
+        address >= self.base_address && ((address - self.base_address) as usize) < self.bytes.len()
+    }
+
+    pub fn byte_at(&self, address: u16) -> Option<u8> {
+        self.contains(address)
+            .then(|| self.bytes[(address - self.base_address) as usize])
+    }
+
+    pub fn word_at(&self, address: u16) -> Option<u16> {
+        let lsb = self.byte_at(address)?;
+        let msb = self.byte_at(address.wrapping_add(1))?;
+        Some(u16::from_le_bytes([lsb, msb]))
+    }
+
+    fn slice_from(&self, address: u16) -> &[u8] {
+        &self.bytes[(address - self.base_address) as usize..]
+    }
+
+    /// Whether `address` is part of a decoded instruction.
+    pub fn is_code(&self, address: u16) -> bool {
+        self.instructions
+            .range(..=address)
+            .next_back()
+            .is_some_and(|(start, instruction)| {
+                (*start as usize + instruction.len()) > address as usize
+            })
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct PrgData {
-    // TODO: Use BTreeMap instead of HashMap
-    pub instructions: HashMap<u16, Instruction>,
+    pub banks: Vec<PrgBank>,
+    pub labels: Labels,
+    pub comments: BTreeMap<Location, String>,
+    pub segments: BTreeMap<Location, String>,
+    pub pointers: BTreeMap<Location, Pointer>,
+    pub xrefs: CrossReferences,
+    /// Access flags of every PRG ROM byte from an emulator CDL file, empty if none was loaded.
+    pub code_data_log: Vec<u8>,
+    pub regions: BTreeMap<Location, Region>,
+    pub structs: BTreeMap<Location, StructTable>,
+    /// Encoding of the text regions, from a `.tbl` file.
+    pub text_table: Option<TextTable>,
+    /// Stored values and indirect jump targets found by following register values.
+    pub inferred: BTreeMap<Location, Inferred>,
+    /// Signatures the user added to the built-in ones.
+    pub signatures: Vec<Signature>,
+    /// Known routines found in the code.
+    pub detected: Vec<SignatureMatch>,
+}
+
+/// How much of a bank a CDL file saw being executed or read.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Coverage {
+    pub code: usize,
+    pub data: usize,
+    pub total: usize,
+}
+
+impl Coverage {
+    pub fn percentage(&self, bytes: usize) -> f64 {
+        bytes as f64 * 100.0 / self.total as f64
+    }
 }
 
 impl PrgData {
-    pub fn parse(_prg_data: Vec<u8>) -> Result<PrgData, InvalidPrgDataError> {
-        Ok(PrgData {
-            instructions: HashMap::new(),
+    pub fn parse(prg_data: Vec<u8>) -> Result<PrgData, InvalidPrgDataError> {
+        if prg_data.is_empty() || !prg_data.len().is_multiple_of(PRG_BANK_SIZE) {
+            return Err(InvalidPrgDataError);
+        }
+        let bank_count = prg_data.len() / PRG_BANK_SIZE;
+        let banks = prg_data
+            .chunks(PRG_BANK_SIZE)
+            .enumerate()
+            .map(|(number, bytes)| {
+                let (base_address, fixed) = PrgData::bank_mapping(number, bank_count);
+                PrgBank {
+                    number,
+                    base_address,
+                    fixed,
+                    bytes: bytes.to_vec(),
+                    instructions: BTreeMap::new(),
+                }
+            })
+            .collect();
+
+        let mut prg_data = PrgData {
+            banks,
+            labels: Labels::default(),
+            comments: BTreeMap::new(),
+            segments: BTreeMap::new(),
+            pointers: BTreeMap::new(),
+            xrefs: CrossReferences::default(),
+            code_data_log: Vec::new(),
+            regions: BTreeMap::new(),
+            structs: BTreeMap::new(),
+            text_table: None,
+            inferred: BTreeMap::new(),
+            signatures: Vec::new(),
+            detected: Vec::new(),
+        };
+        let entry_points = prg_data.vector_locations();
+        prg_data.analyze(entry_points);
+        Ok(prg_data)
+    }
+
+    /// Up to 32KiB of PRG ROM is mapped in its entirety. Bigger ROMs are assumed
+    /// to have their last bank fixed at $C000 and the other ones switched in at $8000.
+    fn bank_mapping(number: usize, bank_count: usize) -> (u16, bool) {
+        match bank_count {
+            1 => (FIXED_BANK_ADDRESS, true),
+            2 => (
+                SWITCHABLE_BANK_ADDRESS + (number * PRG_BANK_SIZE) as u16,
+                true,
+            ),
+            _ if number == bank_count - 1 => (FIXED_BANK_ADDRESS, true),
+            _ => (SWITCHABLE_BANK_ADDRESS, false),
+        }
+    }
+
+    /// Finds the bank an `address` referenced from code in `from_bank` belongs to.
+    pub fn resolve(&self, from_bank: usize, address: u16) -> Location {
+        let bank = self
+            .banks
+            .iter()
+            .find(|bank| bank.fixed && bank.contains(address))
+            .or_else(|| {
+                self.banks
+                    .get(from_bank)
+                    .filter(|bank| bank.contains(address))
+            })
+            .map(|bank| bank.number);
+        Location { bank, address }
+    }
+
+    pub fn byte_at(&self, location: Location) -> Option<u8> {
+        self.banks.get(location.bank?)?.byte_at(location.address)
+    }
+
+    /// Bytes from `location` to the end of its bank.
+    pub fn instruction_bytes(&self, location: Location) -> Option<&[u8]> {
+        let bank = self.banks.get(location.bank?)?;
+        bank.contains(location.address)
+            .then(|| bank.slice_from(location.address))
+    }
+
+    pub fn is_code(&self, location: Location) -> bool {
+        location
+            .bank
+            .and_then(|bank| self.banks.get(bank))
+            .is_some_and(|bank| bank.is_code(location.address))
+    }
+
+    /// Name of the operand `address` of an instruction in `from_bank`. The second byte
+    /// of a `.word` pointer is named after the pointer.
+    pub fn operand_label(&self, from_bank: usize, address: u16) -> Option<String> {
+        let location = self.resolve(from_bank, address);
+        if let Some(name) = self.labels.name(&location) {
+            return Some(name.to_string());
+        }
+        let previous = Location {
+            bank: location.bank,
+            address: address.wrapping_sub(1),
+        };
+        self.pointers
+            .get(&previous)
+            .filter(|pointer| pointer.part == PointerPart::Word)
+            .and_then(|_| self.labels.name(&previous))
+            .map(|name| format!("{}+1", name))
+    }
+
+    /// Maps an offset into PRG ROM to the CPU address it has in its bank.
+    pub fn location_of_offset(&self, offset: usize) -> Option<Location> {
+        let bank = self.banks.get(offset / PRG_BANK_SIZE)?;
+        Some(Location {
+            bank: Some(bank.number),
+            address: bank.base_address + (offset % PRG_BANK_SIZE) as u16,
         })
     }
+
+    /// Applies names, comments and segments of an imported symbol file. Symbols whose
+    /// names can't be used as labels are skipped and reported back.
+    pub fn import_symbols(&mut self, symbol_file: SymbolFile) -> Vec<InvalidLabelError> {
+        let mut errors = Vec::new();
+        for symbol in symbol_file.symbols {
+            if let Some(name) = symbol.name {
+                if let Err(error) = self.labels.rename(symbol.location, name) {
+                    errors.push(error);
+                }
+            }
+            if let Some(comment) = symbol.comment {
+                self.comments.insert(symbol.location, comment);
+            }
+        }
+        for segment in symbol_file.segments {
+            self.segments.insert(segment.location, segment.name);
+        }
+        errors
+    }
+
+    /// Targets of the NMI, RESET and IRQ vectors.
+    pub fn vector_locations(&self) -> Vec<Location> {
+        let last_bank = self.banks.len() - 1;
+        (0..VECTOR_COUNT)
+            .filter_map(|vector| {
+                let vector_address = VECTORS_ADDRESS + (vector * 2) as u16;
+                self.banks[last_bank].word_at(vector_address)
+            })
+            .map(|address| self.resolve(last_bank, address))
+            .collect()
+    }
+
+    /// Disassembles from `entry_points`, from the pointer tables found on the way and from
+    /// the indirect jumps that value tracking resolves, then names and cross-references the
+    /// new code. Existing labels are kept.
+    fn analyze(&mut self, entry_points: Vec<Location>) {
+        let mut entry_points = entry_points;
+        entry_points.extend(
+            self.regions
+                .iter()
+                .filter(|(_, region)| region.kind == RegionKind::Code)
+                .map(|(location, _)| *location),
+        );
+        let region_pointers = self.region_pointers();
+        entry_points.extend(region_pointers.values().map(|pointer| pointer.target));
+        // Code reached through pointer tables can dispatch through tables of its own
+        let mut pointer_targets = entry_points.iter().copied().collect::<BTreeSet<Location>>();
+        self.pointers.extend(region_pointers);
+        while !entry_points.is_empty() {
+            self.disassemble(entry_points);
+            let pointers = find_pointers(self);
+            entry_points = pointers
+                .values()
+                .map(|pointer| pointer.target)
+                .filter(|target| pointer_targets.insert(*target))
+                .collect();
+            self.pointers.extend(pointers);
+            if entry_points.is_empty() {
+                self.inferred = infer_values(self);
+                entry_points = self
+                    .inferred
+                    .values()
+                    .filter_map(|inferred| match inferred {
+                        Inferred::Target(target) => Some(*target),
+                        Inferred::Value(_) => None,
+                    })
+                    .filter(|target| pointer_targets.insert(*target))
+                    .collect();
+            }
+        }
+        let generated = Labels::generate(self);
+        self.labels.regenerate(generated);
+        self.detect_signatures();
+        self.xrefs = CrossReferences::build(self);
+    }
+
+    /// Finds the known routines, naming the subroutines they start that weren't renamed.
+    fn detect_signatures(&mut self) {
+        self.detected = find_signatures(self);
+        for found in self.detected.iter() {
+            if let Some(subroutine) = found.subroutine {
+                self.labels.name_generated(subroutine, &found.name);
+            }
+        }
+    }
+
+    /// Replaces the user signatures, the names of the previous ones go back to generated ones.
+    pub fn set_signatures(&mut self, signatures: Vec<Signature>) {
+        self.signatures = signatures;
+        let generated = Labels::generate(self);
+        self.labels.regenerate(generated);
+        self.detect_signatures();
+    }
+
+    /// Disassembles everything a CDL file saw being executed, and keeps the disassembler
+    /// out of the bytes it saw being read as data.
+    pub fn apply_code_data_log(&mut self, flags: Vec<u8>) {
+        self.code_data_log = flags;
+        for bank in self.banks.iter_mut() {
+            bank.instructions.clear();
+        }
+        self.pointers.clear();
+        let mut entry_points = self.vector_locations();
+        let mut offset = 0;
+        while offset < self.code_data_log.len() {
+            let logged_code = self.code_data_log[offset] & PRG_CODE != 0;
+            let instruction = self
+                .location_of_offset(offset)
+                .filter(|_| logged_code)
+                .and_then(|location| {
+                    let instruction =
+                        Instruction::decode(self.instruction_bytes(location)?, location.address)?;
+                    entry_points.push(location);
+                    Some(instruction)
+                });
+            offset += instruction.map_or(1, |instruction| instruction.len());
+        }
+        self.analyze(entry_points);
+    }
+
+    /// Offset into its bank of `length` bytes at `location`, if they're all in the bank.
+    fn bank_offset(&self, location: Location, length: usize) -> Option<(usize, usize)> {
+        let bank = self.banks.get(location.bank?)?;
+        let start = (location.address as usize)
+            .checked_sub(bank.base_address as usize)
+            .filter(|start| start + length <= bank.bytes.len())?;
+        Some((bank.number, start))
+    }
+
+    /// Overwrites the bytes at `location` and disassembles again so the code it changes is followed.
+    pub fn patch(&mut self, location: Location, bytes: &[u8]) -> Result<(), InvalidPrgDataError> {
+        let (bank, start) = self
+            .bank_offset(location, bytes.len())
+            .ok_or(InvalidPrgDataError)?;
+        self.banks[bank].bytes[start..start + bytes.len()].copy_from_slice(bytes);
+        self.reanalyze(Some((location, bytes.len())));
+        Ok(())
+    }
+
+    /// Overwrites data in several places at once, then disassembles again. Nothing is written
+    /// if any of the places is outside of its bank.
+    pub fn overwrite(&mut self, writes: &[(Location, Vec<u8>)]) -> Result<(), InvalidPrgDataError> {
+        let offsets = writes
+            .iter()
+            .map(|(location, bytes)| self.bank_offset(*location, bytes.len()))
+            .collect::<Option<Vec<(usize, usize)>>>()
+            .ok_or(InvalidPrgDataError)?;
+        for ((bank, start), (_, bytes)) in offsets.into_iter().zip(writes.iter()) {
+            self.banks[bank].bytes[start..start + bytes.len()].copy_from_slice(bytes);
+        }
+        self.reanalyze(None);
+        Ok(())
+    }
+
+    /// Removes the regions and struct tables overlapping `length` bytes from `location`.
+    fn clear_regions(&mut self, location: Location, length: usize) {
+        let end = location.address as usize + length;
+        let outside = |start: &Location, start_length: usize| {
+            start.bank != location.bank
+                || start.address as usize + start_length <= location.address as usize
+                || start.address as usize >= end
+        };
+        self.regions
+            .retain(|start, region| outside(start, region.length));
+        self.structs
+            .retain(|start, table| outside(start, table.length()));
+    }
+
+    /// Marks `length` bytes from `location` as `kind`, replacing the regions they overlap.
+    /// Without a kind, the disassembler decides again.
+    pub fn set_region(&mut self, location: Location, length: usize, kind: Option<RegionKind>) {
+        self.clear_regions(location, length);
+        if let Some(kind) = kind {
+            self.regions.insert(location, Region { kind, length });
+        }
+        self.reanalyze(None);
+    }
+
+    /// Lays out `table` from `location`. Its bytes are kept out of the disassembler and its
+    /// pointer fields are followed.
+    pub fn set_struct_table(&mut self, location: Location, table: StructTable) {
+        let length = table.length();
+        self.clear_regions(location, length);
+        self.regions.insert(
+            location,
+            Region {
+                kind: RegionKind::Byte,
+                length,
+            },
+        );
+        self.structs.insert(location, table);
+        self.reanalyze(None);
+    }
+
+    pub fn import_struct_tables(
+        &mut self,
+        tables: impl IntoIterator<Item = (Location, StructTable)>,
+    ) {
+        for (location, table) in tables {
+            self.regions.insert(
+                location,
+                Region {
+                    kind: RegionKind::Byte,
+                    length: table.length(),
+                },
+            );
+            self.structs.insert(location, table);
+        }
+        self.reanalyze(None);
+    }
+
+    pub fn import_regions(&mut self, regions: impl IntoIterator<Item = (Location, Region)>) {
+        self.regions.extend(regions);
+        self.reanalyze(None);
+    }
+
+    /// Disassembles again from every instruction start found so far, except for the ones
+    /// overwritten by a patch of `patched`, whose start is disassembled instead.
+    fn reanalyze(&mut self, patched: Option<(Location, usize)>) {
+        let overwritten = |bank: usize, address: u16, length: usize| {
+            patched.is_some_and(|(location, patched_length)| {
+                location.bank == Some(bank)
+                    && (address as usize + length) > location.address as usize
+                    && (address as usize) < location.address as usize + patched_length
+            })
+        };
+        let mut entry_points = self.vector_locations();
+        for bank in self.banks.iter_mut() {
+            entry_points.extend(
+                bank.instructions
+                    .iter()
+                    .filter(|(address, instruction)| {
+                        !overwritten(bank.number, **address, instruction.len())
+                    })
+                    .map(|(address, _)| Location {
+                        bank: Some(bank.number),
+                        address: *address,
+                    }),
+            );
+            bank.instructions.clear();
+        }
+        entry_points.extend(patched.map(|(location, _)| location));
+        self.pointers.clear();
+        self.analyze(entry_points);
+    }
+
+    /// Words of the regions marked as pointers and of the pointer fields of struct tables.
+    fn region_pointers(&self) -> BTreeMap<Location, Pointer> {
+        let pointer_regions = self
+            .regions
+            .iter()
+            .filter(|(_, region)| region.kind == RegionKind::Pointer)
+            .flat_map(|(start, region)| {
+                (0..region.length.saturating_sub(1))
+                    .step_by(2)
+                    .map(move |offset| (*start, offset))
+            });
+        let pointer_fields = self.structs.iter().flat_map(|(start, table)| {
+            let size = table.layout.size();
+            (0..table.count).flat_map(move |record| {
+                table
+                    .layout
+                    .offsets()
+                    .filter(|(_, field)| field.field_type == FieldType::Pointer)
+                    .map(move |(offset, _)| (*start, record * size + offset))
+            })
+        });
+        let mut pointers = BTreeMap::new();
+        for (start, offset) in pointer_regions.chain(pointer_fields) {
+            let Some(bank) = start.bank else {
+                continue;
+            };
+            let location = Location {
+                bank: Some(bank),
+                address: start.address.wrapping_add(offset as u16),
+            };
+            let Some(value) = self.banks[bank].word_at(location.address) else {
+                continue;
+            };
+            pointers.insert(
+                location,
+                Pointer {
+                    part: PointerPart::Word,
+                    target: self.resolve(bank, value),
+                    adjust: 0,
+                },
+            );
+        }
+        pointers
+    }
+
+    /// Struct table covering `location`, along with where it starts.
+    pub fn struct_table_at(&self, location: Location) -> Option<(Location, &StructTable)> {
+        self.structs
+            .range(..=location)
+            .next_back()
+            .filter(|(start, table)| {
+                start.bank == location.bank
+                    && ((location.address - start.address) as usize) < table.length()
+            })
+            .map(|(start, table)| (*start, table))
+    }
+
+    /// Region covering `location`, along with where it starts.
+    pub fn region_at(&self, location: Location) -> Option<(Location, Region)> {
+        self.regions
+            .range(..=location)
+            .next_back()
+            .filter(|(start, region)| {
+                start.bank == location.bank && region.contains(start.address, location.address)
+            })
+            .map(|(start, region)| (*start, *region))
+    }
+
+    /// PRG ROM bytes of every bank, patches included.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.banks
+            .iter()
+            .flat_map(|bank| bank.bytes.iter().copied())
+            .collect()
+    }
+
+    fn logged_as_data(&self, bank: usize, address: u16) -> bool {
+        let offset = bank * PRG_BANK_SIZE + (address - self.banks[bank].base_address) as usize;
+        self.code_data_log
+            .get(offset)
+            .is_some_and(|flags| flags & (PRG_CODE | PRG_DATA) == PRG_DATA)
+    }
+
+    /// Whether `location` is outside of the code and regions, and wasn't seen being accessed if
+    /// a CDL file was loaded.
+    pub fn is_unused(&self, location: Location) -> bool {
+        let Some(bank) = location.bank.and_then(|bank| self.banks.get(bank)) else {
+            return false;
+        };
+        let offset = bank.number * PRG_BANK_SIZE + (location.address - bank.base_address) as usize;
+        !bank.is_code(location.address)
+            && self.region_at(location).is_none()
+            && self
+                .code_data_log
+                .get(offset)
+                .is_none_or(|flags| flags & (PRG_CODE | PRG_DATA) == 0)
+    }
+
+    pub fn coverage(&self, bank: usize) -> Option<Coverage> {
+        let flags = self
+            .code_data_log
+            .get(bank * PRG_BANK_SIZE..(bank + 1) * PRG_BANK_SIZE)?;
+        Some(Coverage {
+            code: flags.iter().filter(|flags| *flags & PRG_CODE != 0).count(),
+            data: flags
+                .iter()
+                .filter(|flags| *flags & (PRG_CODE | PRG_DATA) == PRG_DATA)
+                .count(),
+            total: flags.len(),
+        })
+    }
+
+    /// Decodes instructions by following the control flow from `entry_points`.
+    pub fn disassemble(&mut self, entry_points: Vec<Location>) {
+        let mut pending = entry_points;
+        while let Some(location) = pending.pop() {
+            let Some(bank_number) = location.bank else {
+                continue;
+            };
+            let bank = &self.banks[bank_number];
+            let address = location.address;
+            let marked_as_data = self
+                .region_at(location)
+                .is_some_and(|(_, region)| region.kind != RegionKind::Code);
+            if !bank.contains(address)
+                || bank.instructions.contains_key(&address)
+                || self.logged_as_data(bank_number, address)
+                || marked_as_data
+            {
+                continue;
+            }
+            let overlaps = bank.instructions.range(..address).next_back().is_some_and(
+                |(start, instruction)| (*start as usize + instruction.len()) > address as usize,
+            );
+            if overlaps {
+                continue;
+            }
+            let Some(instruction) = Instruction::decode(bank.slice_from(address), address) else {
+                continue;
+            };
+            let next_address = address as usize + instruction.len();
+            let collides = bank
+                .instructions
+                .range(address..)
+                .next()
+                .is_some_and(|(start, _)| (*start as usize) < next_address);
+            let pointer_bytes = self
+                .pointers
+                .range(
+                    ..Location {
+                        bank: Some(bank_number),
+                        address: next_address.min(u16::MAX as usize) as u16,
+                    },
+                )
+                .next_back()
+                .is_some_and(|(start, pointer)| {
+                    start.bank == Some(bank_number)
+                        && start.address as usize + pointer.part.len() > address as usize
+                });
+            let runs_into_data = self
+                .regions
+                .range(location..)
+                .take_while(|(start, _)| {
+                    start.bank == location.bank && (start.address as usize) < next_address
+                })
+                .any(|(_, region)| region.kind != RegionKind::Code);
+            if collides || pointer_bytes || runs_into_data {
+                continue;
+            }
+
+            if let Some(target) = instruction.jump_target() {
+                pending.push(self.resolve(bank_number, target));
+            }
+            if !instruction.ends_flow() && next_address <= u16::MAX as usize {
+                pending.push(self.resolve(bank_number, next_address as u16));
+            }
+            self.banks[bank_number]
+                .instructions
+                .insert(address, instruction);
+        }
+    }
+}
+
+/// A 16KiB NROM bank starting with `code`.
+#[cfg(test)]
+pub(crate) fn nrom_prg(code: &[u8]) -> Vec<u8> {
+    let mut prg = vec![0u8; PRG_BANK_SIZE];
+    prg[..code.len()].copy_from_slice(code);
+    // NMI, RESET and IRQ all point to the start of the bank
+    prg[PRG_BANK_SIZE - 6..].copy_from_slice(&[0x00, 0xC0, 0x00, 0xC0, 0x00, 0xC0]);
+    prg
+}
+
+/// Four 16KiB banks as on MMC1, each starting with the code of `banks`. The vectors of the
+/// fixed bank point to its start.
+#[cfg(test)]
+pub(crate) fn mmc1_prg(banks: [&[u8]; 4]) -> Vec<u8> {
+    let mut prg = banks
+        .iter()
+        .flat_map(|code| {
+            let mut bank = vec![0u8; PRG_BANK_SIZE];
+            bank[..code.len()].copy_from_slice(code);
+            bank
+        })
+        .collect::<Vec<u8>>();
+    let end = prg.len();
+    prg[end - 6..].copy_from_slice(&[0x00, 0xC0, 0x00, 0xC0, 0x00, 0xC0]);
+    prg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prg::instructions::{Mnemonic, Operand},
+        reader::HEADER_SIZE_BYTES,
+    };
+
+    fn location(address: u16) -> Location {
+        Location {
+            bank: Some(0),
+            address,
+        }
+    }
+
+    #[test]
+    fn parse_invalid_prg_data() {
+        assert_eq!(PrgData::parse(vec![0xEA; 0x100]), Err(InvalidPrgDataError));
+        assert_eq!(PrgData::parse(Vec::new()), Err(InvalidPrgDataError));
+    }
+
+    #[test]
+    fn disassemble_from_vectors() {
+        // LDX #$FF; NOP; JSR $C008; RTS; .byte $FF; RTS
+        let prg = nrom_prg(&[0xA2, 0xFF, 0xEA, 0x20, 0x08, 0xC0, 0x60, 0xFF, 0x60]);
+        let result = PrgData::parse(prg).unwrap();
+        let instructions = &result.banks[0].instructions;
+        assert_eq!(
+            instructions.keys().copied().collect::<Vec<u16>>(),
+            vec![0xC000, 0xC002, 0xC003, 0xC006, 0xC008]
+        );
+        assert_eq!(instructions[&0xC003].mnemonic, Mnemonic::Jsr);
+        assert_eq!(
+            instructions[&0xC003].operand,
+            Some(Operand::Absolute { address: 0xC008 })
+        );
+    }
+
+    #[test]
+    fn apply_code_data_log() {
+        // LDA #$01; BNE $C005; .byte $FF; RTS; LDA #$02; RTS
+        let prg = nrom_prg(&[0xA9, 0x01, 0xD0, 0x01, 0xFF, 0x60, 0xA9, 0x02, 0x60]);
+        let mut result = PrgData::parse(prg).unwrap();
+        assert!(result.banks[0].instructions.contains_key(&0xC004));
+        result
+            .labels
+            .rename(location(0xC000), "reset".to_string())
+            .unwrap();
+
+        let mut flags = vec![0u8; PRG_BANK_SIZE];
+        flags[..4].copy_from_slice(&[PRG_CODE; 4]);
+        flags[4] = PRG_DATA;
+        flags[6..9].copy_from_slice(&[PRG_CODE; 3]);
+        result.apply_code_data_log(flags);
+
+        let instructions = &result.banks[0].instructions;
+        assert!(!instructions.contains_key(&0xC004));
+        assert!(instructions.contains_key(&0xC006));
+        assert_eq!(result.labels.name(&location(0xC000)), Some("reset"));
+        let coverage = result.coverage(0).unwrap();
+        assert_eq!((coverage.code, coverage.data), (7, 1));
+        assert_eq!(result.coverage(1), None);
+    }
+
+    #[test]
+    fn patch_instruction() {
+        // LDA #$01; JSR $C006; RTS; RTS
+        let mut prg =
+            PrgData::parse(nrom_prg(&[0xA9, 0x01, 0x20, 0x06, 0xC0, 0x60, 0x60])).unwrap();
+        // LDX #$05; LDY #$06 over the first instruction and the JSR
+        prg.patch(location(0xC000), &[0xA2, 0x05, 0xA0, 0x06, 0xEA])
+            .unwrap();
+        let instructions = &prg.banks[0].instructions;
+        assert_eq!(instructions[&0xC000].mnemonic, Mnemonic::Ldx);
+        assert_eq!(instructions[&0xC002].mnemonic, Mnemonic::Ldy);
+        assert_eq!(
+            instructions.keys().copied().collect::<Vec<u16>>(),
+            vec![0xC000, 0xC002, 0xC004, 0xC005, 0xC006]
+        );
+        assert_eq!(prg.to_bytes()[..6], [0xA2, 0x05, 0xA0, 0x06, 0xEA, 0x60]);
+        assert_eq!(
+            prg.patch(location(0xFFFF), &[0xEA, 0xEA]),
+            Err(InvalidPrgDataError)
+        );
+    }
+
+    #[test]
+    fn import_symbols() {
+        let mut prg = PrgData::parse(nrom_prg(&[0x60])).unwrap();
+        let symbol_file = SymbolFile::parse(
+            "game.nes.0.nl",
+            "$C000#reset#Entry point\n$0300#bad name#\n",
+            &prg,
+            HEADER_SIZE_BYTES,
+        )
+        .unwrap();
+        let errors = prg.import_symbols(symbol_file);
+        let reset = Location {
+            bank: Some(0),
+            address: 0xC000,
+        };
+        assert_eq!(prg.labels.name(&reset), Some("reset"));
+        assert_eq!(prg.comments.get(&reset), Some(&"Entry point".to_string()));
+        assert_eq!(
+            errors,
+            vec![InvalidLabelError::InvalidName("bad name".to_string())]
+        );
+    }
+
+    #[test]
+    fn resolve_bank_of_address() {
+        let prg = PrgData::parse(vec![0x60; PRG_BANK_SIZE * 4]).unwrap();
+        assert_eq!(
+            prg.resolve(1, 0x8123),
+            Location {
+                bank: Some(1),
+                address: 0x8123
+            }
+        );
+        assert_eq!(
+            prg.resolve(1, 0xC123),
+            Location {
+                bank: Some(3),
+                address: 0xC123
+            }
+        );
+        assert_eq!(
+            prg.resolve(3, 0x8123),
+            Location {
+                bank: None,
+                address: 0x8123
+            }
+        );
+    }
 }
@@ -1,7 +1,6 @@
 use std::fmt;
 
-#[allow(dead_code)]
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub enum Index {
     X,
     Y,
@@ -17,8 +16,7 @@ impl fmt::Display for Index {
     }
 }
 
-#[allow(dead_code)]
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub enum Operand {
     Immediate { value: u8 },
     ZeroPage { address: u8 },
@@ -30,24 +28,52 @@ pub enum Operand {
     PostIndexedIndirect { address: u8 },
 }
 
-impl fmt::Display for Operand {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Operand {
+    pub fn address(&self) -> Option<u16> {
         use Operand::*;
+        match *self {
+            Immediate { .. } => None,
+            ZeroPage { address }
+            | ZeroPageIndexed { address, .. }
+            | PreIndexedIndirect { address }
+            | PostIndexedIndirect { address } => Some(address as u16),
+            Absolute { address } | AbsoluteIndexed { address, .. } | Indirect { address } => {
+                Some(address)
+            }
+        }
+    }
+
+    pub fn to_string_with_label(self, label: Option<&str>) -> String {
+        use Operand::*;
+        let address = match label {
+            Some(label) => label.to_string(),
+            None => match self {
+                Absolute { address } | AbsoluteIndexed { address, .. } | Indirect { address } => {
+                    format!("${:04X}", address)
+                }
+                _ => format!("${:02X}", self.address().unwrap_or_default()),
+            },
+        };
         match self {
-            Immediate { value } => write!(f, "#${:02X}", value),
-            ZeroPage { address } => write!(f, "${:02X}", address),
-            ZeroPageIndexed { address, index } => write!(f, "${:02X}, {}", address, index),
-            Absolute { address } => write!(f, "${:04X}", address),
-            AbsoluteIndexed { address, index } => write!(f, "${:04X}, {}", address, index),
-            Indirect { address } => write!(f, "(${:04X})", address),
-            PreIndexedIndirect { address } => write!(f, "(${:02X}, X)", address),
-            PostIndexedIndirect { address } => write!(f, "(${:02X}), Y", address),
+            Immediate { value } => format!("#${:02X}", value),
+            ZeroPage { .. } | Absolute { .. } => address,
+            ZeroPageIndexed { index, .. } | AbsoluteIndexed { index, .. } => {
+                format!("{}, {}", address, index)
+            }
+            Indirect { .. } => format!("({})", address),
+            PreIndexedIndirect { .. } => format!("({}, X)", address),
+            PostIndexedIndirect { .. } => format!("({}), Y", address),
         }
     }
 }
 
-#[allow(dead_code)]
-#[derive(PartialEq, Debug, Clone)]
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_string_with_label(None))
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub enum Mnemonic {
     Lda,
     Ldx,
@@ -139,6 +165,15 @@ pub enum Mnemonic {
     Stp,
 }
 
+impl Mnemonic {
+    /// Whether `name` is the mnemonic of an opcode, legal or not. Assemblers ignore case.
+    pub fn is_mnemonic(name: &str) -> bool {
+        OPCODES
+            .iter()
+            .any(|(mnemonic, _)| mnemonic.to_string().eq_ignore_ascii_case(name))
+    }
+}
+
 impl fmt::Display for Mnemonic {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Mnemonic::*;
@@ -224,9 +259,577 @@ impl fmt::Display for Mnemonic {
     }
 }
 
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum AddressingMode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    IndirectX,
+    IndirectY,
+    Relative,
+}
+
+impl AddressingMode {
+    pub fn operand_size(&self) -> usize {
+        use AddressingMode::*;
+        match self {
+            Implied | Accumulator => 0,
+            Immediate | ZeroPage | ZeroPageX | ZeroPageY | IndirectX | IndirectY | Relative => 1,
+            Absolute | AbsoluteX | AbsoluteY | Indirect => 2,
+        }
+    }
+}
+
+use AddressingMode::*;
+use Mnemonic::*;
+
+pub const OPCODES: [(Mnemonic, AddressingMode); 256] = [
+    (Brk, Implied),
+    (Ora, IndirectX),
+    (Stp, Implied),
+    (Slo, IndirectX),
+    (Nop, ZeroPage),
+    (Ora, ZeroPage),
+    (Asl, ZeroPage),
+    (Slo, ZeroPage),
+    (Php, Implied),
+    (Ora, Immediate),
+    (Asl, Accumulator),
+    (Anc, Immediate),
+    (Nop, Absolute),
+    (Ora, Absolute),
+    (Asl, Absolute),
+    (Slo, Absolute),
+    (Bpl, Relative),
+    (Ora, IndirectY),
+    (Stp, Implied),
+    (Slo, IndirectY),
+    (Nop, ZeroPageX),
+    (Ora, ZeroPageX),
+    (Asl, ZeroPageX),
+    (Slo, ZeroPageX),
+    (Clc, Implied),
+    (Ora, AbsoluteY),
+    (Nop, Implied),
+    (Slo, AbsoluteY),
+    (Nop, AbsoluteX),
+    (Ora, AbsoluteX),
+    (Asl, AbsoluteX),
+    (Slo, AbsoluteX),
+    (Jsr, Absolute),
+    (And, IndirectX),
+    (Stp, Implied),
+    (Rla, IndirectX),
+    (Bit, ZeroPage),
+    (And, ZeroPage),
+    (Rol, ZeroPage),
+    (Rla, ZeroPage),
+    (Plp, Implied),
+    (And, Immediate),
+    (Rol, Accumulator),
+    (Anc, Immediate),
+    (Bit, Absolute),
+    (And, Absolute),
+    (Rol, Absolute),
+    (Rla, Absolute),
+    (Bmi, Relative),
+    (And, IndirectY),
+    (Stp, Implied),
+    (Rla, IndirectY),
+    (Nop, ZeroPageX),
+    (And, ZeroPageX),
+    (Rol, ZeroPageX),
+    (Rla, ZeroPageX),
+    (Sec, Implied),
+    (And, AbsoluteY),
+    (Nop, Implied),
+    (Rla, AbsoluteY),
+    (Nop, AbsoluteX),
+    (And, AbsoluteX),
+    (Rol, AbsoluteX),
+    (Rla, AbsoluteX),
+    (Rti, Implied),
+    (Eor, IndirectX),
+    (Stp, Implied),
+    (Sre, IndirectX),
+    (Nop, ZeroPage),
+    (Eor, ZeroPage),
+    (Lsr, ZeroPage),
+    (Sre, ZeroPage),
+    (Pha, Implied),
+    (Eor, Immediate),
+    (Lsr, Accumulator),
+    (Alr, Immediate),
+    (Jmp, Absolute),
+    (Eor, Absolute),
+    (Lsr, Absolute),
+    (Sre, Absolute),
+    (Bvc, Relative),
+    (Eor, IndirectY),
+    (Stp, Implied),
+    (Sre, IndirectY),
+    (Nop, ZeroPageX),
+    (Eor, ZeroPageX),
+    (Lsr, ZeroPageX),
+    (Sre, ZeroPageX),
+    (Cli, Implied),
+    (Eor, AbsoluteY),
+    (Nop, Implied),
+    (Sre, AbsoluteY),
+    (Nop, AbsoluteX),
+    (Eor, AbsoluteX),
+    (Lsr, AbsoluteX),
+    (Sre, AbsoluteX),
+    (Rts, Implied),
+    (Adc, IndirectX),
+    (Stp, Implied),
+    (Rra, IndirectX),
+    (Nop, ZeroPage),
+    (Adc, ZeroPage),
+    (Ror, ZeroPage),
+    (Rra, ZeroPage),
+    (Pla, Implied),
+    (Adc, Immediate),
+    (Ror, Accumulator),
+    (Arr, Immediate),
+    (Jmp, Indirect),
+    (Adc, Absolute),
+    (Ror, Absolute),
+    (Rra, Absolute),
+    (Bvs, Relative),
+    (Adc, IndirectY),
+    (Stp, Implied),
+    (Rra, IndirectY),
+    (Nop, ZeroPageX),
+    (Adc, ZeroPageX),
+    (Ror, ZeroPageX),
+    (Rra, ZeroPageX),
+    (Sei, Implied),
+    (Adc, AbsoluteY),
+    (Nop, Implied),
+    (Rra, AbsoluteY),
+    (Nop, AbsoluteX),
+    (Adc, AbsoluteX),
+    (Ror, AbsoluteX),
+    (Rra, AbsoluteX),
+    (Nop, Immediate),
+    (Sta, IndirectX),
+    (Nop, Immediate),
+    (Sax, IndirectX),
+    (Sty, ZeroPage),
+    (Sta, ZeroPage),
+    (Stx, ZeroPage),
+    (Sax, ZeroPage),
+    (Dey, Implied),
+    (Nop, Immediate),
+    (Txa, Implied),
+    (Xaa, Immediate),
+    (Sty, Absolute),
+    (Sta, Absolute),
+    (Stx, Absolute),
+    (Sax, Absolute),
+    (Bcc, Relative),
+    (Sta, IndirectY),
+    (Stp, Implied),
+    (Ahx, IndirectY),
+    (Sty, ZeroPageX),
+    (Sta, ZeroPageX),
+    (Stx, ZeroPageY),
+    (Sax, ZeroPageY),
+    (Tya, Implied),
+    (Sta, AbsoluteY),
+    (Txs, Implied),
+    (Tas, AbsoluteY),
+    (Shy, AbsoluteX),
+    (Sta, AbsoluteX),
+    (Shx, AbsoluteY),
+    (Ahx, AbsoluteY),
+    (Ldy, Immediate),
+    (Lda, IndirectX),
+    (Ldx, Immediate),
+    (Lax, IndirectX),
+    (Ldy, ZeroPage),
+    (Lda, ZeroPage),
+    (Ldx, ZeroPage),
+    (Lax, ZeroPage),
+    (Tay, Implied),
+    (Lda, Immediate),
+    (Tax, Implied),
+    (Lxa, Immediate),
+    (Ldy, Absolute),
+    (Lda, Absolute),
+    (Ldx, Absolute),
+    (Lax, Absolute),
+    (Bcs, Relative),
+    (Lda, IndirectY),
+    (Stp, Implied),
+    (Lax, IndirectY),
+    (Ldy, ZeroPageX),
+    (Lda, ZeroPageX),
+    (Ldx, ZeroPageY),
+    (Lax, ZeroPageY),
+    (Clv, Implied),
+    (Lda, AbsoluteY),
+    (Tsx, Implied),
+    (Las, AbsoluteY),
+    (Ldy, AbsoluteX),
+    (Lda, AbsoluteX),
+    (Ldx, AbsoluteY),
+    (Lax, AbsoluteY),
+    (Cpy, Immediate),
+    (Cmp, IndirectX),
+    (Nop, Immediate),
+    (Dcp, IndirectX),
+    (Cpy, ZeroPage),
+    (Cmp, ZeroPage),
+    (Dec, ZeroPage),
+    (Dcp, ZeroPage),
+    (Iny, Implied),
+    (Cmp, Immediate),
+    (Dex, Implied),
+    (Axs, Immediate),
+    (Cpy, Absolute),
+    (Cmp, Absolute),
+    (Dec, Absolute),
+    (Dcp, Absolute),
+    (Bne, Relative),
+    (Cmp, IndirectY),
+    (Stp, Implied),
+    (Dcp, IndirectY),
+    (Nop, ZeroPageX),
+    (Cmp, ZeroPageX),
+    (Dec, ZeroPageX),
+    (Dcp, ZeroPageX),
+    (Cld, Implied),
+    (Cmp, AbsoluteY),
+    (Nop, Implied),
+    (Dcp, AbsoluteY),
+    (Nop, AbsoluteX),
+    (Cmp, AbsoluteX),
+    (Dec, AbsoluteX),
+    (Dcp, AbsoluteX),
+    (Cpx, Immediate),
+    (Sbc, IndirectX),
+    (Nop, Immediate),
+    (Isc, IndirectX),
+    (Cpx, ZeroPage),
+    (Sbc, ZeroPage),
+    (Inc, ZeroPage),
+    (Isc, ZeroPage),
+    (Inx, Implied),
+    (Sbc, Immediate),
+    (Nop, Implied),
+    (Sbc, Immediate),
+    (Cpx, Absolute),
+    (Sbc, Absolute),
+    (Inc, Absolute),
+    (Isc, Absolute),
+    (Beq, Relative),
+    (Sbc, IndirectY),
+    (Stp, Implied),
+    (Isc, IndirectY),
+    (Nop, ZeroPageX),
+    (Sbc, ZeroPageX),
+    (Inc, ZeroPageX),
+    (Isc, ZeroPageX),
+    (Sed, Implied),
+    (Sbc, AbsoluteY),
+    (Nop, Implied),
+    (Isc, AbsoluteY),
+    (Nop, AbsoluteX),
+    (Sbc, AbsoluteX),
+    (Inc, AbsoluteX),
+    (Isc, AbsoluteX),
+];
+
+/// CPU cycles an instruction takes. Indexed reads can take one more when the
+/// indexing crosses a page, branches one more when taken and another one when
+/// the target is on a different page.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Cycles {
+    pub base: u32,
+    pub page_cross: bool,
+    pub branch_taken: bool,
+}
+
+impl Cycles {
+    pub fn min(&self) -> u32 {
+        self.base
+    }
+
+    pub fn max(&self) -> u32 {
+        self.base + self.page_cross as u32 + self.branch_taken as u32
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct Instruction {
     pub mnemonic: Mnemonic,
     pub operand: Option<Operand>,
     pub bytes: Vec<u8>,
 }
+
+impl Instruction {
+    /// Decodes a single instruction located at `address`. Branch operands are
+    /// resolved to their absolute target. Returns `None` if `bytes` ends before
+    /// the operand does.
+    pub fn decode(bytes: &[u8], address: u16) -> Option<Instruction> {
+        let opcode = *bytes.first()?;
+        let (mnemonic, mode) = OPCODES[opcode as usize];
+        let bytes = bytes.get(0..1 + mode.operand_size())?.to_vec();
+        let byte = bytes.get(1).copied().unwrap_or_default();
+        let word = u16::from_le_bytes([byte, bytes.get(2).copied().unwrap_or_default()]);
+        let operand = match mode {
+            Implied | Accumulator => None,
+            AddressingMode::Immediate => Some(Operand::Immediate { value: byte }),
+            AddressingMode::ZeroPage => Some(Operand::ZeroPage { address: byte }),
+            ZeroPageX | ZeroPageY => Some(Operand::ZeroPageIndexed {
+                address: byte,
+                index: if mode == ZeroPageX {
+                    Index::X
+                } else {
+                    Index::Y
+                },
+            }),
+            AddressingMode::Absolute => Some(Operand::Absolute { address: word }),
+            AbsoluteX | AbsoluteY => Some(Operand::AbsoluteIndexed {
+                address: word,
+                index: if mode == AbsoluteX {
+                    Index::X
+                } else {
+                    Index::Y
+                },
+            }),
+            AddressingMode::Indirect => Some(Operand::Indirect { address: word }),
+            IndirectX => Some(Operand::PreIndexedIndirect { address: byte }),
+            IndirectY => Some(Operand::PostIndexedIndirect { address: byte }),
+            Relative => Some(Operand::Absolute {
+                address: address.wrapping_add(2).wrapping_add(byte as i8 as u16),
+            }),
+        };
+        Some(Instruction {
+            mnemonic,
+            operand,
+            bytes,
+        })
+    }
+
+    pub fn addressing_mode(&self) -> AddressingMode {
+        OPCODES[self.bytes[0] as usize].1
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether the opcode is documented, and the one assemblers emit for its mnemonic and mode.
+    pub fn is_official(&self) -> bool {
+        let undocumented = matches!(
+            self.mnemonic,
+            Alr | Anc
+                | Xaa
+                | Arr
+                | Dcp
+                | Isc
+                | Las
+                | Lax
+                | Lxa
+                | Rla
+                | Rra
+                | Sax
+                | Axs
+                | Ahx
+                | Shx
+                | Shy
+                | Slo
+                | Sre
+                | Tas
+                | Stp
+        );
+        let duplicate = match self.mnemonic {
+            Nop => self.bytes[0] != 0xEA,
+            Sbc => self.bytes[0] == 0xEB,
+            _ => false,
+        };
+        !undocumented && !duplicate
+    }
+
+    pub fn is_branch(&self) -> bool {
+        self.addressing_mode() == Relative
+    }
+
+    /// Whether execution never continues with the next instruction in memory.
+    pub fn ends_flow(&self) -> bool {
+        matches!(self.mnemonic, Jmp | Rts | Rti | Brk | Stp)
+    }
+
+    /// Whether the instruction stores to the address in its operand, read-modify-write included.
+    pub fn writes_memory(&self) -> bool {
+        self.operand.and_then(|operand| operand.address()).is_some()
+            && matches!(
+                self.mnemonic,
+                Sta | Stx
+                    | Sty
+                    | Sax
+                    | Ahx
+                    | Shx
+                    | Shy
+                    | Tas
+                    | Asl
+                    | Lsr
+                    | Rol
+                    | Ror
+                    | Inc
+                    | Dec
+                    | Slo
+                    | Rla
+                    | Sre
+                    | Rra
+                    | Dcp
+                    | Isc
+            )
+    }
+
+    /// Whether the instruction loads from the address in its operand, `JMP` through a pointer included.
+    pub fn reads_memory(&self) -> bool {
+        self.operand.and_then(|operand| operand.address()).is_some()
+            && self.jump_target().is_none()
+            && !matches!(self.mnemonic, Sta | Stx | Sty | Sax | Ahx | Shx | Shy | Tas)
+    }
+
+    /// Timing of the instruction when it's located at `address`.
+    pub fn cycles(&self, address: u16) -> Cycles {
+        let mode = self.addressing_mode();
+        let write = self.writes_memory();
+        let read_modify_write = write && self.reads_memory();
+        let base = match (self.mnemonic, mode) {
+            (Brk, _) => 7,
+            (Rts | Rti | Jsr, _) => 6,
+            (Pha | Php, _) => 3,
+            (Pla | Plp, _) => 4,
+            (Jmp, Absolute) => 3,
+            (_, Indirect) => 5,
+            (_, Implied | Accumulator | Immediate | Relative) => 2,
+            (_, ZeroPage) => 3 + 2 * read_modify_write as u32,
+            (_, ZeroPageX | ZeroPageY) => 4 + 2 * read_modify_write as u32,
+            (_, Absolute) => 4 + 2 * read_modify_write as u32,
+            (_, AbsoluteX | AbsoluteY) if read_modify_write => 7,
+            (_, AbsoluteX | AbsoluteY) => 4 + write as u32,
+            (_, IndirectX) => 6 + 2 * read_modify_write as u32,
+            (_, IndirectY) if read_modify_write => 8,
+            (_, IndirectY) => 5 + write as u32,
+        };
+        let page_cross = match mode {
+            AbsoluteX | AbsoluteY | IndirectY => !write,
+            Relative => self.jump_target().is_some_and(|target| {
+                let next_address = address.wrapping_add(self.len() as u16);
+                target & 0xFF00 != next_address & 0xFF00
+            }),
+            _ => false,
+        };
+        Cycles {
+            base,
+            page_cross,
+            branch_taken: mode == Relative,
+        }
+    }
+
+    /// Address that control may be transferred to, for branches, `JSR` and absolute `JMP`.
+    pub fn jump_target(&self) -> Option<u16> {
+        match (self.mnemonic, self.operand) {
+            (Jsr | Jmp, Some(Operand::Absolute { address })) => Some(address),
+            (_, Some(Operand::Absolute { address })) if self.is_branch() => Some(address),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_instructions() {
+        assert_eq!(
+            Instruction::decode(&[0xA2, 0xFF], 0x8000),
+            Some(Instruction {
+                mnemonic: Ldx,
+                operand: Some(Operand::Immediate { value: 0xFF }),
+                bytes: vec![0xA2, 0xFF],
+            })
+        );
+        assert_eq!(
+            Instruction::decode(&[0x1D, 0x05, 0x1E, 0x00], 0x8000),
+            Some(Instruction {
+                mnemonic: Ora,
+                operand: Some(Operand::AbsoluteIndexed {
+                    address: 0x1E05,
+                    index: Index::X
+                }),
+                bytes: vec![0x1D, 0x05, 0x1E],
+            })
+        );
+        assert_eq!(
+            Instruction::decode(&[0x0A], 0x8000),
+            Some(Instruction {
+                mnemonic: Asl,
+                operand: None,
+                bytes: vec![0x0A],
+            })
+        );
+        assert_eq!(Instruction::decode(&[0x20, 0x02], 0x8000), None);
+    }
+
+    #[test]
+    fn decode_branch_resolves_target() {
+        let instruction = Instruction::decode(&[0xD0, 0xFC], 0x800C).unwrap();
+        assert_eq!(
+            instruction.operand,
+            Some(Operand::Absolute { address: 0x800A })
+        );
+        assert_eq!(instruction.jump_target(), Some(0x800A));
+        assert!(!instruction.ends_flow());
+    }
+
+    #[test]
+    fn cycle_counts() {
+        let cycles =
+            |bytes: &[u8], address| Instruction::decode(bytes, address).unwrap().cycles(address);
+        // LDA $0300,X
+        let lda = cycles(&[0xBD, 0x00, 0x03], 0x8000);
+        assert_eq!((lda.min(), lda.max()), (4, 5));
+        // STA $0300,X
+        let sta = cycles(&[0x9D, 0x00, 0x03], 0x8000);
+        assert_eq!((sta.min(), sta.max()), (5, 5));
+        // INC $10
+        assert_eq!(cycles(&[0xE6, 0x10], 0x8000).base, 5);
+        // STA ($10), Y
+        assert_eq!(cycles(&[0x91, 0x10], 0x8000).base, 6);
+        // BNE to the same page, then across a page
+        let branch = cycles(&[0xD0, 0x10], 0x8000);
+        assert_eq!((branch.min(), branch.max()), (2, 3));
+        let branch = cycles(&[0xD0, 0xF0], 0x8000);
+        assert_eq!((branch.min(), branch.max()), (2, 4));
+        assert_eq!(cycles(&[0x20, 0x00, 0x80], 0x8000).base, 6);
+        assert_eq!(cycles(&[0x6C, 0x00, 0x03], 0x8000).base, 5);
+    }
+
+    #[test]
+    fn operand_with_label() {
+        let operand = Operand::PostIndexedIndirect { address: 0xFB };
+        assert_eq!(operand.to_string(), "($FB), Y");
+        assert_eq!(
+            operand.to_string_with_label(Some("data_00FB")),
+            "(data_00FB), Y"
+        );
+        let operand = Operand::Immediate { value: 0x10 };
+        assert_eq!(operand.to_string_with_label(Some("data_0010")), "#$10");
+    }
+}
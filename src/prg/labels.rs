@@ -0,0 +1,366 @@
+use std::{collections::BTreeMap, error, fmt};
+
+use super::{
+    data::{Location, PrgData},
+    instructions::Mnemonic,
+    registers::{hardware_register, MAPPER_REGISTERS_ADDRESS},
+    tables::PointerPart,
+    values::Inferred,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidLabelError {
+    InvalidName(String),
+    Duplicate(String),
+    /// Register or instruction names, which assemblers don't take as labels.
+    Reserved(String),
+}
+
+impl fmt::Display for InvalidLabelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidLabelError::InvalidName(name) => write!(f, "Invalid label name \"{}\"", name),
+            InvalidLabelError::Duplicate(name) => write!(f, "Label \"{}\" already exists", name),
+            InvalidLabelError::Reserved(name) => {
+                write!(f, "\"{}\" is a register or an instruction", name)
+            }
+        }
+    }
+}
+
+impl error::Error for InvalidLabelError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Kinds are ordered by priority, when an address is referenced in more than one way
+/// the label takes the name of the highest one.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum LabelKind {
+    Data,
+    Location,
+    Subroutine,
+}
+
+impl LabelKind {
+    fn prefix(&self) -> &'static str {
+        match self {
+            LabelKind::Data => "data",
+            LabelKind::Location => "loc",
+            LabelKind::Subroutine => "sub",
+        }
+    }
+
+    /// Generated name of a label at `location`. Switchable banks share their addresses, so
+    /// their labels also carry the bank number.
+    fn name(&self, location: Location, switchable: bool) -> String {
+        match location.bank {
+            Some(bank) if switchable => {
+                format!("{}_{:02X}_{:04X}", self.prefix(), bank, location.address)
+            }
+            _ => format!("{}_{:04X}", self.prefix(), location.address),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Label {
+    pub name: String,
+    pub kind: LabelKind,
+    /// Named after the routine a signature matched, which counts as a generated name.
+    pub signature: bool,
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Labels {
+    labels: BTreeMap<Location, Label>,
+}
+
+impl Labels {
+    /// Names every address referenced by the disassembled code.
+    pub fn generate(prg_data: &PrgData) -> Labels {
+        let mut labels = Labels::default();
+        let switchable = |location: Location| {
+            location
+                .bank
+                .and_then(|bank| prg_data.banks.get(bank))
+                .is_some_and(|bank| !bank.fixed)
+        };
+        for location in prg_data.vector_locations() {
+            let name = LabelKind::Subroutine.name(location, switchable(location));
+            labels.insert_generated(
+                location,
+                Label {
+                    name,
+                    kind: LabelKind::Subroutine,
+                    signature: false,
+                },
+            );
+        }
+        for bank in prg_data.banks.iter() {
+            for instruction in bank.instructions.values() {
+                let Some(address) = instruction.operand.and_then(|o| o.address()) else {
+                    continue;
+                };
+                let kind = match instruction.jump_target() {
+                    Some(_) if instruction.mnemonic == Mnemonic::Jsr => LabelKind::Subroutine,
+                    Some(_) => LabelKind::Location,
+                    // Stores to ROM are mapper writes, they're named when listed
+                    None if instruction.writes_memory() && address >= MAPPER_REGISTERS_ADDRESS => {
+                        continue
+                    }
+                    None => LabelKind::Data,
+                };
+                let location = prg_data.resolve(bank.number, address);
+                // The second byte of a .word pointer goes by the pointer's name
+                let previous = Location {
+                    bank: location.bank,
+                    address: address.wrapping_sub(1),
+                };
+                let pointer_high_byte = prg_data
+                    .pointers
+                    .get(&previous)
+                    .is_some_and(|pointer| pointer.part == PointerPart::Word);
+                if kind == LabelKind::Data && pointer_high_byte {
+                    continue;
+                }
+                let name = match hardware_register(address) {
+                    Some(register) if kind == LabelKind::Data => register.name.to_string(),
+                    _ => kind.name(location, switchable(location)),
+                };
+                let label = Label {
+                    name,
+                    kind,
+                    signature: false,
+                };
+                labels.insert_generated(location, label);
+            }
+        }
+        let resolved_jumps = prg_data
+            .inferred
+            .values()
+            .filter_map(|inferred| match inferred {
+                Inferred::Target(target) => Some(*target),
+                Inferred::Value(_) => None,
+            });
+        let pointer_targets = prg_data.pointers.values().map(|pointer| pointer.target);
+        for target in pointer_targets.chain(resolved_jumps) {
+            let name = LabelKind::Location.name(target, switchable(target));
+            labels.insert_generated(
+                target,
+                Label {
+                    name,
+                    kind: LabelKind::Location,
+                    signature: false,
+                },
+            );
+        }
+        labels
+    }
+
+    fn insert_generated(&mut self, location: Location, generated: Label) {
+        let kind = generated.kind;
+        self.labels
+            .entry(location)
+            .and_modify(|label| {
+                if label.kind < kind {
+                    *label = generated.clone();
+                }
+            })
+            .or_insert(generated);
+    }
+
+    /// Whether `label` has the name it would be generated with, or a signature's.
+    fn is_generated(location: &Location, label: &Label) -> bool {
+        let generated_name = [LabelKind::Data, LabelKind::Location, LabelKind::Subroutine]
+            .iter()
+            .any(|kind| {
+                label.name == kind.name(*location, false)
+                    || label.name == kind.name(*location, true)
+            });
+        let register_name =
+            hardware_register(location.address).is_some_and(|register| register.name == label.name);
+        label.signature || generated_name || register_name
+    }
+
+    /// Replaces the generated labels with `generated`, labels that were renamed are kept.
+    pub fn regenerate(&mut self, generated: Labels) {
+        let renamed = std::mem::replace(&mut self.labels, generated.labels)
+            .into_iter()
+            .filter(|(location, label)| !Labels::is_generated(location, label))
+            .collect::<Vec<(Location, Label)>>();
+        self.labels.extend(renamed);
+    }
+
+    /// Gives the label at `location` the name of the signature it matched, if it still has
+    /// its generated name and `name` isn't taken. The name stays a generated one.
+    pub fn name_generated(&mut self, location: Location, name: &str) {
+        let taken = self.labels.values().any(|label| label.name == name);
+        let Some(label) = self
+            .labels
+            .get_mut(&location)
+            .filter(|label| Labels::is_generated(&location, label))
+        else {
+            return;
+        };
+        if taken {
+            log::debug!("{} is already taken, {} keeps its name", name, location);
+            return;
+        }
+        label.name = name.to_string();
+        label.signature = true;
+    }
+
+    /// Labels named by the user or imported from symbol files.
+    pub fn renamed(&self) -> impl Iterator<Item = (&Location, &Label)> {
+        self.labels
+            .iter()
+            .filter(|(location, label)| !Labels::is_generated(location, label))
+    }
+
+    pub fn get(&self, location: &Location) -> Option<&Label> {
+        self.labels.get(location)
+    }
+
+    pub fn name(&self, location: &Location) -> Option<&str> {
+        self.get(location).map(|label| label.name.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Location, &Label)> {
+        self.labels.iter()
+    }
+
+    /// Renames the label at `location`, creating a new one if there is none yet.
+    pub fn rename(&mut self, location: Location, name: String) -> Result<(), InvalidLabelError> {
+        let valid = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(InvalidLabelError::InvalidName(name));
+        }
+        let register = ["A", "X", "Y"]
+            .iter()
+            .any(|register| register.eq_ignore_ascii_case(&name));
+        if register || Mnemonic::is_mnemonic(&name) {
+            return Err(InvalidLabelError::Reserved(name));
+        }
+        let duplicate = self
+            .labels
+            .iter()
+            .any(|(other, label)| *other != location && label.name == name);
+        if duplicate {
+            return Err(InvalidLabelError::Duplicate(name));
+        }
+        self.labels
+            .entry(location)
+            .and_modify(|label| {
+                label.name.clone_from(&name);
+                label.signature = false;
+            })
+            .or_insert(Label {
+                name,
+                kind: LabelKind::Location,
+                signature: false,
+            });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prg::{
+        data::{mmc1_prg, nrom_prg},
+        regions::{Region, RegionKind},
+    };
+
+    fn prg_data(code: &[u8]) -> PrgData {
+        PrgData::parse(nrom_prg(code)).unwrap()
+    }
+
+    fn location(bank: Option<usize>, address: u16) -> Location {
+        Location { bank, address }
+    }
+
+    #[test]
+    fn generate_labels_for_references() {
+        // JSR $C009; LDA $0300; BNE $C003; RTS; RTS
+        let prg = prg_data(&[0x20, 0x09, 0xC0, 0xAD, 0x00, 0x03, 0xD0, 0xFB, 0x60, 0x60]);
+        let labels = &prg.labels;
+        assert_eq!(labels.name(&location(Some(0), 0xC000)), Some("sub_C000"));
+        assert_eq!(labels.name(&location(Some(0), 0xC003)), Some("loc_C003"));
+        assert_eq!(labels.name(&location(Some(0), 0xC009)), Some("sub_C009"));
+        assert_eq!(labels.name(&location(None, 0x0300)), Some("data_0300"));
+        assert_eq!(labels.name(&location(Some(0), 0xC006)), None);
+    }
+
+    #[test]
+    fn generate_labels_for_registers() {
+        // LDA $2002; STA $2008; STA $8000; RTS
+        let prg = prg_data(&[0xAD, 0x02, 0x20, 0x8D, 0x08, 0x20, 0x8D, 0x00, 0x80, 0x60]);
+        let labels = &prg.labels;
+        assert_eq!(labels.name(&location(None, 0x2002)), Some("PPUSTATUS"));
+        assert_eq!(labels.name(&location(None, 0x2008)), Some("data_2008"));
+        assert_eq!(labels.name(&location(None, 0x8000)), None);
+    }
+
+    #[test]
+    fn generated_label_kind_priority() {
+        // BNE $C005; JSR $C005; RTS
+        let prg = prg_data(&[0xD0, 0x03, 0x20, 0x05, 0xC0, 0x60]);
+        let label = prg.labels.get(&location(Some(0), 0xC005)).unwrap();
+        assert_eq!(label.name, "sub_C005");
+        assert_eq!(label.kind, LabelKind::Subroutine);
+    }
+
+    #[test]
+    fn generate_labels_in_switchable_banks() {
+        // LDA $8010; JMP $8000 in banks 0 and 1, LDA $8010; RTS in the fixed bank
+        let switchable: &[u8] = &[0xAD, 0x10, 0x80, 0x4C, 0x00, 0x80];
+        let fixed: &[u8] = &[0xAD, 0x10, 0x80, 0x60];
+        let mut prg = PrgData::parse(mmc1_prg([switchable, switchable, &[], fixed])).unwrap();
+        prg.import_regions([0, 1].map(|bank| {
+            let region = Region {
+                kind: RegionKind::Code,
+                length: 1,
+            };
+            (location(Some(bank), 0x8000), region)
+        }));
+        let labels = &prg.labels;
+        assert_eq!(labels.name(&location(Some(0), 0x8000)), Some("loc_00_8000"));
+        assert_eq!(labels.name(&location(Some(1), 0x8000)), Some("loc_01_8000"));
+        assert_eq!(
+            labels.name(&location(Some(1), 0x8010)),
+            Some("data_01_8010")
+        );
+        assert_eq!(labels.name(&location(None, 0x8010)), Some("data_8010"));
+        assert_eq!(labels.name(&location(Some(3), 0xC000)), Some("sub_C000"));
+        assert_eq!(labels.renamed().count(), 0);
+    }
+
+    #[test]
+    fn rename_label() {
+        let mut labels = prg_data(&[0x60]).labels;
+        let reset = location(Some(0), 0xC000);
+        assert_eq!(labels.rename(reset, "reset".to_string()), Ok(()));
+        assert_eq!(labels.name(&reset), Some("reset"));
+        assert_eq!(
+            labels.rename(location(None, 0x0300), "reset".to_string()),
+            Err(InvalidLabelError::Duplicate("reset".to_string()))
+        );
+        assert_eq!(
+            labels.rename(reset, "1st".to_string()),
+            Err(InvalidLabelError::InvalidName("1st".to_string()))
+        );
+        for reserved in ["a", "X", "lda", "SLO"] {
+            assert_eq!(
+                labels.rename(reset, reserved.to_string()),
+                Err(InvalidLabelError::Reserved(reserved.to_string()))
+            );
+        }
+        assert_eq!(labels.rename(reset, "ldax".to_string()), Ok(()));
+    }
+}
@@ -0,0 +1,480 @@
+use std::fmt;
+
+use super::{
+    data::{Location, PrgData},
+    instructions::Instruction,
+    regions::RegionKind,
+    structs::{FieldType, StructLayout},
+    tables::Pointer,
+    values::Inferred,
+};
+
+const DATA_BYTES_PER_ROW: usize = 8;
+const TEXT_BYTES_PER_ROW: usize = 32;
+
+/// Value of a field of a struct record.
+#[derive(PartialEq, Debug, Clone)]
+pub enum FieldValue {
+    Byte(u8),
+    Word(u16),
+    Pointer {
+        value: u16,
+        target_label: Option<String>,
+    },
+    Enum {
+        value: u8,
+        name: Option<String>,
+    },
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Byte(value) => write!(f, "${:02X}", value),
+            FieldValue::Word(value) => write!(f, "${:04X}", value),
+            FieldValue::Pointer {
+                target_label: Some(label),
+                ..
+            } => write!(f, "{}", label),
+            FieldValue::Pointer { value, .. } => write!(f, "${:04X}", value),
+            FieldValue::Enum {
+                name: Some(name), ..
+            } => write!(f, "{}", name),
+            FieldValue::Enum { value, .. } => write!(f, "${:02X}", value),
+        }
+    }
+}
+
+impl FieldValue {
+    pub fn size(&self) -> usize {
+        match self {
+            FieldValue::Byte(_) | FieldValue::Enum { .. } => 1,
+            FieldValue::Word(_) | FieldValue::Pointer { .. } => 2,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum ListingRow {
+    Segment {
+        name: String,
+    },
+    Label {
+        location: Location,
+        name: String,
+    },
+    Instruction {
+        address: u16,
+        instruction: Instruction,
+        operand_location: Option<Location>,
+        operand_label: Option<String>,
+        /// Value stored or jump target resolved by following register values.
+        inferred: Option<Inferred>,
+        comment: Option<String>,
+    },
+    Pointer {
+        address: u16,
+        pointer: Pointer,
+        target_label: Option<String>,
+        comment: Option<String>,
+    },
+    Word {
+        address: u16,
+        value: u16,
+        comment: Option<String>,
+    },
+    Text {
+        address: u16,
+        bytes: Vec<u8>,
+        comment: Option<String>,
+    },
+    Record {
+        address: u16,
+        layout: StructLayout,
+        index: usize,
+        bytes: Vec<u8>,
+        values: Vec<FieldValue>,
+        comment: Option<String>,
+    },
+    Data {
+        address: u16,
+        bytes: Vec<u8>,
+        comment: Option<String>,
+    },
+}
+
+impl ListingRow {
+    /// Address and length of the bytes listed by the row.
+    pub fn span(&self) -> Option<(u16, usize)> {
+        match self {
+            ListingRow::Segment { .. } | ListingRow::Label { .. } => None,
+            ListingRow::Instruction {
+                address,
+                instruction,
+                ..
+            } => Some((*address, instruction.len())),
+            ListingRow::Pointer {
+                address, pointer, ..
+            } => Some((*address, pointer.part.len())),
+            ListingRow::Word { address, .. } => Some((*address, 2)),
+            ListingRow::Text { address, bytes, .. }
+            | ListingRow::Record { address, bytes, .. }
+            | ListingRow::Data { address, bytes, .. } => Some((*address, bytes.len())),
+        }
+    }
+}
+
+/// Whether anything is attached to `location` that needs a row of its own.
+fn starts_row(prg_data: &PrgData, location: &Location) -> bool {
+    prg_data.labels.get(location).is_some()
+        || prg_data.comments.contains_key(location)
+        || prg_data.segments.contains_key(location)
+        || prg_data.pointers.contains_key(location)
+        || prg_data.regions.contains_key(location)
+}
+
+/// Row of the struct record starting at `location`, if a struct table has one there.
+fn record_row(
+    prg_data: &PrgData,
+    location: Location,
+    comment: Option<&String>,
+) -> Option<ListingRow> {
+    let (start, table) = prg_data.struct_table_at(location)?;
+    let size = table.layout.size();
+    let offset = (location.address - start.address) as usize;
+    if !offset.is_multiple_of(size) {
+        return None;
+    }
+    let bank_number = location.bank?;
+    let bank = &prg_data.banks[bank_number];
+    let values = table
+        .layout
+        .offsets()
+        .map(|(field_offset, field)| {
+            let address = location.address.checked_add(field_offset as u16)?;
+            Some(match &field.field_type {
+                FieldType::Byte => FieldValue::Byte(bank.byte_at(address)?),
+                FieldType::Word => FieldValue::Word(bank.word_at(address)?),
+                FieldType::Pointer => {
+                    let value = bank.word_at(address)?;
+                    let target = prg_data.resolve(bank_number, value);
+                    FieldValue::Pointer {
+                        value,
+                        target_label: prg_data.labels.name(&target).map(str::to_string),
+                    }
+                }
+                FieldType::Enum(names) => {
+                    let value = bank.byte_at(address)?;
+                    FieldValue::Enum {
+                        value,
+                        name: names.get(value as usize).cloned(),
+                    }
+                }
+            })
+        })
+        .collect::<Option<Vec<FieldValue>>>()?;
+    let start_offset = (location.address - bank.base_address) as usize;
+    Some(ListingRow::Record {
+        address: location.address,
+        layout: table.layout.clone(),
+        index: offset / size,
+        bytes: bank.bytes[start_offset..start_offset + size].to_vec(),
+        values,
+        comment: comment.cloned(),
+    })
+}
+
+/// Lays out the contents of a PRG bank. Bytes that weren't disassembled are listed as data
+/// unless they're marked as words, text or struct records, runs of data are broken up on labels, comments
+/// and regions so that each gets its own row.
+pub fn bank_listing(prg_data: &PrgData, bank_number: usize) -> Vec<ListingRow> {
+    let bank = &prg_data.banks[bank_number];
+    let mut rows = Vec::new();
+    let mut offset = 0usize;
+    while offset < bank.bytes.len() {
+        let address = bank.base_address + offset as u16;
+        let location = Location {
+            bank: Some(bank_number),
+            address,
+        };
+        if let Some(name) = prg_data.segments.get(&location) {
+            rows.push(ListingRow::Segment { name: name.clone() });
+        }
+        if let Some(name) = prg_data.labels.name(&location) {
+            rows.push(ListingRow::Label {
+                location,
+                name: name.to_string(),
+            });
+        }
+
+        let comment = prg_data.comments.get(&location);
+        if let Some(row) = record_row(prg_data, location, comment) {
+            offset += row.span().map_or(1, |(_, length)| length);
+            rows.push(row);
+            continue;
+        }
+        if let Some(instruction) = bank.instructions.get(&address) {
+            let operand_address = instruction.operand.and_then(|operand| operand.address());
+            let operand_location =
+                operand_address.map(|target| prg_data.resolve(bank_number, target));
+            let operand_label =
+                operand_address.and_then(|target| prg_data.operand_label(bank_number, target));
+            rows.push(ListingRow::Instruction {
+                address,
+                instruction: instruction.clone(),
+                operand_location,
+                operand_label,
+                inferred: prg_data.inferred.get(&location).copied(),
+                comment: comment.cloned(),
+            });
+            offset += instruction.len();
+            continue;
+        }
+        if let Some(pointer) = prg_data.pointers.get(&location) {
+            rows.push(ListingRow::Pointer {
+                address,
+                pointer: *pointer,
+                target_label: prg_data.labels.name(&pointer.target).map(str::to_string),
+                comment: comment.cloned(),
+            });
+            offset += pointer.part.len();
+            continue;
+        }
+
+        let region = prg_data.region_at(location);
+        let region_end = region.map(|(start, region)| start.address as usize + region.length);
+        let region_kind = region.map(|(_, region)| region.kind);
+        if region_kind == Some(RegionKind::Word)
+            && region_end.is_some_and(|end| address as usize + 2 <= end)
+        {
+            if let Some(value) = bank.word_at(address) {
+                rows.push(ListingRow::Word {
+                    address,
+                    value,
+                    comment: comment.cloned(),
+                });
+                offset += 2;
+                continue;
+            }
+        }
+
+        // With a table, strings get a row each
+        let max_length = match region_kind {
+            Some(RegionKind::Text) => prg_data
+                .text_table
+                .as_ref()
+                .zip(region_end)
+                .and_then(|(table, end)| {
+                    let end = (end - bank.base_address as usize).min(bank.bytes.len());
+                    table.string_length(&bank.bytes[offset..end])
+                })
+                .unwrap_or(TEXT_BYTES_PER_ROW),
+            _ => DATA_BYTES_PER_ROW,
+        };
+        let mut length = 1;
+        while length < max_length && offset + length < bank.bytes.len() {
+            let next_address = address + length as u16;
+            let next_location = Location {
+                bank: Some(bank_number),
+                address: next_address,
+            };
+            if bank.instructions.contains_key(&next_address)
+                || starts_row(prg_data, &next_location)
+                || region_end == Some(next_address as usize)
+            {
+                break;
+            }
+            length += 1;
+        }
+        let bytes = bank.bytes[offset..offset + length].to_vec();
+        let comment = comment.cloned();
+        rows.push(match region_kind {
+            Some(RegionKind::Text) => ListingRow::Text {
+                address,
+                bytes,
+                comment,
+            },
+            _ => ListingRow::Data {
+                address,
+                bytes,
+                comment,
+            },
+        });
+        offset += length;
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prg::{data::nrom_prg, structs::StructTable, text_table::TextTable};
+
+    #[test]
+    fn listing_of_code_and_data() {
+        // JMP $C00D; .byte $FF, $FF, $00 ...; BRK
+        let prg_data = PrgData::parse(nrom_prg(&[0x4C, 0x0D, 0xC0, 0xFF, 0xFF])).unwrap();
+        let rows = bank_listing(&prg_data, 0);
+
+        assert_eq!(
+            rows[0],
+            ListingRow::Label {
+                location: Location {
+                    bank: Some(0),
+                    address: 0xC000
+                },
+                name: "sub_C000".to_string()
+            }
+        );
+        assert!(matches!(
+            &rows[1],
+            ListingRow::Instruction { address: 0xC000, operand_label: Some(label), .. }
+                if label == "loc_C00D"
+        ));
+        assert_eq!(
+            rows[2],
+            ListingRow::Data {
+                address: 0xC003,
+                bytes: vec![0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+                comment: None
+            }
+        );
+        assert_eq!(
+            rows[3],
+            ListingRow::Data {
+                address: 0xC00B,
+                bytes: vec![0x00, 0x00],
+                comment: None
+            }
+        );
+        assert!(matches!(&rows[4], ListingRow::Label { name, .. } if name == "loc_C00D"));
+        assert!(matches!(
+            &rows[5],
+            ListingRow::Instruction {
+                address: 0xC00D,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn listing_of_regions() {
+        // RTS; .word $1234; "HI"; .byte $00
+        let prg = nrom_prg(&[0x60, 0x34, 0x12, b'H', b'I', 0x00]);
+        let mut prg_data = PrgData::parse(prg).unwrap();
+        let location = |address| Location {
+            bank: Some(0),
+            address,
+        };
+        prg_data.set_region(location(0xC001), 2, Some(RegionKind::Word));
+        prg_data.set_region(location(0xC003), 2, Some(RegionKind::Text));
+        let rows = bank_listing(&prg_data, 0);
+
+        assert_eq!(
+            rows[2],
+            ListingRow::Word {
+                address: 0xC001,
+                value: 0x1234,
+                comment: None
+            }
+        );
+        assert_eq!(
+            rows[3],
+            ListingRow::Text {
+                address: 0xC003,
+                bytes: b"HI".to_vec(),
+                comment: None
+            }
+        );
+        assert!(matches!(
+            &rows[4],
+            ListingRow::Data {
+                address: 0xC005,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn listing_of_strings() {
+        // RTS; "HA", <end>, "M", <end>
+        let prg = nrom_prg(&[0x60, 0x07, 0x00, 0xFF, 0x0C, 0xFF]);
+        let mut prg_data = PrgData::parse(prg).unwrap();
+        let location = Location {
+            bank: Some(0),
+            address: 0xC001,
+        };
+        prg_data.set_region(location, 5, Some(RegionKind::Text));
+        prg_data.text_table = Some(TextTable::parse("00=A\n07=H\n0C=M\n/FF").unwrap());
+        let rows = bank_listing(&prg_data, 0);
+
+        let strings = rows
+            .iter()
+            .filter_map(|row| match row {
+                ListingRow::Text { address, bytes, .. } => Some((*address, bytes.clone())),
+                _ => None,
+            })
+            .collect::<Vec<(u16, Vec<u8>)>>();
+        assert_eq!(
+            strings,
+            vec![(0xC001, vec![0x07, 0x00, 0xFF]), (0xC004, vec![0x0C, 0xFF])]
+        );
+    }
+
+    #[test]
+    fn listing_of_struct_tables() {
+        // RTS; { kind: 1, handler: $C000 }, { kind: 7, handler: $C000 }
+        let prg = nrom_prg(&[0x60, 0x01, 0x00, 0xC0, 0x07, 0x00, 0xC0]);
+        let mut prg_data = PrgData::parse(prg).unwrap();
+        let location = Location {
+            bank: Some(0),
+            address: 0xC001,
+        };
+        let table = StructTable::parse("Enemy kind:enum(none|walker) handler:ptr", 6).unwrap();
+        prg_data.set_struct_table(location, table);
+        let rows = bank_listing(&prg_data, 0);
+
+        let records = rows
+            .iter()
+            .filter_map(|row| match row {
+                ListingRow::Record { index, values, .. } => Some((*index, values.clone())),
+                _ => None,
+            })
+            .collect::<Vec<(usize, Vec<FieldValue>)>>();
+        let handler = FieldValue::Pointer {
+            value: 0xC000,
+            target_label: Some("sub_C000".to_string()),
+        };
+        assert_eq!(
+            records,
+            vec![
+                (
+                    0,
+                    vec![
+                        FieldValue::Enum {
+                            value: 1,
+                            name: Some("walker".to_string())
+                        },
+                        handler.clone()
+                    ]
+                ),
+                (
+                    1,
+                    vec![
+                        FieldValue::Enum {
+                            value: 7,
+                            name: None
+                        },
+                        handler
+                    ]
+                ),
+            ]
+        );
+        assert!(matches!(
+            rows.iter()
+                .find(|row| !matches!(row, ListingRow::Label { .. })
+                    && row.span().is_some_and(|(address, _)| address == 0xC007)),
+            Some(ListingRow::Data { .. })
+        ));
+    }
+}
@@ -1,360 +1,1073 @@
+pub mod assembler;
+pub mod cfg;
 pub mod data;
 pub mod instructions;
-
-use std::collections::{BTreeMap, HashMap};
+pub mod labels;
+pub mod listing;
+pub mod pseudocode;
+pub mod regions;
+pub mod registers;
+pub mod script;
+pub mod search;
+pub mod signatures;
+pub mod structs;
+pub mod symbols;
+pub mod tables;
+pub mod text_table;
+pub mod values;
+pub mod xrefs;
 
 use yew::prelude::*;
 
-use data::PrgData;
-use instructions::{Index, Instruction, Mnemonic, Operand};
+use super::ui::{
+    button::Button, download::download, input::TextInput, pagination::Pagination, select::Select,
+};
+use assembler::assemble;
+use cfg::{subroutine_containing, ControlFlowGraph};
+use data::{Location, PrgData};
+use instructions::{Cycles, Instruction};
+use listing::{bank_listing, FieldValue, ListingRow};
+use pseudocode::decompile;
+use regions::{text_operand, RegionKind};
+use registers::register;
+use search::{search, SearchMatch, SearchMode, SearchQuery};
+use structs::StructTable;
+use values::Inferred;
+
+const NOP_OPCODE: u8 = 0xEA;
 
 #[derive(Properties, PartialEq)]
 pub struct PrgProps {
     pub prg_data: Option<PrgData>,
+
+    #[prop_or(0)]
+    pub mapper: u32,
+
+    #[prop_or(Callback::from(|_: (Location, String)| {}))]
+    pub on_rename: Callback<(Location, String)>,
+
+    #[prop_or(Callback::from(|_: (Location, Vec<u8>)| {}))]
+    pub on_patch: Callback<(Location, Vec<u8>)>,
+
+    #[prop_or(Callback::from(|_: (Location, String)| {}))]
+    pub on_comment: Callback<(Location, String)>,
+
+    #[prop_or(Callback::from(|_: (Location, usize, Option<RegionKind>)| {}))]
+    pub on_set_region: Callback<(Location, usize, Option<RegionKind>)>,
+
+    #[prop_or(Callback::from(|_: (Location, StructTable)| {}))]
+    pub on_set_struct: Callback<(Location, StructTable)>,
+}
+
+fn bytes_to_hex_string(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|v| format!("{:02X}", v))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn cycles_cell(cycles: Cycles) -> Html {
+    html! {
+        <div class={classes!("table-cell", "text-neutral-content")}>
+            {cycles.base}
+            if cycles.branch_taken {
+                <span title="+1 cycle when the branch is taken">{"+t"}</span>
+            }
+            if cycles.page_cross {
+                <span class={classes!("text-warning")} title="+1 cycle when crossing a page">{"+p"}</span>
+            }
+        </div>
+    }
+}
+
+/// Sums up the cycles of the instructions between `start` and `end`, both included.
+fn range_cycles_panel(prg_data: &PrgData, start: Location, end: Location) -> Html {
+    let (first, last) = (start.min(end), start.max(end));
+    let (min, max) = first
+        .bank
+        .map(|bank| {
+            prg_data.banks[bank]
+                .instructions
+                .range(first.address..=last.address)
+                .map(|(address, instruction)| instruction.cycles(*address))
+                .fold((0, 0), |(min, max), cycles| {
+                    (min + cycles.min(), max + cycles.max())
+                })
+        })
+        .unwrap_or_default();
+    html! {
+        <div class={classes!("font-mono", "text-sm", "border", "border-base-300", "p-2")}>
+            {format!("{} to {}: {} to {} cycles", first, last, min, max)}
+        </div>
+    }
+}
+
+/// Bytes replacing `instruction`, padded with `NOP`s when they're shorter so that
+/// what follows still decodes the same.
+fn pad_with_nops(bytes: Vec<u8>, instruction: &Instruction) -> Vec<u8> {
+    let mut bytes = bytes;
+    bytes.resize(bytes.len().max(instruction.len()), NOP_OPCODE);
+    bytes
+}
+
+/// Instruction being patched, with what went wrong assembling the replacement.
+#[derive(Clone)]
+struct PatchState {
+    patching: UseStateHandle<Option<Location>>,
+    error: UseStateHandle<Option<String>>,
+    // Assembled bytes that overwrite the instructions after the one being replaced
+    oversized: UseStateHandle<Option<Vec<u8>>>,
+}
+
+impl PatchState {
+    fn start(&self, location: Location) {
+        self.error.set(None);
+        self.oversized.set(None);
+        self.patching.set(Some(location));
+    }
+}
+
+/// Text input replacing the mnemonic and operand cells of the instruction at `location`.
+/// Patches that don't fit in the space of the instruction wait for a confirmation.
+fn patch_editor(
+    location: Location,
+    instruction: &Instruction,
+    operand: String,
+    prg_data: &Option<PrgData>,
+    on_patch: &Callback<(Location, Vec<u8>)>,
+    state: &PatchState,
+) -> Html {
+    let PatchState {
+        patching,
+        error: patch_error,
+        oversized: oversized_patch,
+    } = state;
+    let cancel = {
+        let patching = patching.clone();
+        let oversized_patch = oversized_patch.clone();
+        Callback::from(move |_: MouseEvent| {
+            oversized_patch.set(None);
+            patching.set(None);
+        })
+    };
+    let on_change = {
+        let instruction = instruction.clone();
+        let prg_data = prg_data.clone();
+        let on_patch = on_patch.clone();
+        let patching = patching.clone();
+        let patch_error = patch_error.clone();
+        let oversized_patch = oversized_patch.clone();
+        Callback::from(move |source: String| {
+            let Some(prg_data) = prg_data.as_ref() else {
+                return;
+            };
+            let resolve = |name: &str| {
+                prg_data
+                    .labels
+                    .iter()
+                    .find(|(_, label)| label.name == name)
+                    .map(|(location, _)| location.address)
+            };
+            let bytes = assemble(&source, location.address, resolve)
+                .map_err(|error| error.to_string())
+                .and_then(|bytes| {
+                    let end = location.address as usize + bytes.len() - 1;
+                    let fits = location.bank.is_some_and(|bank| {
+                        end <= u16::MAX as usize && prg_data.banks[bank].contains(end as u16)
+                    });
+                    if fits {
+                        Ok(bytes)
+                    } else {
+                        Err(format!("{} runs past the end of the bank", source.trim()))
+                    }
+                });
+            match bytes {
+                Err(error) => {
+                    oversized_patch.set(None);
+                    patch_error.set(Some(error));
+                }
+                Ok(bytes) if bytes.len() > instruction.len() => {
+                    patch_error.set(None);
+                    oversized_patch.set(Some(bytes));
+                }
+                Ok(bytes) => {
+                    patch_error.set(None);
+                    patching.set(None);
+                    on_patch.emit((location, pad_with_nops(bytes, &instruction)));
+                }
+            }
+        })
+    };
+    let value = format!("{} {}", instruction.mnemonic, operand);
+    html! {
+        <div class={classes!("table-cell")}>
+            <div class={classes!("flex", "items-center", "gap-2")}>
+                <TextInput class={classes!("input-xs")} value={value.trim().to_string()} {on_change}/>
+                <Button class={classes!("btn-xs")} onclick={cancel}>{"Cancel"}</Button>
+            </div>
+            {
+                patch_error
+                    .as_ref()
+                    .map(|error| html! {
+                        <div class={classes!("text-error")}>{error.clone()}</div>
+                    })
+                    .unwrap_or_default()
+            }
+            {
+                oversized_patch
+                    .as_ref()
+                    .map(|bytes| {
+                        let confirm = {
+                            let bytes = bytes.clone();
+                            let on_patch = on_patch.clone();
+                            let patching = patching.clone();
+                            let oversized_patch = oversized_patch.clone();
+                            Callback::from(move |_: MouseEvent| {
+                                oversized_patch.set(None);
+                                patching.set(None);
+                                on_patch.emit((location, bytes.clone()));
+                            })
+                        };
+                        html! {
+                            <div class={classes!("text-warning", "flex", "items-center", "gap-2")}>
+                                {
+                                    format!(
+                                        "{} bytes, {} more than the instruction it replaces",
+                                        bytes.len(),
+                                        bytes.len() - instruction.len()
+                                    )
+                                }
+                                <Button class={classes!("btn-xs")} onclick={confirm}>{"Patch anyway"}</Button>
+                            </div>
+                        }
+                    })
+                    .unwrap_or_default()
+            }
+        </div>
+    }
+}
+
+/// Buttons marking the `length` bytes from `start` as code, data, words, pointers or text.
+fn region_panel(
+    prg_data: &PrgData,
+    start: Location,
+    length: usize,
+    on_set_region: Callback<(Location, usize, Option<RegionKind>)>,
+    on_set_struct: Callback<(Location, StructTable)>,
+    struct_error: &UseStateHandle<Option<String>>,
+) -> Html {
+    let current = prg_data.region_at(start).map(|(_, region)| region.kind);
+    let button = |kind: Option<RegionKind>| {
+        let onclick = {
+            let on_set_region = on_set_region.clone();
+            Callback::from(move |_: MouseEvent| on_set_region.emit((start, length, kind)))
+        };
+        let active = (kind.is_some() && kind == current).then_some("btn-active");
+        html! {
+            <Button class={classes!("btn-xs", "join-item", active)} {onclick}>
+                {kind.map_or("Auto".to_string(), |kind| kind.to_string())}
+            </Button>
+        }
+    };
+    let definition = prg_data
+        .structs
+        .get(&start)
+        .map(StructTable::to_string)
+        .unwrap_or_default();
+    let on_define_struct = {
+        let struct_error = struct_error.clone();
+        Callback::from(
+            move |definition: String| match StructTable::parse(&definition, length) {
+                Ok(table) => {
+                    struct_error.set(None);
+                    on_set_struct.emit((start, table));
+                }
+                Err(error) => struct_error.set(Some(error.to_string())),
+            },
+        )
+    };
+    html! {
+        <div class={classes!("font-mono", "text-sm", "border", "border-base-300", "p-2", "flex", "flex-col", "gap-2")}>
+            <div class={classes!("flex", "items-center", "gap-2")}>
+                <span class={classes!("grow")}>
+                    {format!("{} ({} bytes)", start, length)}
+                </span>
+                <div class={classes!("join")}>
+                    {RegionKind::ALL.iter().map(|kind| button(Some(*kind))).collect::<Html>()}
+                    {button(None)}
+                </div>
+            </div>
+            <TextInput
+                class={classes!("input-xs", "w-full")}
+                value={definition}
+                placeholder="Struct: Enemy[8] x:u8 hp:u16 ai:ptr kind:enum(walker|flyer)"
+                on_change={on_define_struct}/>
+            if let Some(error) = (**struct_error).clone() {
+                <div class={classes!("text-error")}>{error}</div>
+            }
+        </div>
+    }
+}
+
+fn row_id(address: u16) -> String {
+    format!("prg-{:04X}", address)
+}
+
+/// Lists the references to `location`, clicking one calls `on_navigate` with where it's from.
+fn xref_panel(prg_data: &PrgData, location: Location, on_navigate: Callback<Location>) -> Html {
+    let name = prg_data.labels.name(&location);
+    let references = prg_data.xrefs.get(&location);
+    html! {
+        <div class={classes!("font-mono", "text-sm", "border", "border-base-300", "p-2")}>
+            <div class={classes!("font-semibold", "mb-2")}>
+                {name.map_or(location.to_string(), |name| format!("{} ({})", name, location))}
+            </div>
+            if references.is_empty() {
+                <div class={classes!("text-neutral-content")}>{"No references"}</div>
+            } else {
+                <div class={classes!("table", "w-full")}>
+                {
+                    references
+                        .iter()
+                        .map(|reference| {
+                            let from = reference.from;
+                            let instruction = from
+                                .bank
+                                .and_then(|bank| prg_data.banks[bank].instructions.get(&from.address));
+                            let onclick = {
+                                let on_navigate = on_navigate.clone();
+                                Callback::from(move |_: MouseEvent| on_navigate.emit(from))
+                            };
+                            html! {
+                                <div class={classes!("table-row", "cursor-pointer", "hover:bg-base-200")} {onclick}>
+                                    <div class={classes!("table-cell", "text-secondary")}>{reference.kind.to_string()}</div>
+                                    <div class={classes!("table-cell")}>{from.to_string()}</div>
+                                    <div class={classes!("table-cell")}>
+                                    {
+                                        instruction.map_or(String::new(), |instruction| {
+                                            let operand = instruction
+                                                .operand
+                                                .map_or(String::new(), |v| v.to_string_with_label(name));
+                                            format!("{} {}", instruction.mnemonic, operand)
+                                        })
+                                    }
+                                    </div>
+                                </div>
+                            }
+                        })
+                        .collect::<Html>()
+                }
+                </div>
+            }
+        </div>
+    }
+}
+
+/// Search bar over every PRG bank, clicking a match calls `on_navigate`.
+fn search_panel(
+    prg_data: &PrgData,
+    mode: &UseStateHandle<SearchMode>,
+    results: &UseStateHandle<Option<Result<Vec<SearchMatch>, String>>>,
+    on_navigate: Callback<Location>,
+) -> Html {
+    let options = SearchMode::ALL
+        .iter()
+        .map(SearchMode::to_string)
+        .collect::<Vec<String>>();
+    let selected = SearchMode::ALL
+        .iter()
+        .position(|option| option == &**mode)
+        .unwrap_or_default();
+    let on_mode_change = {
+        let mode = mode.clone();
+        let results = results.clone();
+        Callback::from(move |index: usize| {
+            results.set(None);
+            mode.set(SearchMode::ALL[index]);
+        })
+    };
+    let on_search = {
+        let prg_data = prg_data.clone();
+        let mode = **mode;
+        let results = results.clone();
+        Callback::from(move |text: String| {
+            let found = SearchQuery::parse(mode, &text, prg_data.text_table.as_ref())
+                .map(|query| search(&prg_data, &query))
+                .map_err(|e| e.to_string());
+            results.set(Some(found));
+        })
+    };
+
+    html! {
+        <div class={classes!("p-2")}>
+            <div class={classes!("join")}>
+                <Select class={classes!("select-sm", "join-item")} {options} {selected} on_change={on_mode_change}/>
+                <TextInput class={classes!("input-sm", "join-item", "font-mono")} placeholder={mode.placeholder()} on_change={on_search}/>
+            </div>
+            {
+                match &**results {
+                    None => html! {},
+                    Some(Err(error)) => html! {
+                        <div class={classes!("text-error", "text-sm")}>{error}</div>
+                    },
+                    Some(Ok(matches)) => html! {
+                        <div class={classes!("font-mono", "text-sm", "max-h-48", "overflow-auto")}>
+                            <div>{format!("{} matches", matches.len())}</div>
+                            {
+                                matches
+                                    .iter()
+                                    .map(|found| {
+                                        let location = found.location;
+                                        let bytes = location.bank.map_or(Vec::new(), |bank| {
+                                            let bank = &prg_data.banks[bank];
+                                            let offset = (location.address - bank.base_address) as usize;
+                                            bank.bytes[offset..offset + found.length].to_vec()
+                                        });
+                                        let onclick = {
+                                            let on_navigate = on_navigate.clone();
+                                            Callback::from(move |_: MouseEvent| on_navigate.emit(location))
+                                        };
+                                        html! {
+                                            <div class={classes!("cursor-pointer", "hover:bg-base-200")} {onclick}>
+                                                <span class={classes!("text-accent")}>{location.to_string()}</span>
+                                                {format!(" {}", bytes_to_hex_string(&bytes))}
+                                            </div>
+                                        }
+                                    })
+                                    .collect::<Html>()
+                            }
+                        </div>
+                    },
+                }
+            }
+        </div>
+    }
+}
+
+/// Control-flow graph of the subroutine starting at `entry`, clicking a block calls `on_navigate`.
+fn cfg_panel(prg_data: &PrgData, entry: Location, on_navigate: Callback<Location>) -> Html {
+    let cfg = ControlFlowGraph::build(prg_data, entry);
+    let layout = cfg.layout(prg_data);
+    let name = prg_data
+        .labels
+        .name(&entry)
+        .map_or(entry.to_string(), str::to_string);
+
+    let on_export_dot = {
+        let dot = cfg.to_dot(prg_data);
+        let file_name = format!("{}.dot", name);
+        Callback::from(move |_: MouseEvent| {
+            download(&file_name, "text/vnd.graphviz", dot.as_bytes())
+        })
+    };
+    let on_export_svg = {
+        let svg = layout.to_svg();
+        let file_name = format!("{}.svg", name);
+        Callback::from(move |_: MouseEvent| download(&file_name, "image/svg+xml", svg.as_bytes()))
+    };
+
+    html! {
+        <div class={classes!("border", "border-base-300", "p-2")}>
+            <div class={classes!("flex", "items-center", "gap-2", "mb-2")}>
+                <span class={classes!("font-mono", "font-semibold", "grow")}>{name.clone()}</span>
+                <Button class={classes!("btn-xs")} onclick={on_export_dot}>{"DOT"}</Button>
+                <Button class={classes!("btn-xs")} onclick={on_export_svg}>{"SVG"}</Button>
+            </div>
+            <div class={classes!("overflow-auto")}>
+                <svg width={layout.width.to_string()} height={layout.height.to_string()} font-family="monospace" font-size="12">
+                    <defs>
+                        <marker id="cfg-arrow" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="6" markerHeight="6" orient="auto-start-reverse">
+                            <path d="M 0 0 L 10 5 L 0 10 z" class={classes!("fill-base-content")}/>
+                        </marker>
+                    </defs>
+                    {
+                        layout
+                            .edges
+                            .iter()
+                            .map(|edge| html! {
+                                <polyline points={edge.svg_points()} fill="none" stroke={edge.kind.color()} marker-end="url(#cfg-arrow)"/>
+                            })
+                            .collect::<Html>()
+                    }
+                    {
+                        layout
+                            .nodes
+                            .iter()
+                            .map(|node| {
+                                let onclick = {
+                                    let on_navigate = on_navigate.clone();
+                                    let location = node.location;
+                                    Callback::from(move |_: MouseEvent| on_navigate.emit(location))
+                                };
+                                html! {
+                                    <g class={classes!("cursor-pointer")} {onclick}>
+                                        <rect
+                                            x={node.x.to_string()}
+                                            y={node.y.to_string()}
+                                            width={node.width.to_string()}
+                                            height={node.height.to_string()}
+                                            class={classes!("fill-base-100", "stroke-base-content", "hover:fill-base-200")}/>
+                                        {
+                                            node.line_positions()
+                                                .map(|(y, line)| html! {
+                                                    <text x={node.text_x().to_string()} y={y.to_string()} class={classes!("fill-base-content", "whitespace-pre")}>
+                                                        {line.clone()}
+                                                    </text>
+                                                })
+                                                .collect::<Html>()
+                                        }
+                                    </g>
+                                }
+                            })
+                            .collect::<Html>()
+                    }
+                </svg>
+            </div>
+        </div>
+    }
+}
+
+/// Pseudocode of the subroutine starting at `entry`, clicking a line calls `on_navigate`
+/// with the instruction it comes from.
+fn pseudocode_panel(
+    prg_data: &PrgData,
+    entry: Location,
+    selected: Location,
+    on_navigate: Callback<Location>,
+) -> Html {
+    html! {
+        <div class={classes!("border", "border-base-300", "p-2", "font-mono", "text-sm")}>
+            {
+                decompile(prg_data, entry)
+                    .into_iter()
+                    .map(|line| {
+                        let text = format!("{}{}", "    ".repeat(line.indent), line.text);
+                        match line.location {
+                            Some(location) => {
+                                let onclick = {
+                                    let on_navigate = on_navigate.clone();
+                                    Callback::from(move |_: MouseEvent| on_navigate.emit(location))
+                                };
+                                let highlight = (location == selected).then_some("bg-base-300");
+                                html! {
+                                    <div class={classes!("whitespace-pre", "cursor-pointer", "hover:bg-base-200", highlight)} {onclick}>
+                                        {text}
+                                    </div>
+                                }
+                            }
+                            None => html! { <div class={classes!("whitespace-pre")}>{text}</div> },
+                        }
+                    })
+                    .collect::<Html>()
+            }
+        </div>
+    }
 }
 
 #[function_component(Prg)]
-pub fn prg(_props: &PrgProps) -> Html {
-    //let prg_data_clone = props.prg_data.clone();
-
-    use Index::*;
-    use Mnemonic::*;
-    use Operand::*;
-    let prg_data_clone = Some(PrgData {
-        instructions: HashMap::from([
-            (
-                0x0000,
-                Instruction {
-                    mnemonic: Ora,
-                    operand: Some(ZeroPage { address: 0x0B }),
-                    bytes: vec![0x05, 0x0B],
-                },
-            ),
-            (
-                0x0002,
-                Instruction {
-                    mnemonic: Jmp,
-                    operand: Some(Indirect { address: 0x0201 }),
-                    bytes: vec![0x6C, 0x01, 0x02],
-                },
-            ),
-            (
-                0x0005,
-                Instruction {
-                    mnemonic: Asl,
-                    operand: None,
-                    bytes: vec![0x0A],
-                },
-            ),
-            (
-                0x0006,
-                Instruction {
-                    mnemonic: Ldx,
-                    operand: Some(Immediate { value: 0xFF }),
-                    bytes: vec![0xA2, 0xFF],
-                },
-            ),
-            (
-                0x0008,
-                Instruction {
-                    mnemonic: Jsr,
-                    operand: Some(Absolute { address: 0xFD02 }),
-                    bytes: vec![0x20, 0x02, 0xFD],
-                },
-            ),
-            (
-                0x000B,
-                Instruction {
-                    mnemonic: Sei,
-                    operand: None,
-                    bytes: vec![0x78],
-                },
-            ),
-            (
-                0x000C,
-                Instruction {
-                    mnemonic: Bne,
-                    operand: Some(Absolute { address: 0x000A }),
-                    bytes: vec![0xD0, 0xFC],
-                },
-            ),
-            (
-                0x000E,
-                Instruction {
-                    mnemonic: Ora,
-                    operand: Some(AbsoluteIndexed {
-                        address: 0x1E05,
-                        index: X,
-                    }),
-                    bytes: vec![0x1D, 0x05, 0x1E],
-                },
-            ),
-            (
-                0x0011,
-                Instruction {
-                    mnemonic: Nop,
-                    operand: Some(ZeroPage { address: 0x15 }),
-                    bytes: vec![0x04, 0x15],
-                },
-            ),
-            (
-                0x0013,
-                Instruction {
-                    mnemonic: Stp,
-                    operand: None,
-                    bytes: vec![0x02],
-                },
-            ),
-            (
-                0x0014,
-                Instruction {
-                    mnemonic: Stx,
-                    operand: Some(ZeroPageIndexed {
-                        address: 0xAB,
-                        index: Y,
-                    }),
-                    bytes: vec![0x96, 0xAB],
-                },
-            ),
-            (
-                0x0016,
-                Instruction {
-                    mnemonic: Cli,
-                    operand: None,
-                    bytes: vec![0x58],
-                },
-            ),
-            (
-                0x0017,
-                Instruction {
-                    mnemonic: Adc,
-                    operand: Some(PreIndexedIndirect { address: 0x01 }),
-                    bytes: vec![0x61, 0x01],
-                },
-            ),
-            (
-                0x0019,
-                Instruction {
-                    mnemonic: Sta,
-                    operand: Some(PostIndexedIndirect { address: 0xFB }),
-                    bytes: vec![0x91, 0xFB],
-                },
-            ),
-            (
-                0x001C,
-                Instruction {
-                    mnemonic: Ora,
-                    operand: Some(ZeroPage { address: 0x0B }),
-                    bytes: vec![0x05, 0x0B],
-                },
-            ),
-            (
-                0x001E,
-                Instruction {
-                    mnemonic: Jmp,
-                    operand: Some(Indirect { address: 0x0201 }),
-                    bytes: vec![0x6C, 0x01, 0x02],
-                },
-            ),
-            (
-                0x0022,
-                Instruction {
-                    mnemonic: Asl,
-                    operand: None,
-                    bytes: vec![0x0A],
-                },
-            ),
-            (
-                0x0023,
-                Instruction {
-                    mnemonic: Ldx,
-                    operand: Some(Immediate { value: 0xFF }),
-                    bytes: vec![0xA2, 0xFF],
-                },
-            ),
-            (
-                0x0025,
-                Instruction {
-                    mnemonic: Jsr,
-                    operand: Some(Absolute { address: 0xFD02 }),
-                    bytes: vec![0x20, 0x02, 0xFD],
-                },
-            ),
-            (
-                0x0028,
-                Instruction {
-                    mnemonic: Sei,
-                    operand: None,
-                    bytes: vec![0x78],
-                },
-            ),
-            (
-                0x0029,
-                Instruction {
-                    mnemonic: Bne,
-                    operand: Some(Absolute { address: 0x000A }),
-                    bytes: vec![0xD0, 0xFC],
-                },
-            ),
-            (
-                0x002A,
-                Instruction {
-                    mnemonic: Ora,
-                    operand: Some(AbsoluteIndexed {
-                        address: 0x1E05,
-                        index: X,
-                    }),
-                    bytes: vec![0x1D, 0x05, 0x1E],
-                },
-            ),
-            (
-                0x002D,
-                Instruction {
-                    mnemonic: Nop,
-                    operand: Some(ZeroPage { address: 0x15 }),
-                    bytes: vec![0x04, 0x15],
-                },
-            ),
-            (
-                0x0030,
-                Instruction {
-                    mnemonic: Stp,
-                    operand: None,
-                    bytes: vec![0x02],
-                },
-            ),
-            (
-                0x0031,
-                Instruction {
-                    mnemonic: Stx,
-                    operand: Some(ZeroPageIndexed {
-                        address: 0xAB,
-                        index: Y,
-                    }),
-                    bytes: vec![0x96, 0xAB],
-                },
-            ),
-            (
-                0x0033,
-                Instruction {
-                    mnemonic: Cli,
-                    operand: None,
-                    bytes: vec![0x58],
-                },
-            ),
-            (
-                0x0034,
-                Instruction {
-                    mnemonic: Adc,
-                    operand: Some(PreIndexedIndirect { address: 0x01 }),
-                    bytes: vec![0x61, 0x01],
-                },
-            ),
-            (
-                0x0036,
-                Instruction {
-                    mnemonic: Sta,
-                    operand: Some(PostIndexedIndirect { address: 0xFB }),
-                    bytes: vec![0x91, 0xFB],
-                },
-            ),
-            (
-                0x0038,
-                Instruction {
-                    mnemonic: Sta,
-                    operand: Some(PostIndexedIndirect { address: 0xFB }),
-                    bytes: vec![0x91, 0xFB],
-                },
-            ),
-            (
-                0x003A,
-                Instruction {
-                    mnemonic: Sta,
-                    operand: Some(PostIndexedIndirect { address: 0xFB }),
-                    bytes: vec![0x91, 0xFB],
-                },
-            ),
-            (
-                0x003C,
-                Instruction {
-                    mnemonic: Sta,
-                    operand: Some(PostIndexedIndirect { address: 0xFB }),
-                    bytes: vec![0x91, 0xFB],
-                },
-            ),
-            (
-                0x003E,
-                Instruction {
-                    mnemonic: Sta,
-                    operand: Some(PostIndexedIndirect { address: 0xFB }),
-                    bytes: vec![0x91, 0xFB],
-                },
-            ),
-            (
-                0x0040,
-                Instruction {
-                    mnemonic: Sta,
-                    operand: Some(PostIndexedIndirect { address: 0xFB }),
-                    bytes: vec![0x91, 0xFB],
-                },
-            ),
-            (
-                0x0042,
-                Instruction {
-                    mnemonic: Sta,
-                    operand: Some(PostIndexedIndirect { address: 0xFB }),
-                    bytes: vec![0x91, 0xFB],
-                },
-            ),
-            (
-                0x0044,
-                Instruction {
-                    mnemonic: Sta,
-                    operand: Some(PostIndexedIndirect { address: 0xFB }),
-                    bytes: vec![0x91, 0xFB],
-                },
-            ),
-            (
-                0x0046,
-                Instruction {
-                    mnemonic: Sta,
-                    operand: Some(PostIndexedIndirect { address: 0xFB }),
-                    bytes: vec![0x91, 0xFB],
-                },
-            ),
-        ]),
+pub fn prg(props: &PrgProps) -> Html {
+    let bank_count = props
+        .prg_data
+        .as_ref()
+        .map(|data| data.banks.len())
+        .unwrap_or(0usize);
+    let current_bank = use_state(|| 0usize);
+    let editing_label = use_state(|| None::<Location>);
+    let editing_comment = use_state(|| None::<Location>);
+    let selected = use_state(|| None::<Location>);
+    let range_end = use_state(|| None::<Location>);
+    let patch_state = PatchState {
+        patching: use_state(|| None::<Location>),
+        error: use_state(|| None::<String>),
+        oversized: use_state(|| None::<Vec<u8>>),
+    };
+    let scroll_target = use_state(|| None::<u16>);
+    let struct_error = use_state(|| None::<String>);
+    let search_mode = use_state(|| SearchMode::Bytes);
+    let search_results = use_state(|| None::<Result<Vec<SearchMatch>, String>>);
+
+    {
+        let scroll_target = scroll_target.clone();
+        use_effect_with((*scroll_target, *current_bank), move |(target, _)| {
+            let element = target.and_then(|address| {
+                web_sys::window()
+                    .unwrap()
+                    .document()
+                    .unwrap()
+                    .get_element_by_id(&row_id(address))
+            });
+            if let Some(element) = element {
+                element.scroll_into_view();
+            }
+        });
+    }
+
+    let change_callback = {
+        let current_bank = current_bank.clone();
+        let scroll_target = scroll_target.clone();
+        Callback::from(move |page: usize| {
+            scroll_target.set(None);
+            current_bank.set(page.min(bank_count.saturating_sub(1)));
+        })
+    };
+
+    let select = {
+        let selected = selected.clone();
+        let range_end = range_end.clone();
+        Callback::from(move |location: Location| {
+            range_end.set(None);
+            selected.set(Some(location));
+        })
+    };
+
+    let navigate = {
+        let current_bank = current_bank.clone();
+        let scroll_target = scroll_target.clone();
+        Callback::from(move |location: Location| {
+            if let Some(bank) = location.bank {
+                current_bank.set(bank);
+            }
+            scroll_target.set(Some(location.address));
+        })
+    };
+
+    let rows = props
+        .prg_data
+        .as_ref()
+        .filter(|data| *current_bank < data.banks.len())
+        .map(|data| bank_listing(data, *current_bank))
+        .unwrap_or_default();
+
+    let bank = *current_bank;
+    let address_cell = |address: u16| {
+        let location = Location {
+            bank: Some(bank),
+            address,
+        };
+        // Shift-click selects a range of instructions to sum the cycles of
+        let onclick = {
+            let select = select.clone();
+            let selected = selected.clone();
+            let range_end = range_end.clone();
+            Callback::from(move |event: MouseEvent| {
+                let same_bank = selected.is_some_and(|selected| selected.bank == location.bank);
+                if event.shift_key() && same_bank {
+                    range_end.set(Some(location));
+                } else {
+                    select.emit(location);
+                }
+            })
+        };
+        let in_range = selected
+            .zip(*range_end)
+            .is_some_and(|(start, end)| location >= start.min(end) && location <= start.max(end));
+        let highlight = (*selected == Some(location) || in_range).then_some("bg-base-300");
+        html! {
+            <div class={classes!("table-cell", "cursor-pointer", highlight)} {onclick}>
+                {format!("${:04X}:", address)}
+            </div>
+        }
+    };
+
+    let comment_cell = |address: u16, comment: Option<String>| {
+        let location = Location {
+            bank: Some(bank),
+            address,
+        };
+        if *editing_comment == Some(location) {
+            let on_change = {
+                let editing_comment = editing_comment.clone();
+                let on_comment = props.on_comment.clone();
+                Callback::from(move |comment: String| {
+                    editing_comment.set(None);
+                    on_comment.emit((location, comment));
+                })
+            };
+            return html! {
+                <div class={classes!("table-cell")}>
+                    <TextInput class={classes!("input-xs", "w-full")} value={comment.unwrap_or_default()} {on_change}/>
+                </div>
+            };
+        }
+        let onclick = {
+            let editing_comment = editing_comment.clone();
+            Callback::from(move |_: MouseEvent| editing_comment.set(Some(location)))
+        };
+        html! {
+            <div class={classes!("table-cell", "text-neutral-content", "whitespace-pre-line", "cursor-text")} title="Click to edit the comment" {onclick}>
+                {comment.map(|comment| format!("; {}", comment)).unwrap_or_default()}
+            </div>
+        }
+    };
+
+    // Where the selected rows start and how many bytes they span
+    let selection = selected.and_then(|start| {
+        let end = range_end.unwrap_or(start);
+        let (first, last) = (start.min(end), start.max(end));
+        let last_length = rows
+            .iter()
+            .filter_map(ListingRow::span)
+            .find(|(address, _)| *address == last.address)
+            .map_or(1, |(_, length)| length);
+        (first.bank == Some(bank))
+            .then(|| (first, (last.address - first.address) as usize + last_length))
     });
 
     html! {
         <div class={classes!("h-full", "box-border", "border", "border-base-300", )}>
-            <div class={classes!("table", "w-1/2")}>
+            <Pagination count={bank_count} page={Some(*current_bank)} on_change={change_callback}/>
             {
-                prg_data_clone
-                    .map_or(HashMap::new(), |prg_data| prg_data.instructions)
-                    .into_iter()
-                    .collect::<BTreeMap<u16, Instruction>>()
+                props
+                    .prg_data
+                    .as_ref()
+                    .map(|prg_data| {
+                        let on_navigate = {
+                            let navigate = navigate.clone();
+                            let select = select.clone();
+                            Callback::from(move |location: Location| {
+                                navigate.emit(location);
+                                select.emit(location);
+                            })
+                        };
+                        search_panel(prg_data, &search_mode, &search_results, on_navigate)
+                    })
+                    .unwrap_or_default()
+            }
+            {
+                props
+                    .prg_data
+                    .as_ref()
+                    .and_then(|data| data.coverage(*current_bank))
+                    .map(|coverage| html! {
+                        <div class={classes!("text-sm", "p-2")}>
+                            {
+                                format!(
+                                    "CDL coverage: {:.1}% code, {:.1}% data, {:.1}% unused",
+                                    coverage.percentage(coverage.code),
+                                    coverage.percentage(coverage.data),
+                                    coverage.percentage(coverage.total - coverage.code - coverage.data)
+                                )
+                            }
+                        </div>
+                    })
+                    .unwrap_or_default()
+            }
+            <div class={classes!("flex", "gap-4")}>
+            <div class={classes!("table", "w-1/2", "font-mono", "text-sm")}>
+            {
+                rows
                     .into_iter()
-                    .map(|(address, instruction)| {
-                        html! {
+                    .map(|row| match row {
+                        ListingRow::Segment { name } => html! {
                             <div class={classes!("table-row")}>
-                                <div class={classes!("table-cell")}>{format!("${:04X}:", address)}</div>
+                                <div class={classes!("table-cell", "text-secondary")}>
+                                    {format!(".segment \"{}\"", name)}
+                                </div>
+                            </div>
+                        },
+                        ListingRow::Label { location, name } => {
+                            let content = if *editing_label == Some(location) {
+                                let on_change = {
+                                    let editing_label = editing_label.clone();
+                                    let on_rename = props.on_rename.clone();
+                                    Callback::from(move |name: String| {
+                                        editing_label.set(None);
+                                        on_rename.emit((location, name));
+                                    })
+                                };
+                                html! {
+                                    <TextInput class={classes!("input-xs")} value={name} {on_change}/>
+                                }
+                            } else {
+                                let onclick = {
+                                    let editing_label = editing_label.clone();
+                                    Callback::from(move |_: MouseEvent| editing_label.set(Some(location)))
+                                };
+                                html! {
+                                    <span class={classes!("cursor-pointer", "text-primary")} {onclick}>
+                                        {format!("{}:", name)}
+                                    </span>
+                                }
+                            };
+                            html! {
+                                <div class={classes!("table-row")}>
+                                    <div class={classes!("table-cell")}>{content}</div>
+                                </div>
+                            }
+                        }
+                        ListingRow::Instruction {
+                            address,
+                            instruction,
+                            operand_location,
+                            operand_label,
+                            inferred,
+                            comment,
+                        } => {
+                            let register = instruction
+                                .operand
+                                .and_then(|operand| operand.address())
+                                .and_then(|target| {
+                                    register(props.mapper, target, instruction.writes_memory())
+                                });
+                            let operand_name = operand_label
+                                .as_deref()
+                                .or(register.map(|register| register.name));
+                            let operand = instruction.operand.map_or(String::new(), |v| {
+                                v.to_string_with_label(operand_name)
+                            });
+                            let tooltip = register.map(|register| {
+                                format!("{}: {}", register.name, register.description)
+                            });
+                            let operand_text = operand.clone();
+                            let operand = match operand_location {
+                                Some(location) => {
+                                    let onclick = {
+                                        let select = select.clone();
+                                        Callback::from(move |_: MouseEvent| select.emit(location))
+                                    };
+                                    html! {
+                                        <span class={classes!("cursor-pointer", "text-accent")} title={tooltip} {onclick}>
+                                            {operand}
+                                        </span>
+                                    }
+                                }
+                                None => html! { {operand} },
+                            };
+                            let is_mapper_write = register.is_some_and(|register| register.mapper);
+                            let inferred = match inferred {
+                                Some(Inferred::Value(value)) if is_mapper_write => {
+                                    html! { <span>{format!(" ; bank ${:02X}", value)}</span> }
+                                }
+                                Some(Inferred::Value(value)) => html! {
+                                    <span class={classes!("text-neutral-content")}>
+                                        {format!(" ; = ${:02X}", value)}
+                                    </span>
+                                },
+                                Some(Inferred::Target(target)) => {
+                                    let onclick = {
+                                        let select = select.clone();
+                                        Callback::from(move |_: MouseEvent| select.emit(target))
+                                    };
+                                    let name = props
+                                        .prg_data
+                                        .as_ref()
+                                        .and_then(|prg_data| prg_data.labels.name(&target))
+                                        .map_or(target.to_string(), str::to_string);
+                                    html! {
+                                        <span class={classes!("cursor-pointer", "text-accent")} {onclick}>
+                                            {format!(" ; -> {}", name)}
+                                        </span>
+                                    }
+                                }
+                                None => html! {},
+                            };
+                            let operand = html! { <>{operand}{inferred}</> };
+                            // Mapper writes are where banks get switched
+                            let mapper_write =
+                                is_mapper_write.then_some(["bg-warning", "text-warning-content"]);
+                            let location = Location {
+                                bank: Some(bank),
+                                address,
+                            };
+                            let code = if *patch_state.patching == Some(location) {
+                                patch_editor(
+                                    location,
+                                    &instruction,
+                                    operand_text,
+                                    &props.prg_data,
+                                    &props.on_patch,
+                                    &patch_state,
+                                )
+                            } else {
+                                let onclick = {
+                                    let patch_state = patch_state.clone();
+                                    Callback::from(move |_: MouseEvent| patch_state.start(location))
+                                };
+                                html! {
+                                    <>
+                                        <div class={classes!("table-cell")}>
+                                            <span class={classes!("cursor-pointer")} title="Click to patch" {onclick}>
+                                                {instruction.mnemonic.to_string()}
+                                            </span>
+                                        </div>
+                                        <div class={classes!("table-cell")}>{operand}</div>
+                                    </>
+                                }
+                            };
+                            html! {
+                                <div id={row_id(address)} class={classes!("table-row", mapper_write)}>
+                                    {address_cell(address)}
+                                    <div class={classes!("table-cell")}>{bytes_to_hex_string(&instruction.bytes)}</div>
+                                    {code}
+                                    {cycles_cell(instruction.cycles(address))}
+                                    {comment_cell(address, comment)}
+                                </div>
+                            }
+                        }
+                        ListingRow::Pointer { address, pointer, target_label, comment } => {
+                            let onclick = {
+                                let select = select.clone();
+                                Callback::from(move |_: MouseEvent| select.emit(pointer.target))
+                            };
+                            html! {
+                                <div id={row_id(address)} class={classes!("table-row")}>
+                                    {address_cell(address)}
+                                    <div class={classes!("table-cell")}>{bytes_to_hex_string(&pointer.bytes())}</div>
+                                    <div class={classes!("table-cell")}>{pointer.directive()}</div>
+                                    <div class={classes!("table-cell")}>
+                                        <span class={classes!("cursor-pointer", "text-accent")} {onclick}>
+                                            {pointer.to_string_with_label(target_label.as_deref())}
+                                        </span>
+                                    </div>
+                                    <div class={classes!("table-cell")}></div>
+                                    {comment_cell(address, comment)}
+                                </div>
+                            }
+                        }
+                        ListingRow::Word { address, value, comment } => html! {
+                            <div id={row_id(address)} class={classes!("table-row")}>
+                                {address_cell(address)}
+                                <div class={classes!("table-cell")}>{bytes_to_hex_string(&value.to_le_bytes())}</div>
+                                <div class={classes!("table-cell")}>{".word"}</div>
+                                <div class={classes!("table-cell")}>{format!("${:04X}", value)}</div>
+                                <div class={classes!("table-cell")}></div>
+                                {comment_cell(address, comment)}
+                            </div>
+                        },
+                        ListingRow::Text { address, bytes, comment } => html! {
+                            <div id={row_id(address)} class={classes!("table-row")}>
+                                {address_cell(address)}
+                                <div class={classes!("table-cell")}>{bytes_to_hex_string(&bytes)}</div>
+                                <div class={classes!("table-cell")}>{".byte"}</div>
+                                <div class={classes!("table-cell", "text-secondary", "whitespace-pre-wrap")}>
+                                {
+                                    props
+                                        .prg_data
+                                        .as_ref()
+                                        .and_then(|data| data.text_table.as_ref())
+                                        .map_or_else(
+                                            || text_operand(&bytes),
+                                            |table| format!("\"{}\"", table.decode(&bytes)),
+                                        )
+                                }
+                                </div>
+                                <div class={classes!("table-cell")}></div>
+                                {comment_cell(address, comment)}
+                            </div>
+                        },
+                        ListingRow::Record { address, layout, index, bytes, values, comment } => html! {
+                            <div id={row_id(address)} class={classes!("table-row")}>
+                                {address_cell(address)}
+                                <div class={classes!("table-cell")}>{bytes_to_hex_string(&bytes)}</div>
+                                <div class={classes!("table-cell", "text-secondary")}>{format!("{}[{}]", layout.name, index)}</div>
+                                <div class={classes!("table-cell")}>
+                                    <div class={classes!("grid", "grid-flow-col", "auto-cols-fr", "gap-2")}>
+                                    {
+                                        layout
+                                            .fields
+                                            .iter()
+                                            .zip(values.iter())
+                                            .map(|(field, value)| {
+                                                let value = match value {
+                                                    FieldValue::Pointer { value: target, .. } => {
+                                                        let target = props.prg_data.as_ref().map_or(
+                                                            Location { bank: None, address: *target },
+                                                            |data| data.resolve(bank, *target),
+                                                        );
+                                                        let onclick = {
+                                                            let select = select.clone();
+                                                            Callback::from(move |_: MouseEvent| select.emit(target))
+                                                        };
+                                                        html! {
+                                                            <span class={classes!("cursor-pointer", "text-accent")} {onclick}>
+                                                                {value.to_string()}
+                                                            </span>
+                                                        }
+                                                    }
+                                                    _ => html! { {value.to_string()} },
+                                                };
+                                                html! {
+                                                    <div class={classes!("flex", "flex-col")} title={field.name.clone()}>
+                                                        if index == 0 {
+                                                            <span class={classes!("text-neutral-content")}>{field.name.clone()}</span>
+                                                        }
+                                                        {value}
+                                                    </div>
+                                                }
+                                            })
+                                            .collect::<Html>()
+                                    }
+                                    </div>
+                                </div>
+                                <div class={classes!("table-cell")}></div>
+                                {comment_cell(address, comment)}
+                            </div>
+                        },
+                        ListingRow::Data { address, bytes, comment } => html! {
+                            <div id={row_id(address)} class={classes!("table-row")}>
+                                {address_cell(address)}
+                                <div class={classes!("table-cell")}>{bytes_to_hex_string(&bytes)}</div>
+                                <div class={classes!("table-cell")}>{".byte"}</div>
                                 <div class={classes!("table-cell")}>
                                 {
-                                    instruction.bytes
-                                        .into_iter()
-                                        .map(|v| format!("{:02X}", v))
+                                    bytes
+                                        .iter()
+                                        .map(|v| format!("${:02X}", v))
                                         .collect::<Vec<String>>()
-                                        .join(" ")
+                                        .join(", ")
                                 }
                                 </div>
-                                <div class={classes!("table-cell")}>{instruction.mnemonic.to_string()}</div>
-                                <div class={classes!("table-cell")}>{instruction.operand.map_or(String::new(), |v| v.to_string())}</div>
+                                <div class={classes!("table-cell")}></div>
+                                {comment_cell(address, comment)}
                             </div>
-                        }
+                        },
                     })
                     .collect::<Html>()
             }
             </div>
+            {
+                props
+                    .prg_data
+                    .as_ref()
+                    .zip(*selected)
+                    .map(|(prg_data, location)| html! {
+                        <div class={classes!("w-1/2", "sticky", "top-0", "self-start", "max-h-screen", "overflow-auto", "flex", "flex-col", "gap-4")}>
+                            {
+                                selection
+                                    .map(|(start, length)| {
+                                        region_panel(
+                                            prg_data,
+                                            start,
+                                            length,
+                                            props.on_set_region.clone(),
+                                            props.on_set_struct.clone(),
+                                            &struct_error,
+                                        )
+                                    })
+                                    .unwrap_or_default()
+                            }
+                            {
+                                range_end
+                                    .map(|end| range_cycles_panel(prg_data, location, end))
+                                    .unwrap_or_default()
+                            }
+                            {xref_panel(prg_data, location, navigate.clone())}
+                            {
+                                subroutine_containing(prg_data, location)
+                                    .map(|entry| html! {
+                                        <>
+                                            {cfg_panel(prg_data, entry, navigate.clone())}
+                                            {pseudocode_panel(prg_data, entry, location, navigate.clone())}
+                                        </>
+                                    })
+                                    .unwrap_or_default()
+                            }
+                        </div>
+                    })
+                    .unwrap_or_default()
+            }
+            </div>
         </div>
     }
 }
@@ -0,0 +1,767 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{
+    cfg::{ControlFlowGraph, EdgeKind},
+    data::{Location, PrgData},
+    instructions::{Instruction, Mnemonic, Operand},
+    values::Inferred,
+};
+
+/// Line of pseudocode, linked to the instruction its statement starts at.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct PseudoLine {
+    pub location: Option<Location>,
+    pub indent: usize,
+    pub text: String,
+}
+
+/// What the last instruction that set N and Z worked on, for branch conditions.
+#[derive(PartialEq, Eq, Debug, Clone)]
+enum Flags {
+    Unknown,
+    Value(String),
+    Compare(String, String),
+    Bit(String),
+}
+
+/// Flags at some point of a block. The carry is only followed when a comparison set it.
+#[derive(PartialEq, Eq, Debug, Clone)]
+struct FlagState {
+    flags: Flags,
+    carry: Option<(String, String)>,
+}
+
+impl FlagState {
+    fn unknown() -> FlagState {
+        FlagState {
+            flags: Flags::Unknown,
+            carry: None,
+        }
+    }
+
+    /// Conditions under which the branch is taken and not taken.
+    fn condition(&self, mnemonic: Mnemonic) -> (String, String) {
+        use Mnemonic::*;
+        let (set, clear) = match mnemonic {
+            Bcc | Bcs => match &self.carry {
+                Some((left, right)) => (
+                    format!("{} >= {}", left, right),
+                    format!("{} < {}", left, right),
+                ),
+                None => ("C".to_string(), "!C".to_string()),
+            },
+            Beq | Bne => match &self.flags {
+                Flags::Value(value) => (format!("{} == 0", value), format!("{} != 0", value)),
+                Flags::Compare(left, right) => (
+                    format!("{} == {}", left, right),
+                    format!("{} != {}", left, right),
+                ),
+                Flags::Bit(value) => (
+                    format!("(A & {}) == 0", value),
+                    format!("(A & {}) != 0", value),
+                ),
+                Flags::Unknown => ("Z".to_string(), "!Z".to_string()),
+            },
+            Bmi | Bpl => {
+                let sign = match &self.flags {
+                    Flags::Value(value) | Flags::Bit(value) => Some(value.clone()),
+                    Flags::Compare(left, right) => Some(format!("({} - {})", left, right)),
+                    Flags::Unknown => None,
+                };
+                match sign {
+                    Some(sign) => (
+                        format!("({} & $80) != 0", sign),
+                        format!("({} & $80) == 0", sign),
+                    ),
+                    None => ("N".to_string(), "!N".to_string()),
+                }
+            }
+            _ => match &self.flags {
+                Flags::Bit(value) => (
+                    format!("({} & $40) != 0", value),
+                    format!("({} & $40) == 0", value),
+                ),
+                _ => ("V".to_string(), "!V".to_string()),
+            },
+        };
+        match mnemonic {
+            Bcs | Beq | Bmi | Bvs => (set, clear),
+            _ => (clear, set),
+        }
+    }
+}
+
+/// Statement lifted from the instructions starting at `address`.
+struct Statement {
+    address: u16,
+    text: String,
+    /// Register a plain load sets, such loads right before a `JSR` become its arguments.
+    argument: Option<&'static str>,
+}
+
+enum Line {
+    Code(PseudoLine),
+    /// Where a label goes in case anything jumps there with a `goto`.
+    Label(Location, usize),
+}
+
+struct Decompiler<'a> {
+    prg_data: &'a PrgData,
+    bank: usize,
+    cfg: ControlFlowGraph,
+    /// Block starts in address order.
+    starts: Vec<Location>,
+    lines: Vec<Line>,
+    gotos: BTreeSet<Location>,
+    loop_headers: BTreeSet<usize>,
+    /// Branches and jumps already shown by the structure around them.
+    structured: BTreeSet<Location>,
+    flags: BTreeMap<Location, FlagState>,
+}
+
+fn register_name(mnemonic: Mnemonic) -> &'static str {
+    use Mnemonic::*;
+    match mnemonic {
+        Ldx | Stx | Cpx | Inx | Dex | Tax | Tsx => "X",
+        Ldy | Sty | Cpy | Iny | Dey | Tay => "Y",
+        _ => "A",
+    }
+}
+
+/// Whether `high` addresses the byte after `low`, or both are immediates.
+fn is_word_pair(low: Option<Operand>, high: Option<Operand>) -> bool {
+    match (low, high) {
+        (Some(Operand::Immediate { .. }), Some(Operand::Immediate { .. })) => true,
+        (Some(low), Some(high)) => {
+            matches!(
+                (low, high),
+                (
+                    Operand::ZeroPage { .. } | Operand::Absolute { .. },
+                    Operand::ZeroPage { .. } | Operand::Absolute { .. }
+                )
+            ) && low.address().map(|address| address.wrapping_add(1)) == high.address()
+        }
+        _ => false,
+    }
+}
+
+impl Decompiler<'_> {
+    fn location(&self, address: u16) -> Location {
+        Location {
+            bank: Some(self.bank),
+            address,
+        }
+    }
+
+    fn name(&self, location: Location) -> String {
+        self.prg_data
+            .labels
+            .name(&location)
+            .map_or(format!("${:04X}", location.address), str::to_string)
+    }
+
+    fn expression(&self, operand: Operand) -> String {
+        let name = |address: u16| {
+            self.prg_data
+                .operand_label(self.bank, address)
+                .unwrap_or_else(|| match address {
+                    0..=0xFF => format!("${:02X}", address),
+                    _ => format!("${:04X}", address),
+                })
+        };
+        match operand {
+            Operand::Immediate { value } => format!("${:02X}", value),
+            Operand::ZeroPage { address } => name(address as u16),
+            Operand::Absolute { address } => name(address),
+            Operand::ZeroPageIndexed { address, index } => {
+                format!("{}[{}]", name(address as u16), index)
+            }
+            Operand::AbsoluteIndexed { address, index } => format!("{}[{}]", name(address), index),
+            Operand::Indirect { address } => format!("*{}", name(address)),
+            Operand::PreIndexedIndirect { address } => format!("*{}[X]", name(address as u16)),
+            Operand::PostIndexedIndirect { address } => format!("(*{})[Y]", name(address as u16)),
+        }
+    }
+
+    fn word_expression(&self, low: Option<Operand>, high: Option<Operand>) -> String {
+        match (low, high) {
+            (Some(Operand::Immediate { value: low }), Some(Operand::Immediate { value: high })) => {
+                format!("${:04X}", u16::from_le_bytes([low, high]))
+            }
+            (Some(low), _) => format!("word({})", self.expression(low)),
+            _ => String::new(),
+        }
+    }
+
+    /// 16-bit addition, subtraction or comparison starting at `instructions[0]`, along with
+    /// how many instructions it spans.
+    fn word_idiom(
+        &self,
+        instructions: &[(u16, Instruction)],
+        flags: &mut FlagState,
+    ) -> Option<(usize, Option<String>)> {
+        use Mnemonic::*;
+        let mnemonics = instructions
+            .iter()
+            .map(|(_, instruction)| instruction.mnemonic)
+            .collect::<Vec<Mnemonic>>();
+        let operand = |index: usize| instructions[index].1.operand;
+        match mnemonics.as_slice() {
+            [setup @ (Clc | Sec), Lda, op @ (Adc | Sbc), Sta, Lda, second, Sta, ..]
+                if matches!((setup, op), (Clc, Adc) | (Sec, Sbc))
+                    && second == op
+                    && is_word_pair(operand(1), operand(4))
+                    && is_word_pair(operand(2), operand(5))
+                    && is_word_pair(operand(3), operand(6)) =>
+            {
+                let sign = if *op == Adc { "+" } else { "-" };
+                *flags = FlagState::unknown();
+                let text = format!(
+                    "{} = {} {} {};",
+                    self.word_expression(operand(3), operand(6)),
+                    self.word_expression(operand(1), operand(4)),
+                    sign,
+                    self.word_expression(operand(2), operand(5))
+                );
+                Some((7, Some(text)))
+            }
+            [Lda, Cmp, Lda, Sbc, ..]
+                if is_word_pair(operand(0), operand(2)) && is_word_pair(operand(1), operand(3)) =>
+            {
+                let left = self.word_expression(operand(0), operand(2));
+                let right = self.word_expression(operand(1), operand(3));
+                *flags = FlagState {
+                    flags: Flags::Unknown,
+                    carry: Some((left, right)),
+                };
+                Some((4, None))
+            }
+            _ => None,
+        }
+    }
+
+    fn statement(
+        &self,
+        address: u16,
+        instruction: &Instruction,
+        previous: Option<Mnemonic>,
+    ) -> Option<String> {
+        use Mnemonic::*;
+        let operand = instruction.operand.map(|operand| self.expression(operand));
+        let operand = operand.unwrap_or_default();
+        let target = instruction
+            .operand
+            .map_or("A".to_string(), |_| operand.clone());
+        let register = register_name(instruction.mnemonic);
+        let assembly = format!("{} {}", instruction.mnemonic, operand);
+        let assembly = format!("asm(\"{}\");", assembly.trim_end());
+        let text = match instruction.mnemonic {
+            _ if !instruction.is_official() => assembly,
+            Lda | Ldx | Ldy => format!("{} = {};", register, operand),
+            Sta | Stx | Sty => format!("{} = {};", operand, register),
+            Tax | Tay => format!("{} = A;", register),
+            Txa => "A = X;".to_string(),
+            Tya => "A = Y;".to_string(),
+            Tsx => "X = S;".to_string(),
+            Txs => "S = X;".to_string(),
+            Inx | Iny => format!("{}++;", register),
+            Dex | Dey => format!("{}--;", register),
+            Inc => format!("{}++;", operand),
+            Dec => format!("{}--;", operand),
+            Adc if previous == Some(Clc) => format!("A += {};", operand),
+            Adc => format!("A += {} + C;", operand),
+            Sbc if previous == Some(Sec) => format!("A -= {};", operand),
+            Sbc => format!("A -= {} + !C;", operand),
+            And => format!("A &= {};", operand),
+            Ora => format!("A |= {};", operand),
+            Eor => format!("A ^= {};", operand),
+            Asl => format!("{} <<= 1;", target),
+            Lsr => format!("{} >>= 1;", target),
+            Rol => format!("{0} = {0} << 1 | C;", target),
+            Ror => format!("{0} = {0} >> 1 | C << 7;", target),
+            Clc => "C = 0;".to_string(),
+            Sec => "C = 1;".to_string(),
+            Cli => "I = 0;".to_string(),
+            Sei => "I = 1;".to_string(),
+            Cld => "D = 0;".to_string(),
+            Sed => "D = 1;".to_string(),
+            Clv => "V = 0;".to_string(),
+            Pha => "push(A);".to_string(),
+            Php => "push(P);".to_string(),
+            Pla => "A = pop();".to_string(),
+            Plp => "P = pop();".to_string(),
+            Rts | Rti => "return;".to_string(),
+            Brk => "brk();".to_string(),
+            Jsr => format!("{}();", self.call_name(instruction)),
+            Jmp => match (
+                instruction.jump_target(),
+                self.prg_data.inferred.get(&self.location(address)),
+            ) {
+                (Some(_), _) => format!("return {}();", self.call_name(instruction)),
+                (None, Some(Inferred::Target(target))) => format!("goto {};", self.name(*target)),
+                (None, _) => format!("goto {};", operand),
+            },
+            Cmp | Cpx | Cpy | Bit | Nop => return None,
+            _ => assembly,
+        };
+        Some(text)
+    }
+
+    fn call_name(&self, instruction: &Instruction) -> String {
+        instruction
+            .jump_target()
+            .map(|target| self.name(self.prg_data.resolve(self.bank, target)))
+            .unwrap_or_default()
+    }
+
+    fn update_flags(&self, instruction: &Instruction, flags: &mut FlagState) {
+        use Mnemonic::*;
+        let operand = instruction.operand.map(|operand| self.expression(operand));
+        let register = register_name(instruction.mnemonic).to_string();
+        match instruction.mnemonic {
+            Lda | Ldx | Ldy | Tax | Tay | Txa | Tya | Tsx | Pla | Inx | Dex | Iny | Dey | And
+            | Ora | Eor => flags.flags = Flags::Value(register),
+            Inc | Dec => flags.flags = Flags::Value(operand.unwrap_or_default()),
+            // Shifts of memory leave their result there, additions leave it in A
+            Asl | Lsr | Rol | Ror => {
+                flags.flags = Flags::Value(operand.unwrap_or(register));
+                flags.carry = None;
+            }
+            Adc | Sbc => {
+                flags.flags = Flags::Value(register);
+                flags.carry = None;
+            }
+            Cmp | Cpx | Cpy => {
+                let operand = operand.unwrap_or_default();
+                flags.flags = Flags::Compare(register.clone(), operand.clone());
+                flags.carry = Some((register, operand));
+            }
+            Bit => flags.flags = Flags::Bit(operand.unwrap_or_default()),
+            Clc | Sec => flags.carry = None,
+            // Stores don't change the flags, but the value they were set from
+            Sta | Stx | Sty => {
+                let stored = operand.unwrap_or_default();
+                let changed = match &flags.flags {
+                    Flags::Value(value) | Flags::Bit(value) => *value == stored,
+                    Flags::Compare(left, right) => *left == stored || *right == stored,
+                    Flags::Unknown => false,
+                };
+                if changed {
+                    flags.flags = Flags::Unknown;
+                }
+                if flags
+                    .carry
+                    .as_ref()
+                    .is_some_and(|(left, right)| *left == stored || *right == stored)
+                {
+                    flags.carry = None;
+                }
+            }
+            Pha | Php | Txs | Cli | Sei | Cld | Sed | Clv | Nop | Jmp | Rts | Bcc | Bcs | Beq
+            | Bmi | Bne | Bpl | Bvc | Bvs => {}
+            _ => *flags = FlagState::unknown(),
+        }
+    }
+
+    /// Statements of the block at `index`, leaving out a branch or jump at its end that the
+    /// structure shows, and the flags that branch tests.
+    fn lift(&self, index: usize) -> (Vec<Statement>, FlagState) {
+        let block = &self.cfg.blocks[&self.starts[index]];
+        let mut instructions = block.instructions.as_slice();
+        if let Some((_, last)) = instructions.last() {
+            let structural = last.is_branch()
+                || (last.mnemonic == Mnemonic::Jmp
+                    && block
+                        .edges
+                        .iter()
+                        .any(|edge| edge.kind == EdgeKind::Unconditional));
+            if structural {
+                instructions = &instructions[..instructions.len() - 1];
+            }
+        }
+
+        let mut statements: Vec<Statement> = Vec::new();
+        let mut flags = FlagState::unknown();
+        let mut offset = 0;
+        while offset < instructions.len() {
+            if let Some((length, text)) = self.word_idiom(&instructions[offset..], &mut flags) {
+                statements.extend(text.map(|text| Statement {
+                    address: instructions[offset].0,
+                    text,
+                    argument: None,
+                }));
+                offset += length;
+                continue;
+            }
+            let (address, instruction) = &instructions[offset];
+            let previous = offset
+                .checked_sub(1)
+                .map(|previous| instructions[previous].1.mnemonic);
+            let next = instructions.get(offset + 1).map(|(_, next)| next.mnemonic);
+            self.update_flags(instruction, &mut flags);
+            offset += 1;
+            // Carry setup folds into the addition or subtraction after it
+            if matches!(
+                (instruction.mnemonic, next),
+                (Mnemonic::Clc, Some(Mnemonic::Adc)) | (Mnemonic::Sec, Some(Mnemonic::Sbc))
+            ) {
+                continue;
+            }
+            let Some(mut text) = self.statement(*address, instruction, previous) else {
+                continue;
+            };
+            let mut address = *address;
+            if instruction.mnemonic == Mnemonic::Jsr {
+                let mut arguments: Vec<Statement> = Vec::new();
+                while let Some(argument) = statements.last().and_then(|last| last.argument) {
+                    if arguments
+                        .iter()
+                        .any(|other| other.argument == Some(argument))
+                    {
+                        break;
+                    }
+                    arguments.extend(statements.pop());
+                }
+                if let Some(first) = arguments.last() {
+                    address = first.address;
+                    arguments.sort_by_key(|argument| argument.argument);
+                    let arguments = arguments
+                        .iter()
+                        .map(|argument| argument.text.trim_end_matches(';'))
+                        .collect::<Vec<&str>>()
+                        .join(", ");
+                    text = format!("{}({});", self.call_name(instruction), arguments);
+                }
+            }
+            let argument = matches!(
+                instruction.mnemonic,
+                Mnemonic::Lda | Mnemonic::Ldx | Mnemonic::Ldy
+            )
+            .then(|| register_name(instruction.mnemonic));
+            statements.push(Statement {
+                address,
+                text,
+                argument,
+            });
+        }
+        (statements, flags)
+    }
+
+    fn push(&mut self, location: Option<Location>, indent: usize, text: String) {
+        self.lines.push(Line::Code(PseudoLine {
+            location,
+            indent,
+            text,
+        }));
+    }
+
+    fn goto(
+        &mut self,
+        location: Option<Location>,
+        indent: usize,
+        condition: Option<String>,
+        target: Location,
+    ) {
+        self.gotos.insert(target);
+        let text = match condition {
+            Some(condition) => format!("if ({}) goto {};", condition, self.name(target)),
+            None => format!("goto {};", self.name(target)),
+        };
+        self.push(location, indent, text);
+    }
+
+    fn index_of(&self, location: Location) -> Option<usize> {
+        self.starts.binary_search(&location).ok()
+    }
+
+    /// Branch or jump ending the block at `index`, its location, target and whether it's
+    /// conditional.
+    fn terminator(&self, index: usize) -> Option<(Location, Location, bool)> {
+        let block = &self.cfg.blocks[&self.starts[index]];
+        let (address, last) = block.instructions.last()?;
+        let conditional = last.is_branch();
+        if !conditional && last.mnemonic != Mnemonic::Jmp {
+            return None;
+        }
+        let target = block.edges.iter().find(|edge| {
+            edge.kind == EdgeKind::Taken || (!conditional && edge.kind == EdgeKind::Unconditional)
+        })?;
+        Some((self.location(*address), target.to, conditional))
+    }
+
+    /// Conditions under which the branch ending the block at `index` is taken and not taken.
+    fn branch_condition(&self, index: usize) -> (String, String) {
+        let start = self.starts[index];
+        let mnemonic = self.cfg.blocks[&start]
+            .instructions
+            .last()
+            .map(|(_, last)| last.mnemonic);
+        self.flags
+            .get(&start)
+            .cloned()
+            .unwrap_or_else(FlagState::unknown)
+            .condition(mnemonic.unwrap_or(Mnemonic::Bne))
+    }
+
+    /// Last block of the loop that starts at `index`, the one jumping back the furthest.
+    fn loop_tail(&self, index: usize, end: usize) -> Option<usize> {
+        (index..end).rev().find(|tail| {
+            self.terminator(*tail)
+                .is_some_and(|(_, target, _)| target == self.starts[index])
+        })
+    }
+
+    fn emit_range(&mut self, start: usize, end: usize, indent: usize, loop_exit: Option<Location>) {
+        let mut index = start;
+        while index < end {
+            if !self.loop_headers.contains(&index) {
+                let tail = self.loop_tail(index, end);
+                if let Some((tail, (location, _, conditional))) =
+                    tail.and_then(|tail| Some((tail, self.terminator(tail)?)))
+                {
+                    self.loop_headers.insert(index);
+                    self.structured.insert(location);
+                    let exit = self.starts.get(tail + 1).copied();
+                    let header = Some(self.starts[index]);
+                    if conditional {
+                        self.push(header, indent, "do {".to_string());
+                        self.emit_range(index, tail + 1, indent + 1, exit);
+                        let (taken, _) = self.branch_condition(tail);
+                        self.push(Some(location), indent, format!("}} while ({});", taken));
+                    } else {
+                        self.push(header, indent, "while (true) {".to_string());
+                        self.emit_range(index, tail + 1, indent + 1, exit);
+                        self.push(Some(location), indent, "}".to_string());
+                    }
+                    index = tail + 1;
+                    continue;
+                }
+            }
+
+            let start_location = self.starts[index];
+            self.lines.push(Line::Label(start_location, indent));
+            let (statements, flags) = self.lift(index);
+            for statement in statements {
+                let location = self.location(statement.address);
+                self.push(Some(location), indent, statement.text);
+            }
+            self.flags.insert(start_location, flags);
+            let next = self.starts.get(index + 1).copied();
+            index += 1;
+            let Some((location, target, conditional)) = self.terminator(index - 1) else {
+                continue;
+            };
+            if self.structured.contains(&location) {
+                continue;
+            }
+            if !conditional {
+                if Some(target) == loop_exit {
+                    self.push(Some(location), indent, "break;".to_string());
+                } else if Some(target) != next {
+                    self.goto(Some(location), indent, None, target);
+                }
+                continue;
+            }
+            let (taken, not_taken) = self.branch_condition(index - 1);
+            if Some(target) == loop_exit {
+                self.push(Some(location), indent, format!("if ({}) break;", taken));
+                continue;
+            }
+            if Some(target) == next {
+                continue;
+            }
+            let forward = self
+                .index_of(target)
+                .filter(|target_index| *target_index > index && *target_index <= end);
+            let Some(target_index) = forward else {
+                self.goto(Some(location), indent, Some(taken), target);
+                continue;
+            };
+            // A jump over the code the branch lands on makes it an if/else
+            let else_end = self
+                .terminator(target_index - 1)
+                .filter(|(_, _, conditional)| !conditional)
+                .filter(|(_, else_target, _)| Some(*else_target) != loop_exit)
+                .and_then(|(jump, else_target, _)| Some((jump, self.index_of(else_target)?)))
+                .filter(|(_, else_end)| *else_end > target_index && *else_end <= end);
+            self.push(Some(location), indent, format!("if ({}) {{", not_taken));
+            match else_end {
+                Some((jump, else_end)) => {
+                    self.structured.insert(jump);
+                    self.emit_range(index, target_index, indent + 1, loop_exit);
+                    self.push(Some(jump), indent, "} else {".to_string());
+                    self.emit_range(target_index, else_end, indent + 1, loop_exit);
+                    self.push(None, indent, "}".to_string());
+                    index = else_end;
+                }
+                None => {
+                    self.emit_range(index, target_index, indent + 1, loop_exit);
+                    self.push(None, indent, "}".to_string());
+                    index = target_index;
+                }
+            }
+        }
+    }
+}
+
+/// Pseudocode of the subroutine starting at `entry`, with `if`/`else` and loops recovered
+/// from its branches. Whatever doesn't fit those shapes is written with `goto`.
+pub fn decompile(prg_data: &PrgData, entry: Location) -> Vec<PseudoLine> {
+    let cfg = ControlFlowGraph::build(prg_data, entry);
+    let starts = cfg.blocks.keys().copied().collect::<Vec<Location>>();
+    let mut decompiler = Decompiler {
+        prg_data,
+        bank: entry.bank.unwrap_or_default(),
+        cfg,
+        starts,
+        lines: Vec::new(),
+        gotos: BTreeSet::new(),
+        loop_headers: BTreeSet::new(),
+        structured: BTreeSet::new(),
+        flags: BTreeMap::new(),
+    };
+    let name = decompiler.name(entry);
+    decompiler.push(Some(entry), 0, format!("void {}() {{", name));
+    if decompiler
+        .starts
+        .first()
+        .is_some_and(|first| *first != entry)
+    {
+        decompiler.goto(None, 1, None, entry);
+    }
+    decompiler.emit_range(0, decompiler.starts.len(), 1, None);
+    decompiler.push(None, 0, "}".to_string());
+
+    let Decompiler { lines, gotos, .. } = decompiler;
+    lines
+        .into_iter()
+        .filter_map(|line| match line {
+            Line::Code(line) => Some(line),
+            Line::Label(location, indent) if gotos.contains(&location) => Some(PseudoLine {
+                location: Some(location),
+                indent: indent.saturating_sub(1),
+                text: format!(
+                    "{}:",
+                    prg_data
+                        .labels
+                        .name(&location)
+                        .map_or(format!("${:04X}", location.address), str::to_string)
+                ),
+            }),
+            Line::Label(..) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prg::data::nrom_prg;
+
+    fn decompiled(code: &[u8]) -> Vec<String> {
+        let prg_data = PrgData::parse(nrom_prg(code)).unwrap();
+        let entry = Location {
+            bank: Some(0),
+            address: 0xC000,
+        };
+        decompile(&prg_data, entry)
+            .into_iter()
+            .map(|line| format!("{}{}", "  ".repeat(line.indent), line.text))
+            .collect()
+    }
+
+    #[test]
+    fn decompile_branches_and_loops() {
+        let lines = decompiled(&[
+            0xA5, 0x10, // LDA $10
+            0xC9, 0x03, // CMP #$03
+            0xD0, 0x05, // BNE $C00B
+            0xA9, 0x01, // LDA #$01
+            0x4C, 0x0D, 0xC0, // JMP $C00D
+            0xA9, 0x02, // LDA #$02
+            0x85, 0x11, // STA $11
+            0xA2, 0x08, // LDX #$08
+            0xCA, // DEX
+            0xD0, 0xFD, // BNE $C011
+            0xA9, 0x05, // LDA #$05
+            0xA0, 0x01, // LDY #$01
+            0x20, 0x20, 0xC0, // JSR $C020
+            0x60, // RTS
+        ]);
+        assert_eq!(
+            lines,
+            vec![
+                "void sub_C000() {",
+                "  A = data_0010;",
+                "  if (A == $03) {",
+                "    A = $01;",
+                "  } else {",
+                "    A = $02;",
+                "  }",
+                "  data_0011 = A;",
+                "  X = $08;",
+                "  do {",
+                "    X--;",
+                "  } while (X != 0);",
+                "  sub_C020(A = $05, Y = $01);",
+                "  return;",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn decompile_branch_after_addition() {
+        let lines = decompiled(&[
+            0xA5, 0x10, // LDA $10
+            0x18, // CLC
+            0x69, 0x01, // ADC #$01
+            0xF0, 0x02, // BEQ $C009
+            0x85, 0x11, // STA $11
+            0x60, // RTS
+        ]);
+        assert_eq!(
+            lines,
+            vec![
+                "void sub_C000() {",
+                "  A = data_0010;",
+                "  A += $01;",
+                "  if (A != 0) {",
+                "    data_0011 = A;",
+                "  }",
+                "  return;",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn decompile_word_idioms() {
+        let lines = decompiled(&[
+            0x18, // CLC
+            0xA5, 0x10, // LDA $10
+            0x69, 0x34, // ADC #$34
+            0x85, 0x12, // STA $12
+            0xA5, 0x11, // LDA $11
+            0x69, 0x12, // ADC #$12
+            0x85, 0x13, // STA $13
+            0xA5, 0x12, // LDA $12
+            0xC5, 0x14, // CMP $14
+            0xA5, 0x13, // LDA $13
+            0xE5, 0x15, // SBC $15
+            0x90, 0x01, // BCC $C019
+            0x60, // RTS
+            0x60, // RTS
+        ]);
+        assert_eq!(
+            lines[1..lines.len() - 1],
+            [
+                "  word(data_0012) = word(data_0010) + $1234;",
+                "  if (word(data_0012) >= word(data_0014)) {",
+                "    return;",
+                "  }",
+                "  return;",
+            ]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// How the user wants a range of PRG ROM to be read, overriding the disassembler.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegionKind {
+    Code,
+    Byte,
+    Word,
+    Pointer,
+    Text,
+}
+
+impl fmt::Display for RegionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stringified = match self {
+            RegionKind::Code => "Code",
+            RegionKind::Byte => "Byte",
+            RegionKind::Word => "Word",
+            RegionKind::Pointer => "Pointer",
+            RegionKind::Text => "Text",
+        };
+        write!(f, "{}", stringified)
+    }
+}
+
+impl RegionKind {
+    pub const ALL: [RegionKind; 5] = [
+        RegionKind::Code,
+        RegionKind::Byte,
+        RegionKind::Word,
+        RegionKind::Pointer,
+        RegionKind::Text,
+    ];
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Region {
+    pub kind: RegionKind,
+    pub length: usize,
+}
+
+impl Region {
+    /// Whether the region starting at `start` covers `address`.
+    pub fn contains(&self, start: u16, address: u16) -> bool {
+        address >= start && ((address - start) as usize) < self.length
+    }
+}
+
+/// Operand of a `.byte` directive listing `bytes` as text: printable ASCII characters in
+/// quotes and everything else as numbers, e.g. `"GAME OVER", $00`.
+pub fn text_operand(bytes: &[u8]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut text = String::new();
+    for byte in bytes {
+        let printable = byte.is_ascii_graphic() || *byte == b' ';
+        if printable && *byte != b'"' && *byte != b'\\' {
+            text.push(*byte as char);
+            continue;
+        }
+        if !text.is_empty() {
+            parts.push(format!("\"{}\"", text));
+            text.clear();
+        }
+        parts.push(format!("${:02X}", byte));
+    }
+    if !text.is_empty() {
+        parts.push(format!("\"{}\"", text));
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_operand_quotes_printable_characters() {
+        assert_eq!(text_operand(b"GAME OVER\0"), "\"GAME OVER\", $00");
+        assert_eq!(text_operand(b"\x01A\"B"), "$01, \"A\", $22, \"B\"");
+        let region = Region {
+            kind: RegionKind::Text,
+            length: 4,
+        };
+        assert!(region.contains(0xC000, 0xC003));
+        assert!(!region.contains(0xC000, 0xC004));
+    }
+}
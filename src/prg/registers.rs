@@ -0,0 +1,202 @@
+/// Memory mapped register of the PPU, APU, controller ports or cartridge mapper.
+#[derive(PartialEq, Debug)]
+pub struct Register {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub mapper: bool,
+}
+
+const PPU_REGISTERS_ADDRESS: u16 = 0x2000;
+const PPU_REGISTERS_END: u16 = 0x3FFF;
+const PPU_REGISTER_COUNT: u16 = 8;
+const APU_REGISTERS_ADDRESS: u16 = 0x4000;
+pub const MAPPER_REGISTERS_ADDRESS: u16 = 0x8000;
+
+const fn hardware(name: &'static str, description: &'static str) -> Register {
+    Register {
+        name,
+        description,
+        mapper: false,
+    }
+}
+
+const fn mapper(name: &'static str, description: &'static str) -> Register {
+    Register {
+        name,
+        description,
+        mapper: true,
+    }
+}
+
+const PPU_REGISTERS: [Register; 8] = [
+    hardware(
+        "PPUCTRL",
+        "PPU control: NMI enable, sprite size, pattern table and nametable select",
+    ),
+    hardware(
+        "PPUMASK",
+        "PPU mask: background and sprite rendering, color emphasis",
+    ),
+    hardware(
+        "PPUSTATUS",
+        "PPU status: vblank, sprite 0 hit, sprite overflow. Reading resets the address latch",
+    ),
+    hardware("OAMADDR", "OAM address for OAMDATA"),
+    hardware("OAMDATA", "OAM data, increments OAMADDR on write"),
+    hardware("PPUSCROLL", "Scroll position, written twice: X then Y"),
+    hardware(
+        "PPUADDR",
+        "VRAM address, written twice: high byte then low byte",
+    ),
+    hardware("PPUDATA", "VRAM data, increments PPUADDR on access"),
+];
+
+const APU_REGISTERS: [Register; 24] = [
+    hardware("SQ1_VOL", "Pulse 1 duty cycle, envelope and volume"),
+    hardware("SQ1_SWEEP", "Pulse 1 sweep unit"),
+    hardware("SQ1_LO", "Pulse 1 timer low byte"),
+    hardware("SQ1_HI", "Pulse 1 length counter and timer high bits"),
+    hardware("SQ2_VOL", "Pulse 2 duty cycle, envelope and volume"),
+    hardware("SQ2_SWEEP", "Pulse 2 sweep unit"),
+    hardware("SQ2_LO", "Pulse 2 timer low byte"),
+    hardware("SQ2_HI", "Pulse 2 length counter and timer high bits"),
+    hardware("TRI_LINEAR", "Triangle linear counter"),
+    hardware("APU_UNUSED1", "Unused APU register"),
+    hardware("TRI_LO", "Triangle timer low byte"),
+    hardware("TRI_HI", "Triangle length counter and timer high bits"),
+    hardware("NOISE_VOL", "Noise envelope and volume"),
+    hardware("APU_UNUSED2", "Unused APU register"),
+    hardware("NOISE_LO", "Noise mode and period"),
+    hardware("NOISE_HI", "Noise length counter"),
+    hardware("DMC_FREQ", "DMC IRQ enable, loop and frequency"),
+    hardware("DMC_RAW", "DMC output level"),
+    hardware("DMC_START", "DMC sample address, $C000 + value * 64"),
+    hardware("DMC_LEN", "DMC sample length, value * 16 + 1 bytes"),
+    hardware(
+        "OAMDMA",
+        "Sprite DMA, copies page $XX00 of CPU memory to OAM",
+    ),
+    hardware("SND_CHN", "APU channel enable and status"),
+    hardware(
+        "JOY1",
+        "Controller 1 data on read, controller strobe on write",
+    ),
+    hardware(
+        "JOY2",
+        "Controller 2 data on read, APU frame counter on write",
+    ),
+];
+
+const MMC1_REGISTERS: [Register; 4] = [
+    mapper(
+        "MMC1_CONTROL",
+        "MMC1 control: mirroring, PRG and CHR bank modes. Written serially, one bit at a time",
+    ),
+    mapper(
+        "MMC1_CHR0",
+        "MMC1 CHR bank 0. Written serially, one bit at a time",
+    ),
+    mapper(
+        "MMC1_CHR1",
+        "MMC1 CHR bank 1. Written serially, one bit at a time",
+    ),
+    mapper(
+        "MMC1_PRG",
+        "MMC1 PRG bank and PRG RAM enable. Written serially, one bit at a time",
+    ),
+];
+
+const MMC3_REGISTERS: [Register; 8] = [
+    mapper(
+        "MMC3_BANK_SELECT",
+        "MMC3 bank select: bank register to update, PRG and CHR inversion",
+    ),
+    mapper(
+        "MMC3_BANK_DATA",
+        "MMC3 bank data for the register picked with bank select",
+    ),
+    mapper("MMC3_MIRRORING", "MMC3 nametable mirroring"),
+    mapper(
+        "MMC3_PRG_RAM_PROTECT",
+        "MMC3 PRG RAM enable and write protection",
+    ),
+    mapper("MMC3_IRQ_LATCH", "MMC3 scanline counter reload value"),
+    mapper("MMC3_IRQ_RELOAD", "MMC3 scanline counter reload"),
+    mapper("MMC3_IRQ_DISABLE", "MMC3 IRQ disable and acknowledge"),
+    mapper("MMC3_IRQ_ENABLE", "MMC3 IRQ enable"),
+];
+
+const UXROM_BANK: Register = mapper("UXROM_BANK", "UxROM PRG bank switched in at $8000");
+const CNROM_BANK: Register = mapper("CNROM_BANK", "CNROM CHR bank");
+const AXROM_BANK: Register = mapper("AXROM_BANK", "AxROM 32KiB PRG bank and nametable select");
+const GXROM_BANK: Register = mapper("GXROM_BANK", "GxROM PRG and CHR banks");
+const MAPPER_REGISTER: Register = mapper("MAPPER", "Write to a mapper register");
+
+/// Registers decoded from the upper address bits, as mappers rarely look at the others.
+fn mapper_register(mapper: u32, address: u16) -> Option<&'static Register> {
+    let register = match mapper {
+        0 => return None,
+        1 => &MMC1_REGISTERS[((address >> 13) & 0x03) as usize],
+        2 => &UXROM_BANK,
+        3 => &CNROM_BANK,
+        4 => &MMC3_REGISTERS[(((address >> 12) & 0x06) | (address & 0x01)) as usize],
+        7 => &AXROM_BANK,
+        66 => &GXROM_BANK,
+        _ => &MAPPER_REGISTER,
+    };
+    Some(register)
+}
+
+/// Register at exactly `address`, without looking at mirrors or mappers.
+pub fn hardware_register(address: u16) -> Option<&'static Register> {
+    match address {
+        PPU_REGISTERS_ADDRESS..=PPU_REGISTERS_END
+            if address - PPU_REGISTERS_ADDRESS < PPU_REGISTER_COUNT =>
+        {
+            Some(&PPU_REGISTERS[(address - PPU_REGISTERS_ADDRESS) as usize])
+        }
+        _ => APU_REGISTERS.get(address.checked_sub(APU_REGISTERS_ADDRESS)? as usize),
+    }
+}
+
+/// Register accessed through `address`. PRG ROM addresses only map to registers when
+/// they're written to, that's how games talk to the mapper.
+pub fn register(mapper: u32, address: u16, write: bool) -> Option<&'static Register> {
+    match address {
+        PPU_REGISTERS_ADDRESS..=PPU_REGISTERS_END => hardware_register(
+            PPU_REGISTERS_ADDRESS + (address - PPU_REGISTERS_ADDRESS) % PPU_REGISTER_COUNT,
+        ),
+        MAPPER_REGISTERS_ADDRESS.. if write => mapper_register(mapper, address),
+        _ => hardware_register(address),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hardware_registers() {
+        assert_eq!(register(0, 0x2002, false).unwrap().name, "PPUSTATUS");
+        assert_eq!(register(0, 0x3FFE, true).unwrap().name, "PPUADDR");
+        assert_eq!(register(0, 0x4016, false).unwrap().name, "JOY1");
+        assert_eq!(register(0, 0x4018, false), None);
+        assert_eq!(hardware_register(0x2008), None);
+        assert_eq!(register(0, 0x0300, true), None);
+    }
+
+    #[test]
+    fn mapper_registers() {
+        assert_eq!(register(1, 0x8000, true).unwrap().name, "MMC1_CONTROL");
+        assert_eq!(register(1, 0xE000, true).unwrap().name, "MMC1_PRG");
+        assert_eq!(register(4, 0x8000, true).unwrap().name, "MMC3_BANK_SELECT");
+        assert_eq!(
+            register(4, 0xA001, true).unwrap().name,
+            "MMC3_PRG_RAM_PROTECT"
+        );
+        assert_eq!(register(4, 0xFFFF, true).unwrap().name, "MMC3_IRQ_ENABLE");
+        assert!(register(2, 0xC000, true).unwrap().mapper);
+        assert_eq!(register(1, 0x8000, false), None);
+        assert_eq!(register(0, 0x8000, true), None);
+    }
+}
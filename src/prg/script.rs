@@ -0,0 +1,386 @@
+use std::{collections::BTreeMap, error, fmt};
+
+use super::{
+    data::{Location, PrgData},
+    regions::RegionKind,
+    tables::Pointer,
+    text_table::TextTable,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptError {
+    Malformed(usize),
+    UnknownString(Location),
+    Unencodable(Location, char),
+    /// Strings that don't fit in the free space of their bank.
+    Overflow(Vec<Location>),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Malformed(line) => write!(f, "Invalid script line {}", line),
+            ScriptError::UnknownString(location) => {
+                write!(f, "No string of the script starts at {}", location)
+            }
+            ScriptError::Unencodable(location, c) => write!(
+                f,
+                "\"{}\" of the string at {} isn't in the text table",
+                c, location
+            ),
+            ScriptError::Overflow(locations) => write!(
+                f,
+                "No room left in the bank for the strings at {}",
+                locations
+                    .iter()
+                    .map(Location::to_string)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl error::Error for ScriptError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// String of a text region that pointers lead to.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ScriptString {
+    pub location: Location,
+    pub bytes: Vec<u8>,
+    pub pointers: Vec<(Location, Pointer)>,
+}
+
+fn text_table(prg_data: &PrgData) -> TextTable {
+    prg_data.text_table.clone().unwrap_or_else(TextTable::ascii)
+}
+
+/// Strings that pointers lead into text regions to, in address order. Each one runs up to
+/// its end code or the end of its region.
+pub fn script_strings(prg_data: &PrgData) -> Vec<ScriptString> {
+    let table = text_table(prg_data);
+    let mut strings: BTreeMap<Location, ScriptString> = BTreeMap::new();
+    for (location, pointer) in prg_data.pointers.iter() {
+        let target = pointer.target;
+        let region = prg_data
+            .region_at(target)
+            .filter(|(_, region)| region.kind == RegionKind::Text);
+        let (Some((start, region)), Some(bank)) = (region, target.bank) else {
+            continue;
+        };
+        let string = strings.entry(target).or_insert_with(|| {
+            let bank = &prg_data.banks[bank];
+            let first = (target.address - bank.base_address) as usize;
+            let end = ((start.address - bank.base_address) as usize + region.length)
+                .min(bank.bytes.len());
+            let bytes = &bank.bytes[first..end];
+            let length = table.string_length(bytes).unwrap_or(bytes.len());
+            ScriptString {
+                location: target,
+                bytes: bytes[..length].to_vec(),
+                pointers: Vec::new(),
+            }
+        });
+        string.pointers.push((*location, *pointer));
+    }
+    strings.into_values().collect()
+}
+
+/// Editable script of the strings, one `bank:address=text` line each with line breaks
+/// written as `\n`.
+pub fn dump_script(prg_data: &PrgData) -> String {
+    let table = text_table(prg_data);
+    let mut script = String::from("; Edit the text after each =, \\n is a line break\n");
+    for string in script_strings(prg_data) {
+        let text = table.decode(&string.bytes).replace('\n', "\\n");
+        script.push_str(&format!("{}={}\n", string.location, text));
+    }
+    script
+}
+
+fn parse_location(text: &str) -> Option<Location> {
+    let (bank, address) = text.split_once(':')?;
+    Some(Location {
+        bank: Some(usize::from_str_radix(bank, 16).ok()?),
+        address: u16::from_str_radix(address, 16).ok()?,
+    })
+}
+
+/// Strings of an edited script, encoded.
+fn parse_script(
+    script: &str,
+    table: &TextTable,
+) -> Result<BTreeMap<Location, Vec<u8>>, ScriptError> {
+    let mut strings = BTreeMap::new();
+    for (index, line) in script.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let (location, text) = line
+            .split_once('=')
+            .and_then(|(location, text)| Some((parse_location(location)?, text)))
+            .ok_or(ScriptError::Malformed(index + 1))?;
+        let bytes = table
+            .encode(&text.replace("\\n", "\n"))
+            .map_err(|c| ScriptError::Unencodable(location, c))?;
+        strings.insert(location, bytes);
+    }
+    Ok(strings)
+}
+
+/// Fill bytes assemblers pad the end of a bank with.
+const PADDING: [u8; 2] = [0x00, 0xFF];
+/// Shortest run of bytes a CDL file never saw being accessed that strings move to.
+const MIN_UNUSED_LENGTH: usize = 16;
+/// Bytes of the NMI, RESET and IRQ vectors at the end of the fixed bank.
+const VECTORS_LENGTH: usize = 6;
+
+/// Free ranges of a bank as start and end addresses, first fit.
+#[derive(Default)]
+struct FreeSpace {
+    ranges: Vec<(usize, usize)>,
+}
+
+impl FreeSpace {
+    /// Space nothing in `bank` uses: the padding at its end, up to the vectors of the fixed
+    /// bank, and runs of bytes a CDL file never saw being accessed.
+    fn unused(prg_data: &PrgData, bank: usize) -> FreeSpace {
+        let mut space = FreeSpace::default();
+        let prg_bank = &prg_data.banks[bank];
+        let start = prg_bank.base_address as usize;
+        let mut end = start + prg_bank.bytes.len();
+        if end == 0x10000 {
+            end -= VECTORS_LENGTH;
+        }
+        let byte = |address: usize| prg_bank.bytes[address - start];
+        let unused = |address: usize| {
+            prg_data.is_unused(Location {
+                bank: Some(bank),
+                address: address as u16,
+            })
+        };
+
+        let fill = byte(end - 1);
+        if PADDING.contains(&fill) {
+            let padding = (start..end)
+                .rev()
+                .take_while(|&address| byte(address) == fill && unused(address))
+                .last();
+            if let Some(padding) = padding {
+                space.free(padding, end);
+            }
+        }
+
+        if !prg_data.code_data_log.is_empty() {
+            let mut run = None;
+            for address in start..=end {
+                match (address < end && unused(address), run) {
+                    (true, None) => run = Some(address),
+                    (false, Some(first)) => {
+                        if address - first >= MIN_UNUSED_LENGTH {
+                            space.free(first, address);
+                        }
+                        run = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        space
+    }
+
+    fn free(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.ranges.push((start, end));
+        }
+    }
+
+    /// Merges adjacent ranges so that strings can span them.
+    fn merge(&mut self) {
+        self.ranges.sort();
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in self.ranges.drain(..) {
+            match merged.last_mut() {
+                Some((_, last_end)) if *last_end >= start => *last_end = (*last_end).max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        self.ranges = merged;
+    }
+
+    fn allocate(&mut self, length: usize) -> Option<usize> {
+        let range = self
+            .ranges
+            .iter_mut()
+            .find(|(start, end)| end - start >= length)?;
+        let start = range.0;
+        range.0 += length;
+        Some(start)
+    }
+}
+
+/// Writes the strings of an edited script back. Strings that still fit stay where they
+/// are, the ones that grew move to space freed or unused in their bank and get repointed.
+/// Nothing is written when a string doesn't fit anywhere.
+pub fn insert_script(prg_data: &mut PrgData, script: &str) -> Result<(), ScriptError> {
+    let table = text_table(prg_data);
+    let edited = parse_script(script, &table)?;
+    let strings = script_strings(prg_data)
+        .into_iter()
+        .map(|string| (string.location, string))
+        .collect::<BTreeMap<Location, ScriptString>>();
+    if let Some(location) = edited
+        .keys()
+        .find(|location| !strings.contains_key(location))
+    {
+        return Err(ScriptError::UnknownString(*location));
+    }
+
+    let mut writes: Vec<(Location, Vec<u8>)> = Vec::new();
+    let mut free_space: BTreeMap<usize, FreeSpace> = BTreeMap::new();
+    let mut moved = Vec::new();
+    for (location, bytes) in edited.iter() {
+        let string = &strings[location];
+        let bank = location.bank.unwrap_or_default();
+        let start = location.address as usize;
+        let space = free_space
+            .entry(bank)
+            .or_insert_with(|| FreeSpace::unused(prg_data, bank));
+        if bytes.len() <= string.bytes.len() {
+            space.free(start + bytes.len(), start + string.bytes.len());
+            writes.push((*location, bytes.clone()));
+        } else {
+            space.free(start, start + string.bytes.len());
+            moved.push((*location, bytes));
+        }
+    }
+    for space in free_space.values_mut() {
+        space.merge();
+    }
+
+    let mut overflow = Vec::new();
+    for (location, bytes) in moved {
+        let bank = location.bank.unwrap_or_default();
+        let Some(address) = free_space
+            .get_mut(&bank)
+            .and_then(|space| space.allocate(bytes.len()))
+        else {
+            overflow.push(location);
+            continue;
+        };
+        let target = Location {
+            bank: location.bank,
+            address: address as u16,
+        };
+        writes.push((target, bytes.clone()));
+        for (pointer_location, pointer) in strings[&location].pointers.iter() {
+            let repointed = Pointer { target, ..*pointer };
+            writes.push((*pointer_location, repointed.bytes()));
+        }
+    }
+    if !overflow.is_empty() {
+        return Err(ScriptError::Overflow(overflow));
+    }
+    prg_data
+        .overwrite(&writes)
+        .map_err(|_| ScriptError::Overflow(edited.keys().copied().collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cdl::PRG_DATA,
+        prg::data::{nrom_prg, PRG_BANK_SIZE},
+    };
+
+    fn location(address: u16) -> Location {
+        Location {
+            bank: Some(0),
+            address,
+        }
+    }
+
+    fn prg_data() -> PrgData {
+        let mut prg = nrom_prg(&[]);
+        // Pointers to "AB", <end> and "BA", <end>
+        prg[0x100..0x104].copy_from_slice(&[0x10, 0xC1, 0x13, 0xC1]);
+        prg[0x110..0x116].copy_from_slice(&[0x00, 0x01, 0xFF, 0x01, 0x00, 0xFF]);
+        let mut prg_data = PrgData::parse(prg).unwrap();
+        prg_data.text_table = Some(TextTable::parse("00=A\n01=B\n/FF=<end>").unwrap());
+        prg_data.set_region(location(0xC110), 6, Some(RegionKind::Text));
+        prg_data.set_region(location(0xC100), 4, Some(RegionKind::Pointer));
+        prg_data
+    }
+
+    #[test]
+    fn dump_strings_behind_pointers() {
+        let prg_data = prg_data();
+        let strings = script_strings(&prg_data);
+        assert_eq!(strings.len(), 2);
+        assert_eq!(strings[1].bytes, vec![0x01, 0x00, 0xFF]);
+        assert_eq!(strings[1].pointers[0].0, location(0xC102));
+        assert_eq!(
+            dump_script(&prg_data),
+            "; Edit the text after each =, \\n is a line break\n00:C110=AB<end>\n00:C113=BA<end>\n"
+        );
+    }
+
+    #[test]
+    fn insert_edited_strings() {
+        let mut prg_data = prg_data();
+        // The first string shrinks, which leaves room for the second one to grow
+        insert_script(&mut prg_data, "00:C110=A<end>\n00:C113=BAB<end>\n").unwrap();
+        let bank = &prg_data.banks[0];
+        assert_eq!(
+            bank.bytes[0x110..0x116],
+            [0x00, 0xFF, 0x01, 0x00, 0x01, 0xFF]
+        );
+        assert_eq!(bank.word_at(0xC100), Some(0xC110));
+        assert_eq!(bank.word_at(0xC102), Some(0xC112));
+
+        // Without padding at the end of the bank there's no room for a longer string
+        prg_data.banks[0].bytes[0x116..0x3FFA].fill(0xEA);
+        assert_eq!(
+            insert_script(&mut prg_data, "00:C112=BBBBBBB<end>"),
+            Err(ScriptError::Overflow(vec![location(0xC112)]))
+        );
+        assert_eq!(
+            insert_script(&mut prg_data, "00:C111=A"),
+            Err(ScriptError::UnknownString(location(0xC111)))
+        );
+        assert_eq!(
+            insert_script(&mut prg_data, "C110 A"),
+            Err(ScriptError::Malformed(1))
+        );
+    }
+
+    #[test]
+    fn move_strings_to_unused_space() {
+        let mut padded = prg_data();
+        // Nothing shrinks, the first string moves to the padding after the strings
+        insert_script(&mut padded, "00:C110=ABA<end>\n").unwrap();
+        let bank = &padded.banks[0];
+        assert_eq!(bank.bytes[0x116..0x11A], [0x00, 0x01, 0x00, 0xFF]);
+        assert_eq!(bank.word_at(0xC100), Some(0xC116));
+        assert_eq!(bank.word_at(0xC102), Some(0xC113));
+
+        // A CDL file shows which bytes before the padding were never accessed
+        let mut logged = prg_data();
+        logged.banks[0].bytes[0x116..0x3FFA].fill(0xEA);
+        let mut flags = vec![PRG_DATA; PRG_BANK_SIZE];
+        flags[0x200..0x210].fill(0);
+        logged.code_data_log = flags;
+        insert_script(&mut logged, "00:C110=ABAB<end>\n").unwrap();
+        assert_eq!(logged.banks[0].word_at(0xC100), Some(0xC200));
+        assert_eq!(
+            logged.banks[0].bytes[0x200..0x205],
+            [0x00, 0x01, 0x00, 0x01, 0xFF]
+        );
+    }
+}
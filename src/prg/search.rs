@@ -0,0 +1,361 @@
+use std::{error, fmt};
+
+use super::{
+    data::{Location, PrgData},
+    instructions::Instruction,
+    text_table::TextTable,
+    xrefs::ReferenceKind,
+};
+
+// Searching for something too common would list most of the ROM
+const MAX_MATCHES: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidSearchError {
+    Empty,
+    InvalidByte(String),
+    InvalidValue(String),
+    Unencodable(char),
+}
+
+impl fmt::Display for InvalidSearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidSearchError::Empty => write!(f, "Nothing to search for"),
+            InvalidSearchError::InvalidByte(byte) => {
+                write!(f, "\"{}\" isn't a hexadecimal byte or ??", byte)
+            }
+            InvalidSearchError::InvalidValue(value) => {
+                write!(f, "\"{}\" isn't an address such as $4014", value)
+            }
+            InvalidSearchError::Unencodable(c) => {
+                write!(f, "\"{}\" isn't in the text table", c)
+            }
+        }
+    }
+}
+
+impl error::Error for InvalidSearchError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// What the search text is read as.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum SearchMode {
+    Bytes,
+    /// Text in the encoding of the game.
+    Text,
+    Instructions,
+    /// Instructions accessing an address, optionally only in one way.
+    Operand(Option<ReferenceKind>),
+}
+
+impl fmt::Display for SearchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchMode::Bytes => write!(f, "Bytes"),
+            SearchMode::Text => write!(f, "Text"),
+            SearchMode::Instructions => write!(f, "Instructions"),
+            SearchMode::Operand(None) => write!(f, "Any access to"),
+            SearchMode::Operand(Some(kind)) => write!(f, "Any {} to", kind),
+        }
+    }
+}
+
+impl SearchMode {
+    pub const ALL: [SearchMode; 8] = [
+        SearchMode::Bytes,
+        SearchMode::Text,
+        SearchMode::Instructions,
+        SearchMode::Operand(None),
+        SearchMode::Operand(Some(ReferenceKind::Read)),
+        SearchMode::Operand(Some(ReferenceKind::Write)),
+        SearchMode::Operand(Some(ReferenceKind::Call)),
+        SearchMode::Operand(Some(ReferenceKind::Jump)),
+    ];
+
+    pub fn placeholder(&self) -> &'static str {
+        match self {
+            SearchMode::Bytes => "AD 02 20 10 ??",
+            SearchMode::Text => "GAME OVER",
+            SearchMode::Instructions => "LDA $2002 / BPL *",
+            SearchMode::Operand(_) => "$4014",
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum SearchQuery {
+    /// Bytes, `None` matching any byte.
+    Bytes(Vec<Option<u8>>),
+    /// Patterns for consecutive instructions, where `*` stands for any text.
+    Instructions(Vec<String>),
+    Operand {
+        address: u16,
+        kind: Option<ReferenceKind>,
+    },
+}
+
+/// Instruction text without spaces, in upper case.
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| glob_match(rest, &text[skip..])),
+        Some((expected, rest)) => text.first() == Some(expected) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Reads hex bytes separated by spaces, `??` matching any byte.
+pub fn parse_byte_pattern(text: &str) -> Result<Vec<Option<u8>>, InvalidSearchError> {
+    text.split_whitespace()
+        .map(|byte| match byte {
+            "??" => Ok(None),
+            _ if byte.len() == 2 => u8::from_str_radix(byte, 16)
+                .map(Some)
+                .map_err(|_| InvalidSearchError::InvalidByte(byte.to_string())),
+            _ => Err(InvalidSearchError::InvalidByte(byte.to_string())),
+        })
+        .collect()
+}
+
+fn instruction_text(instruction: &Instruction) -> String {
+    let operand = instruction
+        .operand
+        .map_or(String::new(), |operand| operand.to_string());
+    normalize(&format!("{}{}", instruction.mnemonic, operand))
+}
+
+impl SearchQuery {
+    /// Reads `text` as `mode`. Text is encoded with `text_table`, or as ASCII without one.
+    pub fn parse(
+        mode: SearchMode,
+        text: &str,
+        text_table: Option<&TextTable>,
+    ) -> Result<SearchQuery, InvalidSearchError> {
+        if text.trim().is_empty() {
+            return Err(InvalidSearchError::Empty);
+        }
+        match mode {
+            SearchMode::Bytes => parse_byte_pattern(text).map(SearchQuery::Bytes),
+            SearchMode::Text => text_table
+                .cloned()
+                .unwrap_or_else(TextTable::ascii)
+                .encode(text)
+                .map(|bytes| SearchQuery::Bytes(bytes.into_iter().map(Some).collect()))
+                .map_err(InvalidSearchError::Unencodable),
+            SearchMode::Instructions => {
+                let patterns = text
+                    .split('/')
+                    .map(normalize)
+                    .filter(|pattern| !pattern.is_empty())
+                    .collect::<Vec<String>>();
+                if patterns.is_empty() {
+                    return Err(InvalidSearchError::Empty);
+                }
+                Ok(SearchQuery::Instructions(patterns))
+            }
+            SearchMode::Operand(kind) => {
+                let value = text.trim();
+                let address = value
+                    .strip_prefix('$')
+                    .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| InvalidSearchError::InvalidValue(value.to_string()))?;
+                Ok(SearchQuery::Operand { address, kind })
+            }
+        }
+    }
+}
+
+/// Where a search matched, and how many bytes the match spans.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct SearchMatch {
+    pub location: Location,
+    pub length: usize,
+}
+
+/// Looks for `query` in every bank, in bank and address order. Stops after `MAX_MATCHES`.
+pub fn search(prg_data: &PrgData, query: &SearchQuery) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    for bank in prg_data.banks.iter() {
+        let location = |address: u16| Location {
+            bank: Some(bank.number),
+            address,
+        };
+        match query {
+            SearchQuery::Bytes(pattern) => {
+                let found = bank
+                    .bytes
+                    .windows(pattern.len().max(1))
+                    .enumerate()
+                    .filter(|(_, window)| {
+                        window.iter().zip(pattern.iter()).all(|(byte, expected)| {
+                            expected.is_none_or(|expected| expected == *byte)
+                        })
+                    })
+                    .map(|(offset, _)| SearchMatch {
+                        location: location(bank.base_address + offset as u16),
+                        length: pattern.len(),
+                    });
+                matches.extend(found);
+            }
+            SearchQuery::Instructions(patterns) => {
+                let instructions = bank
+                    .instructions
+                    .iter()
+                    .collect::<Vec<(&u16, &Instruction)>>();
+                for start in 0..instructions.len() {
+                    let run =
+                        &instructions[start..(start + patterns.len()).min(instructions.len())];
+                    let contiguous = run
+                        .windows(2)
+                        .all(|pair| *pair[0].0 as usize + pair[0].1.len() == *pair[1].0 as usize);
+                    let matched = run.len() == patterns.len()
+                        && contiguous
+                        && run
+                            .iter()
+                            .zip(patterns.iter())
+                            .all(|((_, instruction), pattern)| {
+                                glob_match(
+                                    pattern.as_bytes(),
+                                    instruction_text(instruction).as_bytes(),
+                                )
+                            });
+                    if matched {
+                        let (first, _) = run[0];
+                        let length = run.iter().map(|(_, instruction)| instruction.len()).sum();
+                        matches.push(SearchMatch {
+                            location: location(*first),
+                            length,
+                        });
+                    }
+                }
+            }
+            SearchQuery::Operand { address, kind } => {
+                let found = bank
+                    .instructions
+                    .iter()
+                    .filter(|(from, instruction)| {
+                        instruction.operand.and_then(|operand| operand.address()) == Some(*address)
+                            && prg_data
+                                .xrefs
+                                .get(&prg_data.resolve(bank.number, *address))
+                                .iter()
+                                .any(|reference| {
+                                    reference.from == location(**from)
+                                        && kind.is_none_or(|kind| reference.kind == kind)
+                                })
+                    })
+                    .map(|(from, instruction)| SearchMatch {
+                        location: location(*from),
+                        length: instruction.len(),
+                    });
+                matches.extend(found);
+            }
+        }
+        if matches.len() >= MAX_MATCHES {
+            matches.truncate(MAX_MATCHES);
+            break;
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prg::data::nrom_prg;
+
+    fn prg_data() -> PrgData {
+        // LDA $2002; BPL $C000; LDA #$02; STA $4014; LDA $4014; RTS
+        PrgData::parse(nrom_prg(&[
+            0xAD, 0x02, 0x20, 0x10, 0xFB, 0xA9, 0x02, 0x8D, 0x14, 0x40, 0xAD, 0x14, 0x40, 0x60,
+        ]))
+        .unwrap()
+    }
+
+    fn addresses(matches: Vec<SearchMatch>) -> Vec<u16> {
+        matches.iter().map(|found| found.location.address).collect()
+    }
+
+    #[test]
+    fn search_bytes() {
+        let prg = prg_data();
+        let query = SearchQuery::parse(SearchMode::Bytes, "AD 02 20 10 ??", None).unwrap();
+        assert_eq!(
+            search(&prg, &query),
+            vec![SearchMatch {
+                location: Location {
+                    bank: Some(0),
+                    address: 0xC000
+                },
+                length: 5
+            }]
+        );
+        let query = SearchQuery::parse(SearchMode::Bytes, "14 40", None).unwrap();
+        assert_eq!(addresses(search(&prg, &query)), vec![0xC008, 0xC00B]);
+        assert_eq!(
+            SearchQuery::parse(SearchMode::Bytes, "AD 2", None),
+            Err(InvalidSearchError::InvalidByte("2".to_string()))
+        );
+    }
+
+    #[test]
+    fn search_instructions() {
+        let prg = prg_data();
+        let query =
+            SearchQuery::parse(SearchMode::Instructions, "LDA $2002 / BPL *", None).unwrap();
+        let matches = search(&prg, &query);
+        assert_eq!(addresses(matches.clone()), vec![0xC000]);
+        assert_eq!(matches[0].length, 5);
+        let query = SearchQuery::parse(SearchMode::Instructions, "lda *", None).unwrap();
+        assert_eq!(
+            addresses(search(&prg, &query)),
+            vec![0xC000, 0xC005, 0xC00A]
+        );
+        assert_eq!(
+            SearchQuery::parse(SearchMode::Instructions, " / ", None),
+            Err(InvalidSearchError::Empty)
+        );
+    }
+
+    #[test]
+    fn search_text() {
+        let mut prg = nrom_prg(&[]);
+        prg[0x100..0x104].copy_from_slice(&[0x07, 0x00, 0x0C, 0xFF]);
+        prg[0x200..0x203].copy_from_slice(b"HI!");
+        let prg = PrgData::parse(prg).unwrap();
+        let table = TextTable::parse("00=A\n07=H\n0C=M\n/FF").unwrap();
+        let query = SearchQuery::parse(SearchMode::Text, "HAM", Some(&table)).unwrap();
+        assert_eq!(addresses(search(&prg, &query)), vec![0xC100]);
+        let query = SearchQuery::parse(SearchMode::Text, "HI!", None).unwrap();
+        assert_eq!(addresses(search(&prg, &query)), vec![0xC200]);
+        assert_eq!(
+            SearchQuery::parse(SearchMode::Text, "HI", Some(&table)),
+            Err(InvalidSearchError::Unencodable('I'))
+        );
+    }
+
+    #[test]
+    fn search_operands() {
+        let prg = prg_data();
+        let writes = SearchMode::Operand(Some(ReferenceKind::Write));
+        let query = SearchQuery::parse(writes, "$4014", None).unwrap();
+        assert_eq!(addresses(search(&prg, &query)), vec![0xC007]);
+        let query = SearchQuery::parse(SearchMode::Operand(None), "$4014", None).unwrap();
+        assert_eq!(addresses(search(&prg, &query)), vec![0xC007, 0xC00A]);
+        assert_eq!(
+            SearchQuery::parse(writes, "4014", None),
+            Err(InvalidSearchError::InvalidValue("4014".to_string()))
+        );
+    }
+}
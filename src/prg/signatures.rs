@@ -0,0 +1,260 @@
+use std::{error, fmt};
+
+use super::{
+    data::{Location, PrgData},
+    labels::LabelKind,
+    search::{parse_byte_pattern, search, SearchQuery},
+    structs::is_valid_name,
+};
+
+/// Routines most games write the same way.
+const BUILT_IN: &str = "\
+reset_stub = 78 A2 FF 9A 8E 00 80 4C ?? ?? ; MMC1 reset stub
+mmc1_write = 8D ?? ?? 4A 8D ?? ?? 4A 8D ?? ?? 4A 8D ?? ?? 4A 8D ?? ?? ; MMC1 serial register writes
+nmi = 48 8A 48 98 48 ; NMI handler saving A, X and Y
+read_joypad = A9 01 8D 16 40 A9 00 8D 16 40 ; Controller read
+read_joypad = A9 01 8D 16 40 4A 8D 16 40 ; Controller read
+read_joypad = A2 01 8E 16 40 CA 8E 16 40 ; Controller read
+wait_vblank = 2C 02 20 10 FB ; Vertical blank wait
+oam_dma = A9 ?? 8D 14 40 ; Sprite DMA
+famitone2_update = AD ?? ?? 30 02 D0 03 4C ?? ?? 18 AD ?? ?? 6D ?? ?? 8D ?? ?? AD ?? ?? 6D ?? ?? CD ?? ?? B0 ?? 8D ?? ?? 4C ?? ?? ; FamiTone2 sound engine
+famistudio_update = AD ?? ?? 30 02 D0 08 A9 01 8D ?? ?? 4C ?? ?? ; FamiStudio sound engine
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidSignatureError {
+    pub line: usize,
+}
+
+impl fmt::Display for InvalidSignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid signature on line {}, expected name = AA ?? BB ; description",
+            self.line
+        )
+    }
+}
+
+impl error::Error for InvalidSignatureError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Byte pattern of a known routine, `None` matching any byte.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Signature {
+    pub name: String,
+    pub pattern: Vec<Option<u8>>,
+    pub description: String,
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pattern = self
+            .pattern
+            .iter()
+            .map(|byte| byte.map_or("??".to_string(), |byte| format!("{:02X}", byte)))
+            .collect::<Vec<String>>()
+            .join(" ");
+        write!(f, "{} = {} ; {}", self.name, pattern, self.description)
+    }
+}
+
+impl Signature {
+    /// Reads one `name = AA ?? BB ; description` signature per line. The description is
+    /// optional, comments start with `#`.
+    pub fn parse_all(text: &str) -> Result<Vec<Signature>, InvalidSignatureError> {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|(index, line)| {
+                let invalid = InvalidSignatureError { line: index + 1 };
+                let (name, rest) = line.split_once('=').ok_or(invalid.clone())?;
+                let (pattern, description) = rest.split_once(';').unwrap_or((rest, ""));
+                let name = name.trim();
+                let pattern = parse_byte_pattern(pattern).map_err(|_| invalid.clone())?;
+                if !is_valid_name(name) || pattern.iter().all(Option::is_none) {
+                    return Err(invalid);
+                }
+                let description = match description.trim() {
+                    "" => name,
+                    description => description,
+                };
+                Ok(Signature {
+                    name: name.to_string(),
+                    pattern,
+                    description: description.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn built_in() -> Vec<Signature> {
+        Signature::parse_all(BUILT_IN).expect("built-in signatures are valid")
+    }
+}
+
+/// Code a signature matched.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SignatureMatch {
+    pub location: Location,
+    pub name: String,
+    pub description: String,
+    /// Subroutine named after the signature, when it starts where the match does.
+    pub subroutine: Option<Location>,
+}
+
+/// Looks for the built-in signatures and those of `prg_data` in its code.
+pub fn find_signatures(prg_data: &PrgData) -> Vec<SignatureMatch> {
+    let mut matches = Vec::new();
+    for signature in Signature::built_in()
+        .into_iter()
+        .chain(prg_data.signatures.iter().cloned())
+    {
+        let query = SearchQuery::Bytes(signature.pattern.clone());
+        for found in search(prg_data, &query) {
+            if !prg_data.is_code(found.location) {
+                continue;
+            }
+            let subroutine = prg_data
+                .labels
+                .get(&found.location)
+                .filter(|label| label.kind == LabelKind::Subroutine)
+                .map(|_| found.location);
+            matches.push(SignatureMatch {
+                location: found.location,
+                name: signature.name.clone(),
+                description: signature.description.clone(),
+                subroutine,
+            });
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prg::data::nrom_prg;
+
+    fn location(address: u16) -> Location {
+        Location {
+            bank: Some(0),
+            address,
+        }
+    }
+
+    #[test]
+    fn parse_signatures() {
+        let signatures =
+            Signature::parse_all("# Sound\nplay = 20 ?? ?? ; Music\nstop = A9 00\n").unwrap();
+        assert_eq!(signatures[0].pattern, vec![Some(0x20), None, None]);
+        assert_eq!(signatures[0].to_string(), "play = 20 ?? ?? ; Music");
+        assert_eq!(signatures[1].description, "stop");
+        assert_eq!(
+            Signature::parse_all("play 20\n"),
+            Err(InvalidSignatureError { line: 1 })
+        );
+        assert_eq!(
+            Signature::parse_all("\nplay = ?? ??\n"),
+            Err(InvalidSignatureError { line: 2 })
+        );
+        assert_eq!(Signature::built_in().len(), BUILT_IN.lines().count());
+    }
+
+    #[test]
+    fn detect_sound_engines() {
+        let famitone2 = [
+            0xAD, 0x10, 0x03, // LDA FT_SONG_SPEED
+            0x30, 0x02, // BMI @pause
+            0xD0, 0x03, // BNE @update
+            0x4C, 0x26, 0xC0, // @pause: JMP @update_sound
+            0x18, // @update: CLC
+            0xAD, 0x11, 0x03, // LDA FT_TEMPO_ACC_L
+            0x6D, 0x13, 0x03, // ADC FT_TEMPO_STEP_L
+            0x8D, 0x11, 0x03, // STA FT_TEMPO_ACC_L
+            0xAD, 0x12, 0x03, // LDA FT_TEMPO_ACC_H
+            0x6D, 0x14, 0x03, // ADC FT_TEMPO_STEP_H
+            0xCD, 0x10, 0x03, // CMP FT_SONG_SPEED
+            0xB0, 0x07, // BCS @update_row
+            0x8D, 0x12, 0x03, // STA FT_TEMPO_ACC_H
+            0x4C, 0x26, 0xC0, // JMP @update_envelopes
+            0x60, // RTS
+        ];
+        let famistudio = [
+            0xAD, 0x10, 0x03, // LDA famistudio_song_speed
+            0x30, 0x02, // BMI @pause
+            0xD0, 0x08, // BNE @update
+            0xA9, 0x01, // @pause: LDA #1
+            0x8D, 0x15, 0x03, // STA famistudio_tempo_frame_cnt
+            0x4C, 0x0F, 0xC0, // JMP @update_sound
+            0x60, // @update: RTS
+        ];
+        let engines: [(&[u8], &str, &str); 2] = [
+            (&famitone2, "famitone2_update", "FamiTone2 sound engine"),
+            (&famistudio, "famistudio_update", "FamiStudio sound engine"),
+        ];
+        for (code, name, description) in engines {
+            let prg_data = PrgData::parse(nrom_prg(code)).unwrap();
+            assert_eq!(prg_data.labels.name(&location(0xC000)), Some(name));
+            assert!(prg_data
+                .detected
+                .iter()
+                .any(|found| found.description == description));
+        }
+    }
+
+    #[test]
+    fn name_matching_subroutines() {
+        let code = [
+            0x20, 0x07, 0xC0, // JSR $C007
+            0x2C, 0x02, 0x20, // BIT $2002
+            0x60, // RTS
+            0xA9, 0x01, // $C007: LDA #$01
+            0x8D, 0x16, 0x40, // STA $4016
+            0xA9, 0x00, // LDA #$00
+            0x8D, 0x16, 0x40, // STA $4016
+            0x60, // RTS
+        ];
+        let mut prg_data = PrgData::parse(nrom_prg(&code)).unwrap();
+
+        assert_eq!(prg_data.labels.name(&location(0xC007)), Some("read_joypad"));
+        assert_eq!(prg_data.detected[0].description, "Controller read");
+
+        prg_data.set_signatures(Signature::parse_all("poll_ppu = 2C 02 20 ; PPU polling").unwrap());
+        assert_eq!(
+            prg_data.detected.last(),
+            Some(&SignatureMatch {
+                location: location(0xC003),
+                name: "poll_ppu".to_string(),
+                description: "PPU polling".to_string(),
+                subroutine: None,
+            })
+        );
+        assert_eq!(prg_data.labels.name(&location(0xC000)), Some("sub_C000"));
+    }
+
+    #[test]
+    fn signature_names_stay_generated() {
+        // JSR $C004; RTS; LDA $2002; RTS
+        let code = [0x20, 0x04, 0xC0, 0x60, 0xAD, 0x02, 0x20, 0x60];
+        let mut prg_data = PrgData::parse(nrom_prg(&code)).unwrap();
+        prg_data.set_signatures(Signature::parse_all("start = 20 04 C0 60 ; Start").unwrap());
+        assert_eq!(prg_data.labels.name(&location(0xC000)), Some("start"));
+        assert_eq!(prg_data.labels.renamed().count(), 0);
+
+        prg_data.set_signatures(Vec::new());
+        assert_eq!(prg_data.labels.name(&location(0xC000)), Some("sub_C000"));
+
+        prg_data.set_signatures(Signature::parse_all("start = 20 04 C0 60 ; Start").unwrap());
+        prg_data
+            .labels
+            .rename(location(0xC000), "main".to_string())
+            .unwrap();
+        prg_data.set_signatures(Signature::parse_all("start = 20 04 C0 60 ; Start").unwrap());
+        assert_eq!(prg_data.labels.name(&location(0xC000)), Some("main"));
+        assert_eq!(prg_data.labels.renamed().count(), 1);
+    }
+}
@@ -0,0 +1,240 @@
+use std::{error, fmt};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidStructError {
+    InvalidName(String),
+    InvalidField(String),
+    InvalidCount(String),
+    NoFields,
+}
+
+impl fmt::Display for InvalidStructError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidStructError::InvalidName(name) => write!(f, "Invalid struct name \"{}\"", name),
+            InvalidStructError::InvalidField(field) => write!(
+                f,
+                "Invalid field \"{}\", expected name:u8, u16, ptr or enum(a|b|...)",
+                field
+            ),
+            InvalidStructError::InvalidCount(count) => {
+                write!(f, "Invalid repeat count \"{}\"", count)
+            }
+            InvalidStructError::NoFields => write!(f, "A struct needs at least one field"),
+        }
+    }
+}
+
+impl error::Error for InvalidStructError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Same rules as labels, since names end up in the exported source.
+pub fn is_valid_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Byte,
+    Word,
+    Pointer,
+    /// Byte naming one of the values, by index.
+    Enum(Vec<String>),
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::Byte => write!(f, "u8"),
+            FieldType::Word => write!(f, "u16"),
+            FieldType::Pointer => write!(f, "ptr"),
+            FieldType::Enum(values) => write!(f, "enum({})", values.join("|")),
+        }
+    }
+}
+
+impl FieldType {
+    pub fn size(&self) -> usize {
+        match self {
+            FieldType::Byte | FieldType::Enum(_) => 1,
+            FieldType::Word | FieldType::Pointer => 2,
+        }
+    }
+
+    fn parse(text: &str) -> Option<FieldType> {
+        match text {
+            "u8" => Some(FieldType::Byte),
+            "u16" => Some(FieldType::Word),
+            "ptr" => Some(FieldType::Pointer),
+            _ => {
+                let values = text.strip_prefix("enum(")?.strip_suffix(')')?;
+                let values = values
+                    .split('|')
+                    .map(str::trim)
+                    .map(str::to_string)
+                    .collect::<Vec<String>>();
+                values
+                    .iter()
+                    .all(|value| is_valid_name(value))
+                    .then_some(FieldType::Enum(values))
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct StructField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct StructLayout {
+    pub name: String,
+    pub fields: Vec<StructField>,
+}
+
+impl StructLayout {
+    pub fn size(&self) -> usize {
+        self.fields
+            .iter()
+            .map(|field| field.field_type.size())
+            .sum()
+    }
+
+    /// Fields along with their offset into a record.
+    pub fn offsets(&self) -> impl Iterator<Item = (usize, &StructField)> {
+        self.fields.iter().scan(0, |offset, field| {
+            let field_offset = *offset;
+            *offset += field.field_type.size();
+            Some((field_offset, field))
+        })
+    }
+}
+
+/// A layout repeated `count` times in a row.
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct StructTable {
+    pub layout: StructLayout,
+    pub count: usize,
+}
+
+impl fmt::Display for StructTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]", self.layout.name, self.count)?;
+        for field in self.layout.fields.iter() {
+            write!(f, " {}:{}", field.name, field.field_type)?;
+        }
+        Ok(())
+    }
+}
+
+impl StructTable {
+    /// Reads a definition such as `Enemy[8] x:u8 y:u8 hp:u16 ai:ptr kind:enum(walker|flyer)`.
+    /// Without a count, the table repeats as often as it fits in `length` bytes.
+    pub fn parse(definition: &str, length: usize) -> Result<StructTable, InvalidStructError> {
+        let mut parts = definition.split_whitespace();
+        let head = parts.next().unwrap_or_default();
+        let (name, count) = match head.split_once('[') {
+            Some((name, count)) => {
+                let count = count
+                    .strip_suffix(']')
+                    .and_then(|count| count.parse::<usize>().ok())
+                    .filter(|count| *count > 0)
+                    .ok_or_else(|| InvalidStructError::InvalidCount(head.to_string()))?;
+                (name, Some(count))
+            }
+            None => (head, None),
+        };
+        if !is_valid_name(name) {
+            return Err(InvalidStructError::InvalidName(name.to_string()));
+        }
+        let fields = parts
+            .map(|part| {
+                part.split_once(':')
+                    .filter(|(name, _)| is_valid_name(name))
+                    .and_then(|(name, field_type)| {
+                        Some(StructField {
+                            name: name.to_string(),
+                            field_type: FieldType::parse(field_type)?,
+                        })
+                    })
+                    .ok_or_else(|| InvalidStructError::InvalidField(part.to_string()))
+            })
+            .collect::<Result<Vec<StructField>, InvalidStructError>>()?;
+        if fields.is_empty() {
+            return Err(InvalidStructError::NoFields);
+        }
+        let layout = StructLayout {
+            name: name.to_string(),
+            fields,
+        };
+        let count = count.unwrap_or((length / layout.size()).max(1));
+        Ok(StructTable { layout, count })
+    }
+
+    pub fn length(&self) -> usize {
+        self.layout.size() * self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_struct_tables() {
+        let table =
+            StructTable::parse("Enemy[8] x:u8 hp:u16 ai:ptr kind:enum(walker|flyer)", 4).unwrap();
+        assert_eq!(table.count, 8);
+        assert_eq!(table.layout.size(), 6);
+        assert_eq!(table.length(), 48);
+        assert_eq!(
+            table.layout.fields[3].field_type,
+            FieldType::Enum(vec!["walker".to_string(), "flyer".to_string()])
+        );
+        assert_eq!(
+            table
+                .layout
+                .offsets()
+                .map(|(offset, _)| offset)
+                .collect::<Vec<usize>>(),
+            vec![0, 1, 3, 5]
+        );
+        assert_eq!(
+            table.to_string(),
+            "Enemy[8] x:u8 hp:u16 ai:ptr kind:enum(walker|flyer)"
+        );
+        assert_eq!(StructTable::parse("Pair a:u8 b:u8", 7).unwrap().count, 3);
+    }
+
+    #[test]
+    fn reject_invalid_struct_tables() {
+        assert_eq!(
+            StructTable::parse("Enemy x:u32", 4),
+            Err(InvalidStructError::InvalidField("x:u32".to_string()))
+        );
+        assert_eq!(
+            StructTable::parse("Enemy[0] x:u8", 4),
+            Err(InvalidStructError::InvalidCount("Enemy[0]".to_string()))
+        );
+        assert_eq!(
+            StructTable::parse("1up x:u8", 4),
+            Err(InvalidStructError::InvalidName("1up".to_string()))
+        );
+        assert_eq!(
+            StructTable::parse("Enemy", 4),
+            Err(InvalidStructError::NoFields)
+        );
+    }
+}
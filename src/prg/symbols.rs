@@ -0,0 +1,433 @@
+use std::{collections::HashMap, error, fmt};
+
+use super::data::{Location, PrgData};
+
+const WORK_RAM_ADDRESS: u16 = 0x6000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidSymbolFileError {
+    UnsupportedFormat(String),
+    InvalidLine(usize),
+}
+
+impl fmt::Display for InvalidSymbolFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidSymbolFileError::UnsupportedFormat(name) => {
+                write!(f, "Unsupported symbol file \"{}\"", name)
+            }
+            InvalidSymbolFileError::InvalidLine(line) => {
+                write!(f, "Invalid symbol file, line {}", line)
+            }
+        }
+    }
+}
+
+impl error::Error for InvalidSymbolFileError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Symbol {
+    pub location: Location,
+    pub name: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Segment {
+    pub location: Location,
+    pub name: String,
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SymbolFile {
+    pub symbols: Vec<Symbol>,
+    pub segments: Vec<Segment>,
+}
+
+impl SymbolFile {
+    /// Parses a ca65 debug info (.dbg), FCEUX name list (.nl) or Mesen label (.mlb) file,
+    /// picking the format by file extension. `prg_rom_start` is the offset of PRG ROM in the
+    /// ROM file, which .dbg files count from.
+    pub fn parse(
+        file_name: &str,
+        text: &str,
+        prg_data: &PrgData,
+        prg_rom_start: usize,
+    ) -> Result<SymbolFile, InvalidSymbolFileError> {
+        let lowercase_name = file_name.to_lowercase();
+        if lowercase_name.ends_with(".dbg") {
+            SymbolFile::parse_ca65_dbg(text, prg_data, prg_rom_start)
+        } else if lowercase_name.ends_with(".mlb") {
+            SymbolFile::parse_mesen_mlb(text, prg_data)
+        } else if let Some(stem) = lowercase_name.strip_suffix(".nl") {
+            // FCEUX keeps one file per bank, named <rom>.<bank in hex>.nl or <rom>.ram.nl
+            let bank = match stem.rsplit('.').next() {
+                Some("ram") => None,
+                Some(bank) => Some(usize::from_str_radix(bank, 16).map_err(|_| {
+                    InvalidSymbolFileError::UnsupportedFormat(file_name.to_string())
+                })?),
+                None => None,
+            };
+            SymbolFile::parse_fceux_nl(text, bank)
+        } else {
+            Err(InvalidSymbolFileError::UnsupportedFormat(
+                file_name.to_string(),
+            ))
+        }
+    }
+
+    fn non_empty(text: &str) -> Option<String> {
+        let text = text.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    fn parse_fceux_nl(
+        text: &str,
+        bank: Option<usize>,
+    ) -> Result<SymbolFile, InvalidSymbolFileError> {
+        let mut symbols = Vec::new();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid_line = InvalidSymbolFileError::InvalidLine(line_number + 1);
+            let mut fields = line.splitn(3, '#');
+            let address = fields
+                .next()
+                .and_then(|field| field.strip_prefix('$'))
+                // Arrays are written as $address/size
+                .and_then(|field| field.split('/').next())
+                .and_then(|field| u16::from_str_radix(field, 16).ok())
+                .ok_or(invalid_line)?;
+            let bank = bank.filter(|_| address >= 0x8000);
+            symbols.push(Symbol {
+                location: Location { bank, address },
+                name: fields.next().and_then(SymbolFile::non_empty),
+                comment: fields.next().and_then(SymbolFile::non_empty),
+            });
+        }
+        Ok(SymbolFile {
+            symbols,
+            segments: Vec::new(),
+        })
+    }
+
+    fn parse_mesen_mlb(
+        text: &str,
+        prg_data: &PrgData,
+    ) -> Result<SymbolFile, InvalidSymbolFileError> {
+        let mut symbols = Vec::new();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid_line = InvalidSymbolFileError::InvalidLine(line_number + 1);
+            let mut fields = line.splitn(4, ':');
+            let memory_type = fields.next().ok_or(invalid_line.clone())?;
+            // Ranges are written as start-end, only the start gets the label
+            let value = fields
+                .next()
+                .and_then(|field| field.split('-').next())
+                .and_then(|field| usize::from_str_radix(field, 16).ok())
+                .ok_or(invalid_line.clone())?;
+            let location = match memory_type {
+                "P" | "NesPrgRom" => prg_data.location_of_offset(value),
+                "R" | "NesInternalRam" | "G" | "NesMemory" => {
+                    u16::try_from(value).ok().map(|address| Location {
+                        bank: None,
+                        address,
+                    })
+                }
+                "S" | "W" | "NesSaveRam" | "NesWorkRam" => {
+                    u16::try_from(value).ok().map(|offset| Location {
+                        bank: None,
+                        address: WORK_RAM_ADDRESS.wrapping_add(offset),
+                    })
+                }
+                // Labels for CHR memory and other devices have no place in the PRG listing
+                _ => continue,
+            };
+            let Some(location) = location else {
+                continue;
+            };
+            symbols.push(Symbol {
+                location,
+                name: fields.next().and_then(SymbolFile::non_empty),
+                comment: fields
+                    .next()
+                    .map(|comment| comment.replace("\\n", "\n"))
+                    .as_deref()
+                    .and_then(SymbolFile::non_empty),
+            });
+        }
+        Ok(SymbolFile {
+            symbols,
+            segments: Vec::new(),
+        })
+    }
+
+    /// Splits a `key=value,key="value"` list of a ca65 debug info line.
+    fn ca65_attributes(attributes: &str) -> HashMap<&str, &str> {
+        let mut result = HashMap::new();
+        let mut rest = attributes;
+        while let Some((key, value_and_rest)) = rest.split_once('=') {
+            let (value, next) = if let Some(quoted) = value_and_rest.strip_prefix('"') {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                let next = quoted[end..].trim_start_matches('"');
+                (&quoted[..end], next.strip_prefix(',').unwrap_or(next))
+            } else {
+                value_and_rest
+                    .split_once(',')
+                    .unwrap_or((value_and_rest, ""))
+            };
+            result.insert(key.trim(), value);
+            rest = next;
+        }
+        result
+    }
+
+    fn ca65_number(value: &str) -> Option<usize> {
+        match value.strip_prefix("0x") {
+            Some(hex) => usize::from_str_radix(hex, 16).ok(),
+            None => value.parse().ok(),
+        }
+    }
+
+    fn parse_ca65_dbg(
+        text: &str,
+        prg_data: &PrgData,
+        prg_rom_start: usize,
+    ) -> Result<SymbolFile, InvalidSymbolFileError> {
+        struct Ca65Segment {
+            name: String,
+            start: usize,
+            output_offset: Option<usize>,
+        }
+
+        let mut segments: HashMap<usize, Ca65Segment> = HashMap::new();
+        let mut symbol_lines = Vec::new();
+        for (line_number, line) in text.lines().enumerate() {
+            let Some((kind, attributes)) = line.split_once(char::is_whitespace) else {
+                continue;
+            };
+            let attributes = SymbolFile::ca65_attributes(attributes.trim());
+            let invalid_line = InvalidSymbolFileError::InvalidLine(line_number + 1);
+            match kind {
+                "seg" => {
+                    let id = attributes
+                        .get("id")
+                        .and_then(|v| SymbolFile::ca65_number(v))
+                        .ok_or(invalid_line.clone())?;
+                    let start = attributes
+                        .get("start")
+                        .and_then(|v| SymbolFile::ca65_number(v))
+                        .ok_or(invalid_line)?;
+                    segments.insert(
+                        id,
+                        Ca65Segment {
+                            name: attributes.get("name").unwrap_or(&"").to_string(),
+                            start,
+                            output_offset: attributes
+                                .get("ooffs")
+                                .and_then(|v| SymbolFile::ca65_number(v)),
+                        },
+                    );
+                }
+                "sym" => symbol_lines.push((line_number, attributes)),
+                _ => {}
+            }
+        }
+
+        // ld65 output offsets count from the start of the file, header and trainer included
+        let segment_location = |segment: &Ca65Segment, value: usize| match segment.output_offset {
+            Some(output_offset) => (output_offset + value)
+                .checked_sub(segment.start + prg_rom_start)
+                .and_then(|offset| prg_data.location_of_offset(offset)),
+            None => u16::try_from(value).ok().map(|address| Location {
+                bank: None,
+                address,
+            }),
+        };
+
+        let mut symbols = Vec::new();
+        for (line_number, attributes) in symbol_lines {
+            // Imports repeat symbols exported elsewhere, equates are not addresses
+            if attributes.get("type") != Some(&"lab") {
+                continue;
+            }
+            let invalid_line = InvalidSymbolFileError::InvalidLine(line_number + 1);
+            let value = attributes
+                .get("val")
+                .and_then(|v| SymbolFile::ca65_number(v))
+                .ok_or(invalid_line)?;
+            let segment = attributes
+                .get("seg")
+                .and_then(|v| SymbolFile::ca65_number(v))
+                .and_then(|id| segments.get(&id));
+            let location = match segment {
+                Some(segment) => segment_location(segment, value),
+                None => u16::try_from(value).ok().map(|address| Location {
+                    bank: None,
+                    address,
+                }),
+            };
+            if let Some(location) = location {
+                symbols.push(Symbol {
+                    location,
+                    name: attributes.get("name").map(|name| name.to_string()),
+                    comment: None,
+                });
+            }
+        }
+
+        let mut segments = segments
+            .into_values()
+            .filter(|segment| segment.output_offset.is_some())
+            .filter_map(|segment| {
+                let location = segment_location(&segment, segment.start)?;
+                Some(Segment {
+                    location,
+                    name: segment.name,
+                })
+            })
+            .collect::<Vec<Segment>>();
+        segments.sort_by_key(|segment| segment.location);
+
+        Ok(SymbolFile { symbols, segments })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prg::data::{nrom_prg, PRG_BANK_SIZE},
+        reader::{RomReader, HEADER_SIZE_BYTES},
+    };
+
+    fn prg_data(bank_count: usize) -> PrgData {
+        PrgData::parse(vec![0x60; PRG_BANK_SIZE * bank_count]).unwrap()
+    }
+
+    fn location(bank: Option<usize>, address: u16) -> Location {
+        Location { bank, address }
+    }
+
+    #[test]
+    fn parse_fceux_name_list() {
+        let text = "$8000#init#Sets up the PPU\n$0300/10#buffer#\n";
+        let result =
+            SymbolFile::parse("game.nes.2.nl", text, &prg_data(4), HEADER_SIZE_BYTES).unwrap();
+        assert_eq!(
+            result.symbols,
+            vec![
+                Symbol {
+                    location: location(Some(2), 0x8000),
+                    name: Some("init".to_string()),
+                    comment: Some("Sets up the PPU".to_string()),
+                },
+                Symbol {
+                    location: location(None, 0x0300),
+                    name: Some("buffer".to_string()),
+                    comment: None,
+                }
+            ]
+        );
+        assert_eq!(
+            SymbolFile::parse(
+                "game.nes.0.nl",
+                "C000#init#",
+                &prg_data(4),
+                HEADER_SIZE_BYTES
+            ),
+            Err(InvalidSymbolFileError::InvalidLine(1))
+        );
+    }
+
+    #[test]
+    fn parse_mesen_labels() {
+        let text = "P:4010:nmi:Frame start\\nSaves registers\nR:0010-0011:pointer:\nG:2002:PPUSTATUS:\nW:0010::Save slot\n";
+        let result = SymbolFile::parse("game.mlb", text, &prg_data(4), HEADER_SIZE_BYTES).unwrap();
+        assert_eq!(
+            result.symbols,
+            vec![
+                Symbol {
+                    location: location(Some(1), 0x8010),
+                    name: Some("nmi".to_string()),
+                    comment: Some("Frame start\nSaves registers".to_string()),
+                },
+                Symbol {
+                    location: location(None, 0x0010),
+                    name: Some("pointer".to_string()),
+                    comment: None,
+                },
+                Symbol {
+                    location: location(None, 0x2002),
+                    name: Some("PPUSTATUS".to_string()),
+                    comment: None,
+                },
+                Symbol {
+                    location: location(None, 0x6010),
+                    name: None,
+                    comment: Some("Save slot".to_string()),
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_ca65_debug_info() {
+        let text = concat!(
+            "version\tmajor=2,minor=0\n",
+            "seg\tid=0,name=\"HEADER\",start=0x000000,size=0x0010,addrsize=absolute,type=ro,oname=\"game.nes\",ooffs=0\n",
+            "seg\tid=1,name=\"CODE\",start=0x00C000,size=0x0100,addrsize=absolute,type=ro,oname=\"game.nes\",ooffs=16400\n",
+            "seg\tid=2,name=\"BSS\",start=0x000300,size=0x0100,addrsize=absolute,type=rw\n",
+            "sym\tid=0,name=\"reset\",addrsize=absolute,scope=0,def=1,val=0xC004,seg=1,type=lab\n",
+            "sym\tid=1,name=\"buffer\",addrsize=absolute,scope=0,def=2,val=0x300,seg=2,type=lab\n",
+            "sym\tid=2,name=\"SPEED\",addrsize=zeropage,scope=0,def=3,val=0x3,type=equ\n",
+        );
+        let result = SymbolFile::parse("game.dbg", text, &prg_data(2), HEADER_SIZE_BYTES).unwrap();
+        assert_eq!(
+            result.symbols,
+            vec![
+                Symbol {
+                    location: location(Some(1), 0xC004),
+                    name: Some("reset".to_string()),
+                    comment: None,
+                },
+                Symbol {
+                    location: location(None, 0x0300),
+                    name: Some("buffer".to_string()),
+                    comment: None,
+                }
+            ]
+        );
+        assert_eq!(
+            result.segments,
+            vec![Segment {
+                location: location(Some(1), 0xC000),
+                name: "CODE".to_string(),
+            }]
+        );
+
+        // A trainer comes between the header and PRG ROM
+        let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 0x02, 0x01, 0x04, 0x00];
+        rom.extend_from_slice(&[0u8; 8]);
+        rom.extend_from_slice(&[0u8; 512]);
+        rom.extend(nrom_prg(&[]));
+        rom.extend(nrom_prg(&[0x60]));
+        rom.extend_from_slice(&[0u8; 0x2000]);
+        let rom = RomReader::read(rom).unwrap();
+        let text = text.replace("ooffs=16400", "ooffs=16912");
+        let result =
+            SymbolFile::parse("game.dbg", &text, &rom.prg_data, rom.prg_rom_start()).unwrap();
+        assert_eq!(result.symbols[0].location, location(Some(1), 0xC004));
+        assert_eq!(result.segments[0].location, location(Some(1), 0xC000));
+    }
+}
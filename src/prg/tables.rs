@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+
+use super::{
+    data::{Location, PrgData},
+    instructions::{AddressingMode, Index, Instruction, Mnemonic, Operand},
+    registers::MAPPER_REGISTERS_ADDRESS,
+};
+
+const MAX_TABLE_ENTRIES: usize = 128;
+// Longest run of instructions a dispatch pattern is looked for in
+const MAX_PATTERN_LENGTH: usize = 7;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum PointerPart {
+    Word,
+    Low,
+    High,
+}
+
+impl PointerPart {
+    pub fn len(&self) -> usize {
+        match self {
+            PointerPart::Word => 2,
+            PointerPart::Low | PointerPart::High => 1,
+        }
+    }
+}
+
+/// Entry of a table that code dispatches through. Tables used with the RTS trick hold
+/// the target minus one, `adjust` is what has to be subtracted from the target.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Pointer {
+    pub part: PointerPart,
+    pub target: Location,
+    pub adjust: u16,
+}
+
+impl Pointer {
+    pub fn value(&self) -> u16 {
+        self.target.address.wrapping_sub(self.adjust)
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        let [low, high] = self.value().to_le_bytes();
+        match self.part {
+            PointerPart::Word => vec![low, high],
+            PointerPart::Low => vec![low],
+            PointerPart::High => vec![high],
+        }
+    }
+
+    pub fn directive(&self) -> &'static str {
+        match self.part {
+            PointerPart::Word => ".word",
+            PointerPart::Low | PointerPart::High => ".byte",
+        }
+    }
+
+    /// Operand of the `.word` or `.byte` directive holding the pointer.
+    pub fn to_string_with_label(self, label: Option<&str>) -> String {
+        let value = match (label, self.adjust) {
+            (Some(label), 0) => label.to_string(),
+            (Some(label), adjust) => format!("{}-{}", label, adjust),
+            (None, _) => format!("${:04X}", self.value()),
+        };
+        match self.part {
+            PointerPart::Word => value,
+            PointerPart::Low => format!("<({})", value),
+            PointerPart::High => format!(">({})", value),
+        }
+    }
+}
+
+/// Table found in the code, low and high bytes either interleaved as words or in tables of their own.
+#[derive(PartialEq, Debug)]
+struct PointerTable {
+    low: u16,
+    high: u16,
+    adjust: u16,
+}
+
+fn indexed_load(instruction: &Instruction) -> Option<(u16, Index)> {
+    match (instruction.mnemonic, instruction.operand) {
+        (Mnemonic::Lda, Some(Operand::AbsoluteIndexed { address, index })) => {
+            Some((address, index))
+        }
+        _ => None,
+    }
+}
+
+fn store(instruction: &Instruction) -> Option<u16> {
+    match instruction.mnemonic {
+        Mnemonic::Sta => instruction.operand?.address(),
+        _ => None,
+    }
+}
+
+/// `LDA high,X; PHA; LDA low,X; PHA; RTS` returns to the pushed address plus one.
+fn rts_table(run: &[&Instruction]) -> Option<PointerTable> {
+    let [load_high, push_high, load_low, push_low, rts, ..] = run else {
+        return None;
+    };
+    let (high, high_index) = indexed_load(load_high)?;
+    let (low, low_index) = indexed_load(load_low)?;
+    let pushes = [push_high, push_low]
+        .iter()
+        .all(|push| push.mnemonic == Mnemonic::Pha);
+    (pushes && high_index == low_index && rts.mnemonic == Mnemonic::Rts).then_some(PointerTable {
+        low,
+        high,
+        adjust: 1,
+    })
+}
+
+/// `LDA table,X; STA ptr; LDA table+1,X; STA ptr+1` followed shortly by `JMP (ptr)`.
+fn jmp_table(run: &[&Instruction]) -> Option<PointerTable> {
+    let [first_load, first_store, second_load, second_store, rest @ ..] = run else {
+        return None;
+    };
+    let (first, first_index) = indexed_load(first_load)?;
+    let (second, second_index) = indexed_load(second_load)?;
+    let first_store = store(first_store)?;
+    let second_store = store(second_store)?;
+    if first_index != second_index {
+        return None;
+    }
+    let pointer = rest
+        .iter()
+        .take(MAX_PATTERN_LENGTH - 4)
+        .find(|instruction| instruction.addressing_mode() == AddressingMode::Indirect)
+        .and_then(|instruction| instruction.operand?.address())?;
+    if first_store == pointer && second_store == pointer.wrapping_add(1) {
+        Some(PointerTable {
+            low: first,
+            high: second,
+            adjust: 0,
+        })
+    } else if second_store == pointer && first_store == pointer.wrapping_add(1) {
+        Some(PointerTable {
+            low: second,
+            high: first,
+            adjust: 0,
+        })
+    } else {
+        None
+    }
+}
+
+impl PointerTable {
+    /// Reads entries until one points outside of PRG ROM, at something that isn't an
+    /// instruction, or the table runs into code.
+    fn read_entries(
+        &self,
+        prg_data: &PrgData,
+        bank: usize,
+        pointers: &mut BTreeMap<Location, Pointer>,
+    ) {
+        let interleaved = self.high == self.low.wrapping_add(1);
+        let (stride, limit) = if interleaved {
+            (2, MAX_TABLE_ENTRIES)
+        } else {
+            // Split tables usually sit right next to each other
+            (
+                1,
+                (self.high.abs_diff(self.low) as usize).min(MAX_TABLE_ENTRIES),
+            )
+        };
+        for entry in 0..limit {
+            let offset = (entry * stride) as u16;
+            let low = prg_data.resolve(bank, self.low.wrapping_add(offset));
+            let high = prg_data.resolve(bank, self.high.wrapping_add(offset));
+            let (Some(low_byte), Some(high_byte)) = (prg_data.byte_at(low), prg_data.byte_at(high))
+            else {
+                break;
+            };
+            if prg_data.is_code(low) || prg_data.is_code(high) {
+                break;
+            }
+            let value = u16::from_le_bytes([low_byte, high_byte]);
+            let target = prg_data.resolve(bank, value.wrapping_add(self.adjust));
+            let valid_target = target.address >= MAPPER_REGISTERS_ADDRESS
+                && prg_data
+                    .instruction_bytes(target)
+                    .and_then(|bytes| Instruction::decode(bytes, target.address))
+                    .is_some_and(|instruction| {
+                        instruction.is_official() && instruction.mnemonic != Mnemonic::Brk
+                    });
+            if !valid_target {
+                break;
+            }
+            let pointer = |part| Pointer {
+                part,
+                target,
+                adjust: self.adjust,
+            };
+            if interleaved {
+                pointers.insert(low, pointer(PointerPart::Word));
+            } else {
+                pointers.insert(low, pointer(PointerPart::Low));
+                pointers.insert(high, pointer(PointerPart::High));
+            }
+        }
+    }
+}
+
+/// Looks for code dispatching through pointer tables and decodes their entries.
+pub fn find_pointers(prg_data: &PrgData) -> BTreeMap<Location, Pointer> {
+    let mut pointers = BTreeMap::new();
+    for bank in prg_data.banks.iter() {
+        let instructions = bank
+            .instructions
+            .iter()
+            .collect::<Vec<(&u16, &Instruction)>>();
+        for start in 0..instructions.len() {
+            // Only instructions that follow each other in memory make up a pattern
+            let mut run = vec![instructions[start].1];
+            for pair in instructions[start..]
+                .windows(2)
+                .take(MAX_PATTERN_LENGTH - 1)
+            {
+                let (address, instruction) = pair[0];
+                if (*address as usize + instruction.len()) != *pair[1].0 as usize {
+                    break;
+                }
+                run.push(pair[1].1);
+            }
+            if let Some(table) = rts_table(&run).or_else(|| jmp_table(&run)) {
+                table.read_entries(prg_data, bank.number, &mut pointers);
+            }
+        }
+    }
+    pointers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prg::data::nrom_prg;
+
+    fn prg_data(code: &[u8]) -> PrgData {
+        PrgData::parse(nrom_prg(code)).unwrap()
+    }
+
+    fn location(address: u16) -> Location {
+        Location {
+            bank: Some(0),
+            address,
+        }
+    }
+
+    #[test]
+    fn jmp_indirect_table() {
+        // ASL A; TAX; LDA $C010,X; STA $00; LDA $C011,X; STA $01; JMP ($0000)
+        let mut code = vec![
+            0x0A, 0xAA, 0xBD, 0x10, 0xC0, 0x85, 0x00, 0xBD, 0x11, 0xC0, 0x85, 0x01, 0x6C, 0x00,
+            0x00, 0x00,
+        ];
+        // .word $C014, $C015; RTS; RTS
+        code.extend_from_slice(&[0x14, 0xC0, 0x15, 0xC0, 0x60, 0x60]);
+        let prg = prg_data(&code);
+        assert_eq!(
+            prg.pointers.get(&location(0xC010)),
+            Some(&Pointer {
+                part: PointerPart::Word,
+                target: location(0xC014),
+                adjust: 0,
+            })
+        );
+        assert_eq!(prg.pointers.len(), 2);
+        let instructions = &prg.banks[0].instructions;
+        assert!(instructions.contains_key(&0xC014));
+        assert!(instructions.contains_key(&0xC015));
+        assert_eq!(prg.labels.name(&location(0xC015)), Some("loc_C015"));
+        assert_eq!(
+            prg.operand_label(0, 0xC011),
+            Some("data_C010+1".to_string())
+        );
+    }
+
+    #[test]
+    fn rts_split_table() {
+        // LDA $C00B,X; PHA; LDA $C009,X; PHA; RTS
+        let mut code = vec![0xBD, 0x0B, 0xC0, 0x48, 0xBD, 0x09, 0xC0, 0x48, 0x60];
+        // Low bytes then high bytes of $C00D-1 and $C00E-1; RTS; RTS
+        code.extend_from_slice(&[0x0C, 0x0D, 0xC0, 0xC0, 0x60, 0x60]);
+        let prg = prg_data(&code);
+        let pointer = prg.pointers[&location(0xC009)];
+        assert_eq!(pointer.part, PointerPart::Low);
+        assert_eq!(pointer.target, location(0xC00D));
+        assert_eq!(
+            pointer.to_string_with_label(Some("loc_C00D")),
+            "<(loc_C00D-1)"
+        );
+        assert_eq!(prg.pointers[&location(0xC00C)].part, PointerPart::High);
+        assert_eq!(prg.pointers.len(), 4);
+        assert!(prg.banks[0].instructions.contains_key(&0xC00E));
+    }
+}
@@ -0,0 +1,218 @@
+use std::{collections::BTreeMap, error, fmt};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidTextTableError {
+    pub line: usize,
+}
+
+impl fmt::Display for InvalidTextTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid table entry on line {}", self.line)
+    }
+}
+
+impl error::Error for InvalidTextTableError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum EntryKind {
+    Text,
+    /// Ends a string, `/XX` in Thingy tables.
+    End,
+    /// Starts a new line, `*XX` in Thingy tables.
+    Newline,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TableEntry {
+    pub kind: EntryKind,
+    pub text: String,
+}
+
+/// Character encoding of a game, read from a Thingy `.tbl` file. Entries can span more
+/// than one byte, the longest one matching is used.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TextTable {
+    entries: BTreeMap<Vec<u8>, TableEntry>,
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+impl TextTable {
+    /// Reads `XX=text` entries, `/XX` string ends and `*XX` line breaks. Table ids (`@`)
+    /// and comments (`;` or `#`) are skipped.
+    pub fn parse(text: &str) -> Result<TextTable, InvalidTextTableError> {
+        let mut entries = BTreeMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with(['@', ';', '#', '(']) {
+                continue;
+            }
+            let (kind, line) = match line.chars().next() {
+                Some('/') => (EntryKind::End, &line[1..]),
+                Some('*') => (EntryKind::Newline, &line[1..]),
+                Some('$') => (EntryKind::Text, &line[1..]),
+                _ => (EntryKind::Text, line),
+            };
+            let (hex, text) = match line.split_once('=') {
+                Some((hex, text)) => (hex, Some(text)),
+                None => (line, None),
+            };
+            let bytes = parse_hex(hex.trim()).ok_or(InvalidTextTableError { line: index + 1 })?;
+            let text = match (kind, text) {
+                (EntryKind::Text, Some(text)) => text.to_string(),
+                (EntryKind::Text, None) => return Err(InvalidTextTableError { line: index + 1 }),
+                (EntryKind::End, text) => text.unwrap_or_default().to_string(),
+                (EntryKind::Newline, text) => text.unwrap_or("\n").to_string(),
+            };
+            entries.insert(bytes, TableEntry { kind, text });
+        }
+        Ok(TextTable { entries })
+    }
+
+    /// Writes the table back in the `.tbl` format.
+    pub fn to_tbl(&self) -> String {
+        self.entries
+            .iter()
+            .map(|(bytes, entry)| {
+                let hex = bytes
+                    .iter()
+                    .map(|byte| format!("{:02X}", byte))
+                    .collect::<String>();
+                match entry.kind {
+                    EntryKind::Text => format!("{}={}\n", hex, entry.text),
+                    EntryKind::End if entry.text.is_empty() => format!("/{}\n", hex),
+                    EntryKind::End => format!("/{}={}\n", hex, entry.text),
+                    EntryKind::Newline if entry.text == "\n" => format!("*{}\n", hex),
+                    EntryKind::Newline => format!("*{}={}\n", hex, entry.text),
+                }
+            })
+            .collect()
+    }
+
+    /// Printable ASCII, for games without a table of their own.
+    pub fn ascii() -> TextTable {
+        let entries = (0x20u8..0x7F)
+            .map(|byte| {
+                let entry = TableEntry {
+                    kind: EntryKind::Text,
+                    text: (byte as char).to_string(),
+                };
+                (vec![byte], entry)
+            })
+            .collect();
+        TextTable { entries }
+    }
+
+    fn longest_entry(&self, bytes: &[u8]) -> Option<(usize, &TableEntry)> {
+        let longest = self.entries.keys().map(Vec::len).max().unwrap_or_default();
+        (1..=longest.min(bytes.len()))
+            .rev()
+            .find_map(|length| Some((length, self.entries.get(&bytes[..length])?)))
+    }
+
+    /// Decodes `bytes`, writing bytes without an entry as `[XX]`.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        let mut text = String::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            match self.longest_entry(&bytes[offset..]) {
+                Some((length, entry)) => {
+                    text.push_str(&entry.text);
+                    offset += length;
+                }
+                None => {
+                    text.push_str(&format!("[{:02X}]", bytes[offset]));
+                    offset += 1;
+                }
+            }
+        }
+        text
+    }
+
+    /// Length of the string at the start of `bytes` including its end code, if it has one.
+    pub fn string_length(&self, bytes: &[u8]) -> Option<usize> {
+        let mut offset = 0;
+        while offset < bytes.len() {
+            match self.longest_entry(&bytes[offset..]) {
+                Some((length, entry)) if entry.kind == EntryKind::End => {
+                    return Some(offset + length)
+                }
+                Some((length, _)) => offset += length,
+                None => offset += 1,
+            }
+        }
+        None
+    }
+
+    /// Encodes `text`, using the longest entries first. `[XX]` stands for a byte. Returns
+    /// the first character that has no entry when it can't be encoded.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, char> {
+        let mut bytes = Vec::new();
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            let escaped = rest
+                .strip_prefix('[')
+                .and_then(|escape| escape.get(..3))
+                .and_then(|escape| escape.strip_suffix(']'))
+                .and_then(parse_hex);
+            if let Some(escaped) = escaped {
+                bytes.extend(escaped);
+                rest = &rest[4..];
+                continue;
+            }
+            let (key, entry) = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| !entry.text.is_empty() && rest.starts_with(&entry.text))
+                .max_by_key(|(_, entry)| entry.text.len())
+                .ok_or(c)?;
+            bytes.extend(key);
+            rest = &rest[entry.text.len()..];
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = "@table\n00=A\n01=B\n1A=the\n1A1B=there\n;comment\n*FE\n/FF=<end>\n";
+
+    #[test]
+    fn decode_with_table() {
+        let table = TextTable::parse(TABLE).unwrap();
+        assert_eq!(
+            table.decode(&[0x1A, 0x1B, 0x00, 0xFE, 0x1A, 0x01, 0x42, 0xFF]),
+            "thereA\ntheB[42]<end>"
+        );
+        assert_eq!(table.string_length(&[0x00, 0x01, 0xFF, 0x00]), Some(3));
+        assert_eq!(table.string_length(&[0x00, 0x01]), None);
+        assert_eq!(
+            TextTable::parse("00=A\nZZ=B"),
+            Err(InvalidTextTableError { line: 2 })
+        );
+    }
+
+    #[test]
+    fn encode_with_table() {
+        let table = TextTable::parse(TABLE).unwrap();
+        assert_eq!(table.encode("thereAB"), Ok(vec![0x1A, 0x1B, 0x00, 0x01]));
+        assert_eq!(table.encode("the[42]"), Ok(vec![0x1A, 0x42]));
+        assert_eq!(table.encode("AC"), Err('C'));
+        assert_eq!(TextTable::ascii().encode("HI"), Ok(b"HI".to_vec()));
+        assert_eq!(TextTable::parse(&table.to_tbl()), Ok(table));
+    }
+}
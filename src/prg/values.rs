@@ -0,0 +1,352 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use super::{
+    data::{Location, PrgBank, PrgData},
+    instructions::{Index, Instruction, Mnemonic, Operand},
+};
+
+/// What the tracked values tell about an instruction.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Inferred {
+    /// Value a store writes, the bank number when it's a mapper write.
+    Value(u8),
+    /// Where a `JMP` through a pointer goes.
+    Target(Location),
+}
+
+/// Values known to be in the registers and in zero page before an instruction runs.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+struct KnownValues {
+    a: Option<u8>,
+    x: Option<u8>,
+    y: Option<u8>,
+    zero_page: BTreeMap<u8, u8>,
+}
+
+impl KnownValues {
+    /// Keeps what both agree on, for where control flow joins.
+    fn meet(&self, other: &KnownValues) -> KnownValues {
+        let same = |a: Option<u8>, b: Option<u8>| a.filter(|_| a == b);
+        KnownValues {
+            a: same(self.a, other.a),
+            x: same(self.x, other.x),
+            y: same(self.y, other.y),
+            zero_page: self
+                .zero_page
+                .iter()
+                .filter(|(address, value)| other.zero_page.get(address) == Some(value))
+                .map(|(address, value)| (*address, *value))
+                .collect(),
+        }
+    }
+
+    fn index(&self, index: Index) -> Option<u8> {
+        match index {
+            Index::X => self.x,
+            Index::Y => self.y,
+        }
+    }
+
+    /// Zero page address the operand accesses, `Err` when it's in zero page but the index
+    /// isn't known and `Ok(None)` when it's outside of zero page.
+    fn zero_page_address(&self, operand: Operand) -> Result<Option<u8>, ()> {
+        match operand {
+            Operand::ZeroPage { address } => Ok(Some(address)),
+            Operand::Absolute { address } => Ok(u8::try_from(address).ok()),
+            Operand::ZeroPageIndexed { address, index } => self
+                .index(index)
+                .map(|index| Some(address.wrapping_add(index)))
+                .ok_or(()),
+            Operand::AbsoluteIndexed { address, index } if address < 0x100 => self
+                .index(index)
+                .map(|index| u8::try_from(address + index as u16).ok())
+                .ok_or(()),
+            // Pointers could lead anywhere, zero page included
+            Operand::PreIndexedIndirect { .. } | Operand::PostIndexedIndirect { .. } => Err(()),
+            _ => Ok(None),
+        }
+    }
+
+    fn operand_value(&self, operand: Operand) -> Option<u8> {
+        match operand {
+            Operand::Immediate { value } => Some(value),
+            _ => self
+                .zero_page_address(operand)
+                .ok()
+                .flatten()
+                .and_then(|address| self.zero_page.get(&address).copied()),
+        }
+    }
+
+    fn store(&mut self, operand: Operand, value: Option<u8>) {
+        match (self.zero_page_address(operand), value) {
+            (Ok(Some(address)), Some(value)) => {
+                self.zero_page.insert(address, value);
+            }
+            (Ok(Some(address)), None) => {
+                self.zero_page.remove(&address);
+            }
+            (Ok(None), _) => {}
+            (Err(()), _) => self.zero_page.clear(),
+        }
+    }
+
+    /// Values after `instruction` runs. Anything that can't be followed becomes unknown.
+    fn step(&self, instruction: &Instruction) -> KnownValues {
+        use Mnemonic::*;
+        let mut after = self.clone();
+        let operand = instruction.operand;
+        let value = operand.and_then(|operand| self.operand_value(operand));
+        let logic = |op: fn(u8, u8) -> u8| self.a.zip(value).map(|(a, value)| op(a, value));
+        match instruction.mnemonic {
+            Lda => after.a = value,
+            Ldx => after.x = value,
+            Ldy => after.y = value,
+            Lax => {
+                after.a = value;
+                after.x = value;
+            }
+            Tax => after.x = self.a,
+            Tay => after.y = self.a,
+            Txa => after.a = self.x,
+            Tya => after.a = self.y,
+            Tsx => after.x = None,
+            Inx => after.x = self.x.map(|x| x.wrapping_add(1)),
+            Dex => after.x = self.x.map(|x| x.wrapping_sub(1)),
+            Iny => after.y = self.y.map(|y| y.wrapping_add(1)),
+            Dey => after.y = self.y.map(|y| y.wrapping_sub(1)),
+            And => after.a = logic(|a, b| a & b),
+            Ora => after.a = logic(|a, b| a | b),
+            Eor => after.a = logic(|a, b| a ^ b),
+            Asl if operand.is_none() => after.a = self.a.map(|a| a << 1),
+            Lsr if operand.is_none() => after.a = self.a.map(|a| a >> 1),
+            // Called code can change anything
+            Jsr => return KnownValues::default(),
+            Adc | Sbc | Pla | Rol | Ror | Slo | Rla | Sre | Rra | Alr | Anc | Arr | Xaa
+                if operand.is_none() || !instruction.writes_memory() =>
+            {
+                after.a = None
+            }
+            Axs | Lxa | Las => {
+                after.a = None;
+                after.x = None;
+            }
+            _ => {}
+        }
+        if let Some(operand) = operand.filter(|_| instruction.writes_memory()) {
+            let stored = match instruction.mnemonic {
+                Sta => self.a,
+                Stx => self.x,
+                Sty => self.y,
+                Sax => self.a.zip(self.x).map(|(a, x)| a & x),
+                Inc => value.map(|value| value.wrapping_add(1)),
+                Dec => value.map(|value| value.wrapping_sub(1)),
+                Asl => value.map(|value| value << 1),
+                Lsr => value.map(|value| value >> 1),
+                _ => None,
+            };
+            after.store(operand, stored);
+            // Read-modify-write combinations also change A
+            if matches!(instruction.mnemonic, Slo | Rla | Sre | Rra | Dcp | Isc) {
+                after.a = None;
+            }
+        }
+        after
+    }
+}
+
+/// Where execution continues within the bank after the instruction at `address`.
+/// Subroutines are entered with unknown values, so `JSR` only leads to the next one.
+fn successors(bank: &PrgBank, address: u16, instruction: &Instruction) -> Vec<u16> {
+    let mut successors = Vec::new();
+    if !instruction.ends_flow() {
+        successors.push(address.wrapping_add(instruction.len() as u16));
+    }
+    if instruction.mnemonic != Mnemonic::Jsr {
+        successors.extend(instruction.jump_target());
+    }
+    successors.retain(|address| bank.instructions.contains_key(address));
+    successors
+}
+
+/// Instructions that can be reached with unknown values: vectors, subroutines, pointer
+/// targets, resolved indirect jumps and code jumped to from other banks or from nowhere
+/// in its own bank.
+fn unknown_entries(prg_data: &PrgData, bank: &PrgBank) -> BTreeSet<u16> {
+    let in_bank =
+        |location: Location| (location.bank == Some(bank.number)).then_some(location.address);
+    let mut reached = BTreeSet::new();
+    for (address, instruction) in bank.instructions.iter() {
+        reached.extend(successors(bank, *address, instruction));
+    }
+    let mut entries = bank
+        .instructions
+        .keys()
+        .filter(|address| !reached.contains(address))
+        .copied()
+        .collect::<BTreeSet<u16>>();
+    entries.extend(prg_data.vector_locations().into_iter().filter_map(in_bank));
+    entries.extend(
+        prg_data
+            .pointers
+            .values()
+            .filter_map(|pointer| in_bank(pointer.target)),
+    );
+    entries.extend(
+        prg_data
+            .inferred
+            .values()
+            .filter_map(|inferred| match inferred {
+                Inferred::Target(target) => in_bank(*target),
+                Inferred::Value(_) => None,
+            }),
+    );
+    for other in prg_data.banks.iter() {
+        for instruction in other.instructions.values() {
+            let Some(target) = instruction.jump_target() else {
+                continue;
+            };
+            let location = prg_data.resolve(other.number, target);
+            if instruction.mnemonic == Mnemonic::Jsr || other.number != bank.number {
+                entries.extend(in_bank(location));
+            }
+        }
+    }
+    // Pointers can also lead to data
+    entries.retain(|address| bank.instructions.contains_key(address));
+    entries
+}
+
+/// Follows the values of A, X, Y and zero page through the code of every bank, to find
+/// what stores write and where `JMP`s through zero page pointers go.
+pub fn infer_values(prg_data: &PrgData) -> BTreeMap<Location, Inferred> {
+    let mut inferred = BTreeMap::new();
+    for bank in prg_data.banks.iter() {
+        let entries = unknown_entries(prg_data, bank);
+        let mut states: BTreeMap<u16, KnownValues> = entries
+            .iter()
+            .map(|address| (*address, KnownValues::default()))
+            .collect();
+        let mut queue = entries.into_iter().collect::<VecDeque<u16>>();
+        while let Some(address) = queue.pop_front() {
+            let instruction = &bank.instructions[&address];
+            let after = states[&address].step(instruction);
+            for successor in successors(bank, address, instruction) {
+                let state = match states.get(&successor) {
+                    Some(state) => state.meet(&after),
+                    None => after.clone(),
+                };
+                if states.get(&successor) != Some(&state) {
+                    states.insert(successor, state);
+                    queue.push_back(successor);
+                }
+            }
+        }
+
+        for (address, state) in states.iter() {
+            let instruction = &bank.instructions[address];
+            let location = Location {
+                bank: Some(bank.number),
+                address: *address,
+            };
+            let stored = match instruction.mnemonic {
+                Mnemonic::Sta => state.a,
+                Mnemonic::Stx => state.x,
+                Mnemonic::Sty => state.y,
+                _ => None,
+            };
+            if let Some(value) = stored {
+                inferred.insert(location, Inferred::Value(value));
+            }
+            if let (Mnemonic::Jmp, Some(Operand::Indirect { address: pointer })) =
+                (instruction.mnemonic, instruction.operand)
+            {
+                let Ok(low) = u8::try_from(pointer) else {
+                    continue;
+                };
+                // The high byte doesn't carry over to the next page
+                let target = state
+                    .zero_page
+                    .get(&low)
+                    .zip(state.zero_page.get(&low.wrapping_add(1)))
+                    .map(|(low, high)| u16::from_le_bytes([*low, *high]));
+                if let Some(target) = target {
+                    let target = prg_data.resolve(bank.number, target);
+                    inferred.insert(location, Inferred::Target(target));
+                }
+            }
+        }
+    }
+    inferred
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prg::data::nrom_prg;
+
+    fn location(address: u16) -> Location {
+        Location {
+            bank: Some(0),
+            address,
+        }
+    }
+
+    #[test]
+    fn track_bank_numbers_and_pointers() {
+        let code = [
+            0xA9, 0x03, // LDA #$03
+            0x8D, 0x00, 0x80, // STA $8000
+            0xA2, 0x1F, // LDX #$1F
+            0xE8, // INX
+            0x86, 0x00, // STX $00
+            0xA9, 0xC0, // LDA #$C0
+            0x85, 0x01, // STA $01
+            0x6C, 0x00, 0x00, // JMP ($0000)
+        ];
+        let mut prg = nrom_prg(&code);
+        // $C020: RTS
+        prg[0x20] = 0x60;
+        let prg_data = PrgData::parse(prg).unwrap();
+        let inferred = &prg_data.inferred;
+
+        assert_eq!(
+            inferred.get(&location(0xC002)),
+            Some(&Inferred::Value(0x03))
+        );
+        assert_eq!(
+            inferred.get(&location(0xC008)),
+            Some(&Inferred::Value(0x20))
+        );
+        assert_eq!(
+            inferred.get(&location(0xC00E)),
+            Some(&Inferred::Target(location(0xC020)))
+        );
+        // The resolved target gets disassembled and labelled
+        assert!(prg_data.banks[0].instructions.contains_key(&0xC020));
+        assert!(prg_data.labels.name(&location(0xC020)).is_some());
+    }
+
+    #[test]
+    fn forget_values_where_flow_joins() {
+        let code = [
+            0xA9, 0x01, // LDA #$01
+            0xD0, 0x02, // BNE $C006
+            0xA9, 0x02, // LDA #$02
+            0x8D, 0x00, 0x80, // STA $8000
+            0xA2, 0x05, // LDX #$05
+            0xCA, // DEX
+            0x8E, 0x00, 0x80, // STX $8000
+            0xD0, 0xFA, // BNE $C00B
+            0x20, 0x00, 0xC0, // JSR $C000
+            0x8D, 0x00, 0x80, // STA $8000
+            0x60, // RTS
+        ];
+        let prg_data = PrgData::parse(nrom_prg(&code)).unwrap();
+        let inferred = &prg_data.inferred;
+
+        assert_eq!(inferred.get(&location(0xC006)), None);
+        assert_eq!(inferred.get(&location(0xC00C)), None);
+        assert_eq!(inferred.get(&location(0xC014)), None);
+    }
+}
@@ -0,0 +1,153 @@
+use std::{collections::BTreeMap, fmt};
+
+use super::{
+    data::{Location, PrgData},
+    instructions::{Instruction, Mnemonic},
+    values::Inferred,
+};
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum ReferenceKind {
+    Call,
+    Jump,
+    Read,
+    Write,
+}
+
+impl fmt::Display for ReferenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind_string = match self {
+            ReferenceKind::Call => "call",
+            ReferenceKind::Jump => "jump",
+            ReferenceKind::Read => "read",
+            ReferenceKind::Write => "write",
+        };
+        write!(f, "{}", kind_string)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Reference {
+    pub from: Location,
+    pub kind: ReferenceKind,
+}
+
+/// Kinds of access an instruction makes to the address in its operand.
+fn reference_kinds(instruction: &Instruction) -> Vec<ReferenceKind> {
+    match instruction.jump_target() {
+        Some(_) if instruction.mnemonic == Mnemonic::Jsr => vec![ReferenceKind::Call],
+        Some(_) => vec![ReferenceKind::Jump],
+        None => [
+            (instruction.reads_memory(), ReferenceKind::Read),
+            (instruction.writes_memory(), ReferenceKind::Write),
+        ]
+        .into_iter()
+        .filter_map(|(accessed, kind)| accessed.then_some(kind))
+        .collect(),
+    }
+}
+
+/// Every place an address is referenced from, for PRG ROM, RAM and registers alike.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct CrossReferences {
+    references: BTreeMap<Location, Vec<Reference>>,
+}
+
+impl CrossReferences {
+    pub fn build(prg_data: &PrgData) -> CrossReferences {
+        let mut references: BTreeMap<Location, Vec<Reference>> = BTreeMap::new();
+        for bank in prg_data.banks.iter() {
+            for (address, instruction) in bank.instructions.iter() {
+                let Some(target) = instruction.operand.and_then(|o| o.address()) else {
+                    continue;
+                };
+                let from = Location {
+                    bank: Some(bank.number),
+                    address: *address,
+                };
+                let target = prg_data.resolve(bank.number, target);
+                for kind in reference_kinds(instruction) {
+                    references
+                        .entry(target)
+                        .or_default()
+                        .push(Reference { from, kind });
+                }
+            }
+        }
+        for (from, inferred) in prg_data.inferred.iter() {
+            if let Inferred::Target(target) = inferred {
+                references.entry(*target).or_default().push(Reference {
+                    from: *from,
+                    kind: ReferenceKind::Jump,
+                });
+            }
+        }
+        CrossReferences { references }
+    }
+
+    pub fn get(&self, location: &Location) -> &[Reference] {
+        self.references
+            .get(location)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prg::data::nrom_prg;
+
+    fn prg_data(code: &[u8]) -> PrgData {
+        PrgData::parse(nrom_prg(code)).unwrap()
+    }
+
+    fn location(bank: Option<usize>, address: u16) -> Location {
+        Location { bank, address }
+    }
+
+    #[test]
+    fn references_to_rom_and_ram() {
+        // JSR $C00E; LDA $0300; STA $2007; INC $10; BNE $C003; RTS; RTS
+        let prg = prg_data(&[
+            0x20, 0x0E, 0xC0, 0xAD, 0x00, 0x03, 0x8D, 0x07, 0x20, 0xE6, 0x10, 0xD0, 0xF6, 0x60,
+            0x60,
+        ]);
+        let xrefs = &prg.xrefs;
+        assert_eq!(
+            xrefs.get(&location(Some(0), 0xC00E)),
+            &[Reference {
+                from: location(Some(0), 0xC000),
+                kind: ReferenceKind::Call,
+            }]
+        );
+        assert_eq!(
+            xrefs.get(&location(Some(0), 0xC003)),
+            &[Reference {
+                from: location(Some(0), 0xC00B),
+                kind: ReferenceKind::Jump,
+            }]
+        );
+        assert_eq!(
+            xrefs.get(&location(None, 0x0300)),
+            &[Reference {
+                from: location(Some(0), 0xC003),
+                kind: ReferenceKind::Read,
+            }]
+        );
+        assert_eq!(
+            xrefs.get(&location(None, 0x2007)),
+            &[Reference {
+                from: location(Some(0), 0xC006),
+                kind: ReferenceKind::Write,
+            }]
+        );
+        let kinds = xrefs
+            .get(&location(None, 0x0010))
+            .iter()
+            .map(|reference| reference.kind)
+            .collect::<Vec<ReferenceKind>>();
+        assert_eq!(kinds, vec![ReferenceKind::Read, ReferenceKind::Write]);
+        assert!(xrefs.get(&location(Some(0), 0xC00D)).is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use std::{error, fmt};
+
+use js_sys::{Function, Promise};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{DomException, IdbDatabase, IdbRequest, IdbTransactionMode};
+
+const DATABASE_NAME: &str = "nes-cart-reader";
+const DATABASE_VERSION: u32 = 1;
+// Projects as JSON, keyed by ROM hash
+const PROJECTS_STORE: &str = "projects";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageError(String);
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Couldn't access the project storage: {}", self.0)
+    }
+}
+
+impl error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+impl From<JsValue> for StorageError {
+    fn from(value: JsValue) -> Self {
+        let message = value
+            .dyn_ref::<DomException>()
+            .map(|exception| exception.message())
+            .or_else(|| {
+                value
+                    .dyn_ref::<js_sys::Error>()
+                    .map(|error| error.message().into())
+            })
+            .or_else(|| value.as_string())
+            .unwrap_or_else(|| format!("{:?}", value));
+        StorageError(message)
+    }
+}
+
+/// Waits for `request` to succeed, with its result.
+async fn completion(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve: Function, reject: Function| {
+        let on_success = {
+            let request = request.clone();
+            Closure::once_into_js(move || {
+                let result = request.result().unwrap_or(JsValue::UNDEFINED);
+                let _ = resolve.call1(&JsValue::UNDEFINED, &result);
+            })
+        };
+        let on_error = {
+            let request = request.clone();
+            Closure::once_into_js(move || {
+                let error = request
+                    .error()
+                    .ok()
+                    .flatten()
+                    .map_or(JsValue::UNDEFINED, JsValue::from);
+                let _ = reject.call1(&JsValue::UNDEFINED, &error);
+            })
+        };
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise).await
+}
+
+async fn open() -> Result<IdbDatabase, JsValue> {
+    let factory = web_sys::window()
+        .ok_or_else(|| JsValue::from_str("No window"))?
+        .indexed_db()?
+        .ok_or_else(|| JsValue::from_str("IndexedDB isn't available"))?;
+    let request = factory.open_with_u32(DATABASE_NAME, DATABASE_VERSION)?;
+    let on_upgrade_needed = {
+        let request = request.clone();
+        Closure::once_into_js(move || {
+            if let Ok(database) = request
+                .result()
+                .and_then(|result| result.dyn_into::<IdbDatabase>())
+            {
+                let _ = database.create_object_store(PROJECTS_STORE);
+            }
+        })
+    };
+    request.set_onupgradeneeded(Some(on_upgrade_needed.unchecked_ref()));
+    completion(&request).await?.dyn_into::<IdbDatabase>()
+}
+
+/// Project saved for the ROM with `rom_hash`, if there is one.
+pub async fn load(rom_hash: String) -> Result<Option<String>, StorageError> {
+    let database = open().await?;
+    let transaction = database.transaction_with_str(PROJECTS_STORE)?;
+    let store = transaction.object_store(PROJECTS_STORE)?;
+    let project = completion(&store.get(&JsValue::from_str(&rom_hash))?).await?;
+    Ok(project.as_string())
+}
+
+pub async fn save(rom_hash: String, project: String) -> Result<(), StorageError> {
+    let database = open().await?;
+    let transaction =
+        database.transaction_with_str_and_mode(PROJECTS_STORE, IdbTransactionMode::Readwrite)?;
+    let store = transaction.object_store(PROJECTS_STORE)?;
+    let request =
+        store.put_with_key(&JsValue::from_str(&project), &JsValue::from_str(&rom_hash))?;
+    completion(&request).await?;
+    Ok(())
+}
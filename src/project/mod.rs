@@ -0,0 +1,270 @@
+pub mod indexed_db;
+
+use std::{error, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    export::zip::crc32,
+    prg::{
+        data::{Location, PrgData},
+        labels::InvalidLabelError,
+        regions::Region,
+        signatures::Signature,
+        structs::StructTable,
+        text_table::TextTable,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidProjectError {
+    Malformed(String),
+    OtherRom(String),
+}
+
+impl fmt::Display for InvalidProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidProjectError::Malformed(reason) => write!(f, "Invalid project file: {}", reason),
+            InvalidProjectError::OtherRom(rom_hash) => {
+                write!(f, "The project belongs to another ROM ({})", rom_hash)
+            }
+        }
+    }
+}
+
+impl error::Error for InvalidProjectError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Identifies a ROM by its PRG and CHR data, so that headers fixed up later don't matter.
+pub fn rom_hash(prg: &[u8], chr: &[u8]) -> String {
+    format!("{:08X}", crc32(&[prg, chr].concat()))
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct LabelEntry {
+    #[serde(flatten)]
+    pub location: Location,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct CommentEntry {
+    #[serde(flatten)]
+    pub location: Location,
+    pub comment: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct RegionEntry {
+    #[serde(flatten)]
+    pub location: Location,
+    #[serde(flatten)]
+    pub region: Region,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct StructEntry {
+    #[serde(flatten)]
+    pub location: Location,
+    #[serde(flatten)]
+    pub table: StructTable,
+}
+
+/// Annotations the user made to a ROM: renamed labels, comments, regions, struct tables
+/// and the text table, kept in the `.tbl` format.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Project {
+    pub rom_hash: String,
+    #[serde(default)]
+    pub labels: Vec<LabelEntry>,
+    #[serde(default)]
+    pub comments: Vec<CommentEntry>,
+    #[serde(default)]
+    pub regions: Vec<RegionEntry>,
+    #[serde(default)]
+    pub structs: Vec<StructEntry>,
+    #[serde(default)]
+    pub text_table: Option<String>,
+    /// Signatures added by the user, one per line.
+    #[serde(default)]
+    pub signatures: Option<String>,
+}
+
+impl Project {
+    pub fn new(rom_hash: String, prg_data: &PrgData) -> Project {
+        Project {
+            rom_hash,
+            labels: prg_data
+                .labels
+                .renamed()
+                .map(|(location, label)| LabelEntry {
+                    location: *location,
+                    name: label.name.clone(),
+                })
+                .collect(),
+            comments: prg_data
+                .comments
+                .iter()
+                .map(|(location, comment)| CommentEntry {
+                    location: *location,
+                    comment: comment.clone(),
+                })
+                .collect(),
+            regions: prg_data
+                .regions
+                .iter()
+                .map(|(location, region)| RegionEntry {
+                    location: *location,
+                    region: *region,
+                })
+                .collect(),
+            structs: prg_data
+                .structs
+                .iter()
+                .map(|(location, table)| StructEntry {
+                    location: *location,
+                    table: table.clone(),
+                })
+                .collect(),
+            text_table: prg_data.text_table.as_ref().map(TextTable::to_tbl),
+            signatures: (!prg_data.signatures.is_empty()).then(|| {
+                prg_data
+                    .signatures
+                    .iter()
+                    .map(|signature| format!("{}\n", signature))
+                    .collect()
+            }),
+        }
+    }
+
+    /// Reads a project, checking that it was made for the ROM identified by `rom_hash`.
+    pub fn from_json(json: &str, rom_hash: &str) -> Result<Project, InvalidProjectError> {
+        let project: Project = serde_json::from_str(json)
+            .map_err(|error| InvalidProjectError::Malformed(error.to_string()))?;
+        if project.rom_hash != rom_hash {
+            return Err(InvalidProjectError::OtherRom(project.rom_hash));
+        }
+        if let Some(Err(error)) = project.text_table.as_deref().map(TextTable::parse) {
+            return Err(InvalidProjectError::Malformed(error.to_string()));
+        }
+        if let Some(Err(error)) = project.signatures.as_deref().map(Signature::parse_all) {
+            return Err(InvalidProjectError::Malformed(error.to_string()));
+        }
+        Ok(project)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Applies the annotations to `prg_data`. Labels that can't be used are skipped and
+    /// reported back.
+    pub fn apply(&self, prg_data: &mut PrgData) -> Vec<InvalidLabelError> {
+        if let Some(Ok(table)) = self.text_table.as_deref().map(TextTable::parse) {
+            prg_data.text_table = Some(table);
+        }
+        if let Some(Ok(signatures)) = self.signatures.as_deref().map(Signature::parse_all) {
+            prg_data.set_signatures(signatures);
+        }
+        prg_data.import_regions(
+            self.regions
+                .iter()
+                .map(|entry| (entry.location, entry.region)),
+        );
+        prg_data.import_struct_tables(
+            self.structs
+                .iter()
+                .map(|entry| (entry.location, entry.table.clone())),
+        );
+        for entry in self.comments.iter() {
+            prg_data
+                .comments
+                .insert(entry.location, entry.comment.clone());
+        }
+        self.labels
+            .iter()
+            .filter_map(|entry| {
+                prg_data
+                    .labels
+                    .rename(entry.location, entry.name.clone())
+                    .err()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prg::{data::nrom_prg, regions::RegionKind, structs::StructTable};
+
+    fn prg_data() -> PrgData {
+        // JSR $C004; RTS; RTS
+        PrgData::parse(nrom_prg(&[0x20, 0x04, 0xC0, 0x60, 0x60])).unwrap()
+    }
+
+    fn location(address: u16) -> Location {
+        Location {
+            bank: Some(0),
+            address,
+        }
+    }
+
+    #[test]
+    fn project_round_trip() {
+        let mut annotated = prg_data();
+        annotated
+            .labels
+            .rename(location(0xC000), "reset".to_string())
+            .unwrap();
+        annotated
+            .comments
+            .insert(location(0xC004), "Does nothing".to_string());
+        annotated.set_region(location(0xC010), 4, Some(RegionKind::Text));
+        annotated.set_struct_table(
+            location(0xC020),
+            StructTable::parse("Enemy[2] kind:enum(walker|flyer) ai:ptr", 0).unwrap(),
+        );
+        annotated.text_table = Some(TextTable::parse("00=A\n0A=ab\n*FE\n/FF=<end>").unwrap());
+        annotated.set_signatures(Signature::parse_all("play = 20 ?? C0 ; Music").unwrap());
+        let project = Project::new("1234ABCD".to_string(), &annotated);
+        assert_eq!(
+            project.labels,
+            vec![LabelEntry {
+                location: location(0xC000),
+                name: "reset".to_string()
+            }]
+        );
+
+        let json = project.to_json();
+        assert!(json.contains("\"kind\": \"text\""));
+        let mut restored = prg_data();
+        let project = Project::from_json(&json, "1234ABCD").unwrap();
+        assert!(project.apply(&mut restored).is_empty());
+        assert_eq!(restored.labels.name(&location(0xC000)), Some("reset"));
+        assert_eq!(restored.labels.name(&location(0xC004)), Some("sub_C004"));
+        assert_eq!(restored.comments, annotated.comments);
+        assert_eq!(restored.regions, annotated.regions);
+        assert_eq!(restored.structs, annotated.structs);
+        assert_eq!(restored.text_table, annotated.text_table);
+        assert_eq!(restored.signatures, annotated.signatures);
+    }
+
+    #[test]
+    fn reject_invalid_projects() {
+        let json = Project::new("1234ABCD".to_string(), &prg_data()).to_json();
+        assert_eq!(
+            Project::from_json(&json, "00000000"),
+            Err(InvalidProjectError::OtherRom("1234ABCD".to_string()))
+        );
+        assert!(matches!(
+            Project::from_json("{\"labels\": []}", "1234ABCD"),
+            Err(InvalidProjectError::Malformed(_))
+        ));
+        assert_eq!(rom_hash(&[], &[]), "00000000");
+    }
+}
@@ -1,9 +1,11 @@
 use std::{error, fmt};
 
 use super::{
+    cdl::CodeDataLog,
     chr::data::{ChrData, InvalidChrDataError},
     header::data::{HeaderData, InvalidHeaderError},
     prg::data::{InvalidPrgDataError, PrgData},
+    project::rom_hash,
 };
 
 #[derive(Debug, PartialEq)]
@@ -11,6 +13,32 @@ pub struct RomReaderResult {
     pub header: HeaderData,
     pub chr_data: ChrData,
     pub prg_data: PrgData,
+    pub header_bytes: [u8; HEADER_SIZE_BYTES],
+    pub trainer: Option<Vec<u8>>,
+    pub misc_rom: Vec<u8>,
+    pub code_data_log: Option<CodeDataLog>,
+    /// The file as it was read, before any patch.
+    pub original_bytes: Vec<u8>,
+    pub rom_hash: String,
+}
+
+impl RomReaderResult {
+    /// The ROM file with the PRG and CHR data as they are now.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header_bytes.to_vec();
+        if let Some(trainer) = self.trainer.as_ref() {
+            bytes.extend_from_slice(trainer);
+        }
+        bytes.extend(self.prg_data.to_bytes());
+        bytes.extend(self.chr_data.to_bytes());
+        bytes.extend_from_slice(&self.misc_rom);
+        bytes
+    }
+
+    /// Offset of PRG ROM in the file, after the header and the trainer.
+    pub fn prg_rom_start(&self) -> usize {
+        HEADER_SIZE_BYTES + self.trainer.as_ref().map_or(0, Vec::len)
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -58,11 +86,13 @@ impl From<InvalidPrgDataError> for RomReaderError {
     }
 }
 
+pub const HEADER_SIZE_BYTES: usize = 16;
+const TRAINER_SIZE_BYTES: usize = 512;
+
 pub struct RomReader;
 
 impl RomReader {
     pub fn read(data: Vec<u8>) -> Result<RomReaderResult, RomReaderError> {
-        const HEADER_SIZE_BYTES: usize = 16;
         let header_bytes: &[u8; HEADER_SIZE_BYTES] = &data[0..HEADER_SIZE_BYTES]
             .try_into()
             .expect("Slice with incorrect lenght!");
@@ -71,10 +101,18 @@ impl RomReader {
             return Err(RomReaderError::from(e));
         }
         let header = header_parse_result.unwrap();
-        let prg_rom_start = HEADER_SIZE_BYTES + (if header.trainer_present { 512 } else { 0 });
+        let trainer = header
+            .trainer_present
+            .then(|| data[HEADER_SIZE_BYTES..HEADER_SIZE_BYTES + TRAINER_SIZE_BYTES].to_vec());
+        let prg_rom_start = HEADER_SIZE_BYTES
+            + (if header.trainer_present {
+                TRAINER_SIZE_BYTES
+            } else {
+                0
+            });
         let prg_rom_bytes =
             data[prg_rom_start..prg_rom_start + header.prg_rom_size as usize].to_vec();
-        let prg_data_parse_result = PrgData::parse(prg_rom_bytes);
+        let prg_data_parse_result = PrgData::parse(prg_rom_bytes.clone());
         if let Err(e) = prg_data_parse_result {
             return Err(RomReaderError::from(e));
         }
@@ -82,16 +120,24 @@ impl RomReader {
         let chr_rom_start = prg_rom_start + header.prg_rom_size as usize;
         let chr_rom_bytes =
             data[chr_rom_start..chr_rom_start + header.chr_rom_size as usize].to_vec();
+        let rom_hash = rom_hash(&prg_rom_bytes, &chr_rom_bytes);
         let chr_data_parse_result = ChrData::parse(chr_rom_bytes);
         if let Err(e) = chr_data_parse_result {
             return Err(RomReaderError::from(e));
         }
         let chr_data = chr_data_parse_result.unwrap();
+        let misc_rom = data[chr_rom_start + header.chr_rom_size as usize..].to_vec();
 
         Ok(RomReaderResult {
             header,
             chr_data,
             prg_data,
+            header_bytes: *header_bytes,
+            trainer,
+            misc_rom,
+            code_data_log: None,
+            original_bytes: data,
+            rom_hash,
         })
     }
 }
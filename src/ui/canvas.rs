@@ -9,6 +9,9 @@ pub struct CanvasProps {
     pub width: u32,
     pub height: u32,
     pub class: Classes,
+
+    #[prop_or_default]
+    pub onclick: Callback<MouseEvent>,
 }
 
 #[function_component(Canvas)]
@@ -18,7 +21,8 @@ pub fn canvas(props: &CanvasProps) -> Html {
             id={props.id.clone()}
             width={props.width.to_string()}
             height={props.height.to_string()}
-            class={props.class.clone()}>
+            class={props.class.clone()}
+            onclick={props.onclick.clone()}>
         </canvas>
     }
 }
@@ -0,0 +1,24 @@
+use gloo::{
+    file::{Blob, ObjectUrl},
+    timers::callback::Timeout,
+};
+use wasm_bindgen::JsCast;
+use web_sys::HtmlAnchorElement;
+
+/// Hands `bytes` over to the browser as a file download.
+pub fn download(file_name: &str, mime_type: &str, bytes: &[u8]) {
+    let url = ObjectUrl::from(Blob::new_with_options(bytes, Some(mime_type)));
+    let anchor = web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .create_element("a")
+        .unwrap()
+        .dyn_into::<HtmlAnchorElement>()
+        .unwrap();
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    // Revoking the URL right away could cancel the download
+    Timeout::new(0, move || drop(url)).forget();
+}
@@ -27,8 +27,15 @@ pub fn file_input(props: &FileInputProps) -> Html {
     let on_change_clone = props.on_change.clone();
     let onchange = Callback::from(move |e: Event| {
         let input: HtmlInputElement = e.target_unchecked_into();
-        let file = input.files().unwrap().get(0).map(File::from).unwrap();
-        let file_name = file.name();
+        let files: Vec<File> = gloo::file::FileList::from(input.files().unwrap())
+            .iter()
+            .cloned()
+            .collect();
+        let file_name = files
+            .iter()
+            .map(|file| file.name())
+            .collect::<Vec<String>>()
+            .join(", ");
         let file_name_input = web_sys::window()
             .unwrap()
             .document()
@@ -37,7 +44,9 @@ pub fn file_input(props: &FileInputProps) -> Html {
             .unwrap()
             .unchecked_into::<HtmlInputElement>();
         file_name_input.set_value(&file_name);
-        on_change_clone.emit(file);
+        for file in files {
+            on_change_clone.emit(file);
+        }
     });
 
     let id_clone = props.id.clone();
@@ -81,30 +90,34 @@ pub fn file_input(props: &FileInputProps) -> Html {
 }
 
 #[derive(Properties, PartialEq)]
-pub struct ColorInputProps {
+pub struct TextInputProps {
     #[prop_or(classes!())]
     pub class: Classes,
 
-    #[prop_or("#000000".to_owned())]
+    #[prop_or_default]
     pub value: String,
 
-    #[prop_or(Callback::from(move |_color: String| {}))]
+    #[prop_or_default]
+    pub placeholder: String,
+
+    #[prop_or(Callback::from(move |_text: String| {}))]
     pub on_change: Callback<String>,
 }
 
-#[function_component(ColorInput)]
-pub fn color_input(props: &ColorInputProps) -> Html {
+#[function_component(TextInput)]
+pub fn text_input(props: &TextInputProps) -> Html {
     let on_change_clone = props.on_change.clone();
     let onchange = Callback::from(move |e: Event| {
-        let new_color = e.target_dyn_into::<HtmlInputElement>().unwrap().value();
-        on_change_clone.emit(new_color);
+        let new_text = e.target_dyn_into::<HtmlInputElement>().unwrap().value();
+        on_change_clone.emit(new_text);
     });
 
     html! {
         <input
-            type="color"
-            class={props.class.clone()}
+            type="text"
+            class={classes!("input", "input-bordered", props.class.clone())}
             value={props.value.clone()}
+            placeholder={props.placeholder.clone()}
             {onchange}/>
     }
 }
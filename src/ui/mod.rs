@@ -2,6 +2,8 @@ pub mod r#box;
 pub mod button;
 pub mod canvas;
 pub mod dialog;
+pub mod download;
 pub mod input;
 pub mod navbar;
 pub mod pagination;
+pub mod select;
@@ -37,11 +37,23 @@ pub struct PaginationProps {
 
     #[prop_or(Callback::from(move |_:usize| {}))]
     pub on_change: Callback<usize>,
+
+    /// Page to show when it's changed from outside of the pagination.
+    #[prop_or_default]
+    pub page: Option<usize>,
 }
 
 #[function_component(Pagination)]
 pub fn pagination(props: &PaginationProps) -> Html {
     let current_page_state = use_state(|| 0usize);
+    {
+        let current_page_state = current_page_state.clone();
+        use_effect_with(props.page, move |page| {
+            if let Some(page) = page {
+                current_page_state.set(*page);
+            }
+        });
+    }
 
     let increment = {
         let current_page_state = current_page_state.clone();
@@ -0,0 +1,46 @@
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct SelectProps {
+    #[prop_or(classes!())]
+    pub class: Classes,
+
+    pub options: Vec<String>,
+
+    #[prop_or(0usize)]
+    pub selected: usize,
+
+    #[prop_or(Callback::from(move |_index: usize| {}))]
+    pub on_change: Callback<usize>,
+}
+
+#[function_component(Select)]
+pub fn select(props: &SelectProps) -> Html {
+    let on_change_clone = props.on_change.clone();
+    let onchange = Callback::from(move |e: Event| {
+        let index = e
+            .target_dyn_into::<HtmlSelectElement>()
+            .unwrap()
+            .selected_index();
+        if index >= 0 {
+            on_change_clone.emit(index as usize);
+        }
+    });
+
+    html! {
+        <select class={classes!("select", "select-bordered", props.class.clone())} {onchange}>
+            {
+                props.options
+                    .iter()
+                    .enumerate()
+                    .map(|(index, option)| {
+                        html! {
+                            <option selected={index == props.selected}>{option}</option>
+                        }
+                    })
+                    .collect::<Html>()
+            }
+        </select>
+    }
+}